@@ -1,7 +1,16 @@
 use bevy::ecs::resource::Resource;
 use bevy::prelude::States;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource, Clone)]
+use crate::detection::{CameraMapping, DetectionConfig};
+use crate::frame::FrameSourceConfig;
+use crate::homography::Homography;
+use crate::lens::{CameraOptics, LensIntrinsics};
+use crate::layout::Frustum;
+use crate::projector::Keystone;
+use crate::radiometry::RadiometricCalibration;
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SceneConfiguration {
     /// Defines the distance of a target detection plane in modeled physical world in meters.
     pub target_projection_distance: f32,
@@ -25,6 +34,10 @@ pub struct ProjectorConfiguration {
     pub angle: f32,
     pub transform: bevy::prelude::Transform,
     pub enabled: bool,
+    /// Mapping of the output pixels to scene-local meters, measured by the projector calibration.
+    pub mapping: Option<Homography>,
+    /// Correction drawing the picture into a quad of the output, for a projector not square to the wall.
+    pub keystone: Keystone,
 }
 
 impl Default for ProjectorConfiguration {
@@ -35,8 +48,26 @@ impl Default for ProjectorConfiguration {
             transform: bevy::prelude::Transform::from_translation(bevy::prelude::Vec3::new(0.0, 1.5, 5.0))
                 .looking_at(bevy::prelude::Vec3::new(0.0, 1.5, 0.0), bevy::prelude::Vec3::Y),
             enabled: false,
+            mapping: None,
+            keystone: Keystone::default(),
+        }
+    }
+}
+
+impl ProjectorConfiguration {
+    /// The projector thrown from its pose with its angle
+    pub fn frustum(&self) -> Frustum {
+        Frustum {
+            transform: self.transform,
+            angle: self.angle,
+            resolution: self.output_resolution,
         }
     }
+
+    /// Mapping of the output pixels to scene-local meters, as calibrated or else as given by the pose
+    pub fn scene_mapping(&self, scene: &bevy::prelude::Transform) -> Option<Homography> {
+        self.mapping.or_else(|| self.frustum().homography(scene))
+    }
 }
 
 #[derive(Resource, Clone)]
@@ -45,6 +76,20 @@ pub struct CameraConfiguration {
     pub input_resolution: bevy::prelude::UVec2,
     /// Defines the camera's position and orientation in world space.
     pub transform: bevy::prelude::Transform,
+    /// Where thermal frames are read from.
+    pub frame_source: FrameSourceConfig,
+    /// How laser spots are told apart from the background.
+    pub detection: DetectionConfig,
+    /// Conversion of the raw sensor counts to temperatures.
+    pub radiometry: RadiometricCalibration,
+    /// Intrinsics and distortion of the camera lens, detected spots are undistorted with them.
+    pub lens: Option<LensIntrinsics>,
+    /// Focal length and sensor size of the camera, the scene width and its field of view follow each other when set.
+    pub optics: Option<CameraOptics>,
+    /// Part of the scene the camera image covers, the whole scene width when none.
+    pub footprint: Option<CameraFootprint>,
+    /// Mapping of the undistorted pixels to the scene measured on reference points, replaces the footprint.
+    pub homography: Option<Homography>,
 }
 
 impl Default for CameraConfiguration {
@@ -53,6 +98,56 @@ impl Default for CameraConfiguration {
             input_resolution: bevy::prelude::UVec2::new(256, 192),
             transform: bevy::prelude::Transform::from_translation(bevy::prelude::Vec3::new(0.0, 1.5, 5.0))
                 .looking_at(bevy::prelude::Vec3::new(0.0, 1.5, 0.0), bevy::prelude::Vec3::Y),
+            frame_source: FrameSourceConfig::default(),
+            detection: DetectionConfig::default(),
+            radiometry: RadiometricCalibration::default(),
+            lens: None,
+            optics: None,
+            footprint: None,
+            homography: None,
         }
     }
-}
\ No newline at end of file
+}
+
+impl CameraConfiguration {
+    /// The camera as a frustum aimed at the middle of its footprint on the scene, seeing it with its
+    /// optics or else just across its width
+    pub fn frustum(&self, mapping: &CameraMapping, scene: &bevy::prelude::Transform) -> Frustum {
+        let footprint = mapping.footprint();
+        let position = self.transform.translation;
+        let aim = scene.transform_point(footprint.center().extend(0.0));
+        let angle = match self.optics {
+            Some(optics) => optics.fov().x,
+            None => 2.0 * (footprint.width() / 2.0 / position.distance(aim)).atan().to_degrees(),
+        };
+        Frustum {
+            transform: bevy::prelude::Transform::from_translation(position).looking_at(aim, bevy::prelude::Vec3::Y),
+            angle,
+            resolution: self.input_resolution,
+        }
+    }
+
+    /// Mapping of the camera input pixels to the scene-local meters of a scene of the given width
+    pub fn mapping(&self, scene_width: f32) -> CameraMapping {
+        let footprint = self.footprint.unwrap_or(CameraFootprint { center: [0.0; 2], width: scene_width });
+        CameraMapping {
+            center: footprint.center.into(),
+            ..CameraMapping::for_scene(self.input_resolution, footprint.width)
+        }
+        .with_lens(self.lens)
+        .with_homography(self.homography)
+    }
+}
+
+/// Area of the scene seen by one of several cameras, in scene-local meters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraFootprint {
+    /// Scene-local position of the center of the camera image
+    pub center: [f32; 2],
+    /// Width of the scene covered by the camera image, its height follows from the aspect ratio
+    pub width: f32,
+}
+
+/// Thermal cameras watching parts of the scene next to the main camera, for walls wider than one sensor covers
+#[derive(Resource, Clone, Default)]
+pub struct AdditionalCameras(pub Vec<CameraConfiguration>);
\ No newline at end of file
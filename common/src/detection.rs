@@ -0,0 +1,403 @@
+use bevy::prelude::{IRect, Rect, UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::frame::ThermalFrame;
+use crate::homography::Homography;
+use crate::lens::LensIntrinsics;
+use crate::masks::PixelMask;
+use crate::radiometry::{DriftCompensator, DriftConfig, RadiometricCalibration};
+use crate::tracking::TrackingConfig;
+
+/// Pixels that belong to a blob adapt to the background this many times slower than the others
+const FOREGROUND_SLOWDOWN: f32 = 10.0;
+
+/// Tuning of the laser spot detector, temperatures are in kelvin above the rolling background
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionConfig {
+    /// Weight of each new frame in the rolling background, between 0 and 1
+    pub background_rate: f32,
+    /// Minimum rise above the background for a pixel to be part of a blob
+    pub threshold: f32,
+    /// Minimum rise above the background of the hottest pixel of a blob
+    pub min_intensity: f32,
+    /// Smallest blob accepted as a shot, in pixels
+    pub min_pixels: u32,
+    /// Largest blob accepted as a shot, in pixels, bigger ones are warm bodies or reflections
+    pub max_pixels: u32,
+    /// Largest ratio between the long and short side of a blob, laser spots are round
+    pub max_elongation: f32,
+    /// Longest time a warm area may take to get as hot as a laser spot, slower ones are heaters or people, in seconds
+    pub max_rise_secs: f32,
+    /// Distance around rejected warm areas in which spots are rejected too, in pixels
+    pub warm_region_margin: u32,
+    /// Time a rejected warm area keeps rejecting spots after it vanished, in seconds
+    pub warm_region_hold_secs: f32,
+    /// Compensation of the sensor drift and shutter events before comparing with the background
+    pub drift: DriftConfig,
+    /// How hit spots are followed across frames while they cool down
+    pub tracking: TrackingConfig,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            background_rate: 0.05,
+            threshold: 2.5,
+            min_intensity: 5.0,
+            min_pixels: 2,
+            max_pixels: 400,
+            max_elongation: 3.0,
+            max_rise_secs: 0.25,
+            warm_region_margin: 3,
+            warm_region_hold_secs: 2.0,
+            drift: DriftConfig::default(),
+            tracking: TrackingConfig::default(),
+        }
+    }
+}
+
+/// A connected group of pixels warmer than the background
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blob {
+    /// Intensity weighted centroid in camera input pixels, pixel centers sit at half coordinates
+    pub centroid: Vec2,
+    pub pixels: u32,
+    /// Width and height of the bounding box, in pixels
+    pub size: UVec2,
+    /// Rise above the background of the hottest pixel, in kelvin
+    pub peak: f32,
+}
+
+impl Blob {
+    /// Ratio between the long and short side of the bounding box
+    pub fn elongation(&self) -> f32 {
+        self.size.max_element() as f32 / self.size.min_element().max(1) as f32
+    }
+
+    fn passes(&self, config: &DetectionConfig) -> bool {
+        (config.min_pixels..=config.max_pixels).contains(&self.pixels)
+            && self.peak >= config.min_intensity
+            && self.elongation() <= config.max_elongation
+    }
+}
+
+/// What a warm area followed across frames turned out to be, later kinds win when areas merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RegionKind {
+    /// Not hot enough to be a laser spot yet
+    Rising,
+    Spot,
+    /// A person, a heater or a reflection, spots in and around it are ignored
+    Rejected,
+}
+
+/// A warm area followed across frames, to tell laser spots from bodies and heaters
+#[derive(Debug, Clone, Copy)]
+struct WarmRegion {
+    /// Bounding box in pixels, both corners included
+    area: IRect,
+    /// Centroid of the area when it was first seen
+    origin: Vec2,
+    first_seen: Duration,
+    last_seen: Duration,
+    kind: RegionKind,
+}
+
+/// Finds laser heat spots in consecutive thermal frames
+pub struct SpotDetector {
+    pub config: DetectionConfig,
+    /// Conversion of the sensor counts of the frames to temperatures
+    pub calibration: RadiometricCalibration,
+    /// Pixels where spots are never shots
+    pub mask: Option<PixelMask>,
+    resolution: UVec2,
+    /// Rolling background in drift compensated counts
+    background: Vec<f32>,
+    drift: DriftCompensator,
+    regions: Vec<WarmRegion>,
+}
+
+impl SpotDetector {
+    pub fn new(config: DetectionConfig) -> Self {
+        Self {
+            config,
+            calibration: RadiometricCalibration::default(),
+            mask: None,
+            resolution: UVec2::ZERO,
+            background: Vec::new(),
+            drift: DriftCompensator::new(config.drift),
+            regions: Vec::new(),
+        }
+    }
+
+    /// Forget the background, the next frame starts a new one
+    pub fn reset(&mut self) {
+        self.background.clear();
+        self.drift.reset();
+        self.regions.clear();
+    }
+
+    /// Blobs of the frame that pass the size and intensity filters and do not belong to a rejected warm area
+    ///
+    /// The first frame, the first one after a change of resolution and frames following a shutter
+    /// event only seed the background. Masked pixels never belong to a blob.
+    pub fn process(&mut self, frame: &ThermalFrame) -> Vec<Blob> {
+        let reseed = self.background.len() != frame.pixels.len() || self.resolution != frame.resolution;
+        if reseed {
+            self.drift.reset();
+        }
+        self.drift.config = self.config.drift;
+        let correction = self.drift.update(frame, &self.calibration);
+        let pixels: Vec<f32> = frame.pixels.iter().map(|&pixel| pixel as f32 + correction.offset).collect();
+
+        if reseed || correction.shutter {
+            // A flat-field correction also changes the fixed pattern of each pixel
+            self.resolution = frame.resolution;
+            self.background = pixels;
+            return Vec::new();
+        }
+
+        let difference: Vec<f32> = pixels
+            .iter()
+            .zip(&self.background)
+            .map(|(&pixel, &background)| (pixel - background) * self.calibration.gain)
+            .collect();
+        let mask = self.mask.as_ref().filter(|mask| mask.resolution() == frame.resolution);
+        let foreground: Vec<bool> = difference
+            .iter()
+            .enumerate()
+            .map(|(index, &rise)| rise >= self.config.threshold && !mask.is_some_and(|mask| mask.excludes(index)))
+            .collect();
+
+        let blobs = self.find_blobs(&difference, &foreground);
+        self.update_background(&pixels, &foreground);
+        self.classify(blobs, frame.timestamp)
+    }
+
+    /// Group foreground pixels into 8-connected blobs, with their bounding box
+    ///
+    /// Blobs that did not warm up overall have no centroid and are left out, which only happens when
+    /// the threshold lets cooling pixels into the foreground.
+    fn find_blobs(&self, difference: &[f32], foreground: &[bool]) -> Vec<(Blob, IRect)> {
+        connected_components(foreground, self.resolution)
+            .into_iter()
+            .filter_map(|component| {
+                let mut weight = 0.0;
+                let mut weighted_position = Vec2::ZERO;
+                let mut peak = f32::MIN;
+                let mut min = UVec2::MAX;
+                let mut max = UVec2::ZERO;
+                for &index in &component {
+                    let pixel = UVec2::new(index as u32 % self.resolution.x, index as u32 / self.resolution.x);
+                    let rise = difference[index];
+                    weight += rise;
+                    weighted_position += (pixel.as_vec2() + 0.5) * rise;
+                    peak = peak.max(rise);
+                    min = min.min(pixel);
+                    max = max.max(pixel);
+                }
+                if weight <= 0.0 {
+                    return None;
+                }
+
+                let blob = Blob {
+                    centroid: weighted_position / weight,
+                    pixels: component.len() as u32,
+                    size: max - min + UVec2::ONE,
+                    peak,
+                };
+                Some((blob, IRect::from_corners(min.as_ivec2(), max.as_ivec2())))
+            })
+            .collect()
+    }
+
+    /// Follow the warm areas of the frame and keep the blobs that look like laser spots
+    ///
+    /// Laser spots appear at once and stay in place. Areas that are too big or too elongated, that take
+    /// longer than `max_rise_secs` to get hot or that move are rejected, along with any blob within
+    /// `warm_region_margin` of them until `warm_region_hold_secs` after they vanished.
+    fn classify(&mut self, blobs: Vec<(Blob, IRect)>, time: Duration) -> Vec<Blob> {
+        let margin = self.config.warm_region_margin as i32;
+        let max_rise = Duration::from_secs_f32(self.config.max_rise_secs.max(0.0));
+        let hold = Duration::from_secs_f32(self.config.warm_region_hold_secs.max(0.0));
+        let mut matched = vec![false; self.regions.len()];
+        let mut regions = Vec::with_capacity(blobs.len());
+        let mut spots = Vec::new();
+
+        for (blob, area) in blobs {
+            let mut kind = None;
+            let mut first: Option<&WarmRegion> = None;
+            for (index, region) in self.regions.iter().enumerate() {
+                if !overlaps(region.area.inflate(margin), area) {
+                    continue;
+                }
+                matched[index] = true;
+                kind = kind.max(Some(region.kind));
+                if first.is_none_or(|first| region.first_seen < first.first_seen) {
+                    first = Some(region);
+                }
+            }
+            let first_seen = first.map_or(time, |region| region.first_seen);
+            let origin = first.map_or(blob.centroid, |region| region.origin);
+
+            let warm_body = blob.pixels > self.config.max_pixels || blob.elongation() > self.config.max_elongation;
+            let moved = blob.centroid.distance(origin) > margin as f32;
+            let kind = match kind {
+                Some(RegionKind::Rejected) => RegionKind::Rejected,
+                _ if warm_body || moved => RegionKind::Rejected,
+                Some(RegionKind::Spot) => RegionKind::Spot,
+                _ if !blob.passes(&self.config) => RegionKind::Rising,
+                _ if time.saturating_sub(first_seen) <= max_rise => RegionKind::Spot,
+                _ => RegionKind::Rejected,
+            };
+
+            if kind == RegionKind::Spot && blob.passes(&self.config) {
+                spots.push(blob);
+            }
+            regions.push(WarmRegion { area, origin, first_seen, last_seen: time, kind });
+        }
+
+        // Rejected areas keep rejecting spots for a while, a person may step back in front of the wall
+        let held = self.regions.iter().zip(&matched).filter(|(region, matched)| {
+            !**matched && region.kind == RegionKind::Rejected && time.saturating_sub(region.last_seen) < hold
+        });
+        regions.extend(held.map(|(region, _)| *region));
+        self.regions = regions;
+
+        spots
+    }
+
+    fn update_background(&mut self, pixels: &[f32], foreground: &[bool]) {
+        let rate = self.config.background_rate.clamp(0.0, 1.0);
+        for ((background, &pixel), &is_foreground) in self.background.iter_mut().zip(pixels).zip(foreground) {
+            // Keep a visible hit from melting into the background while it is still warm
+            let rate = if is_foreground { rate / FOREGROUND_SLOWDOWN } else { rate };
+            *background += (pixel - *background) * rate;
+        }
+    }
+}
+
+/// Indices of the pixels of each 8-connected group of set pixels
+pub(crate) fn connected_components(set: &[bool], resolution: UVec2) -> Vec<Vec<usize>> {
+    let width = resolution.x as i32;
+    let height = resolution.y as i32;
+    let mut visited = vec![false; set.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+
+    for start in 0..set.len() {
+        if !set[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+
+        let mut component = Vec::new();
+        while let Some(index) = stack.pop() {
+            component.push(index);
+            let x = index as i32 % width;
+            let y = index as i32 / width;
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let neighbour = (ny * width + nx) as usize;
+                if set[neighbour] && !visited[neighbour] {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        components.push(component);
+    }
+
+    components
+}
+
+/// Whether two pixel boxes with included corners share a pixel
+fn overlaps(a: IRect, b: IRect) -> bool {
+    a.min.cmple(b.max).all() && b.min.cmple(a.max).all()
+}
+
+/// Maps camera input pixels to scene-local meters
+///
+/// Scene-local coordinates have their origin at the center of the scene with y pointing up, while
+/// pixel rows go down. The camera image is centered on `center`, the center of the scene unless
+/// several cameras share it. With lens intrinsics, pixels are undistorted before they are scaled to
+/// the scene. A calibrated homography replaces the scaling for a camera that sees the scene off-axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CameraMapping {
+    pub resolution: UVec2,
+    /// Scene width covered by one camera input pixel
+    pub units_per_pixel: f32,
+    /// Scene-local position of the center of the camera image
+    pub center: Vec2,
+    /// Distortion of the camera lens, none for an ideal lens
+    pub lens: Option<LensIntrinsics>,
+    /// Projective mapping of the undistorted pixels to the scene, none for a camera facing its center
+    pub homography: Option<Homography>,
+}
+
+impl CameraMapping {
+    /// Mapping of a camera image spanning a scene of the given width in meters
+    pub fn for_scene(resolution: UVec2, scene_width: f32) -> Self {
+        Self {
+            resolution,
+            units_per_pixel: scene_width / resolution.x as f32,
+            center: Vec2::ZERO,
+            lens: None,
+            homography: None,
+        }
+    }
+
+    pub fn with_lens(self, lens: Option<LensIntrinsics>) -> Self {
+        Self { lens, ..self }
+    }
+
+    /// The mapping through the given homography, with center and scale taken from the area it covers
+    pub fn with_homography(self, homography: Option<Homography>) -> Self {
+        let mut mapping = Self { homography, ..self };
+        if homography.is_some() {
+            let footprint = mapping.footprint();
+            mapping.center = footprint.center();
+            mapping.units_per_pixel = footprint.width() / self.resolution.x as f32;
+        }
+        mapping
+    }
+
+    /// Scene-local area covered by the camera image
+    pub fn footprint(&self) -> Rect {
+        if self.homography.is_none() {
+            return Rect::from_center_size(self.center, self.resolution.as_vec2() * self.units_per_pixel);
+        }
+        let size = self.resolution.as_vec2();
+        [Vec2::ZERO, Vec2::new(size.x, 0.0), size, Vec2::new(0.0, size.y)]
+            .into_iter()
+            .map(|corner| self.to_scene(corner))
+            .fold(Rect::EMPTY, |footprint, corner| footprint.union_point(corner))
+    }
+
+    pub fn to_scene(&self, pixel: Vec2) -> Vec2 {
+        let pixel = self.lens.map_or(pixel, |lens| lens.undistort(pixel));
+        if let Some(homography) = self.homography {
+            return homography.apply(pixel);
+        }
+        let center = self.resolution.as_vec2() / 2.0;
+        self.center + Vec2::new(pixel.x - center.x, center.y - pixel.y) * self.units_per_pixel
+    }
+
+    pub fn to_pixel(&self, position: Vec2) -> Vec2 {
+        let pixel = match self.homography.and_then(|homography| homography.inverse()) {
+            Some(inverse) => inverse.apply(position),
+            None => {
+                let center = self.resolution.as_vec2() / 2.0;
+                let offset = (position - self.center) / self.units_per_pixel;
+                Vec2::new(center.x + offset.x, center.y - offset.y)
+            }
+        };
+        self.lens.map_or(pixel, |lens| lens.distort(pixel))
+    }
+}
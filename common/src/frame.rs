@@ -0,0 +1,437 @@
+use bevy::prelude::{UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::recording::PlaybackSource;
+
+/// Extension of raw frame files picked up from a directory
+pub const RAW_FRAME_EXTENSION: &str = "raw";
+
+/// A 16-bit radiometric frame from the thermal camera
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalFrame {
+    pub resolution: UVec2,
+    /// Raw sensor counts in row-major order, radiometric calibration maps them to temperatures
+    pub pixels: Vec<u16>,
+    /// Time since the first frame of the source
+    pub timestamp: Duration,
+}
+
+impl ThermalFrame {
+    /// A frame with every pixel set to the same value
+    pub fn filled(resolution: UVec2, value: u16, timestamp: Duration) -> Self {
+        Self {
+            resolution,
+            pixels: vec![value; (resolution.x * resolution.y) as usize],
+            timestamp,
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u16 {
+        self.pixels[(y * self.resolution.x + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: u16) {
+        self.pixels[(y * self.resolution.x + x) as usize] = value;
+    }
+
+    /// Decode a frame stored as little-endian 16-bit values, none unless there are exactly two bytes per pixel
+    pub fn from_le_bytes(resolution: UVec2, bytes: &[u8], timestamp: Duration) -> Option<Self> {
+        if bytes.len() != (resolution.x * resolution.y) as usize * 2 {
+            return None;
+        }
+        let pixels = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        Some(Self { resolution, pixels, timestamp })
+    }
+
+    /// Encode the frame as little-endian 16-bit values, the raw frame file format
+    pub fn to_le_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect()
+    }
+}
+
+/// Errors raised while opening or reading a frame source
+#[derive(Debug)]
+pub enum FrameSourceError {
+    Io(std::io::Error),
+    /// A raw file ended in the middle of a frame
+    TruncatedFrame { path: PathBuf },
+    /// The source does not contain any frame
+    Empty,
+    /// A recording file with a missing or unsupported header
+    InvalidRecording { path: PathBuf, reason: String },
+    InvalidConfiguration(String),
+}
+
+impl std::fmt::Display for FrameSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameSourceError::Io(e) => write!(f, "{}", e),
+            FrameSourceError::TruncatedFrame { path } => write!(f, "{} ends with a truncated frame", path.display()),
+            FrameSourceError::Empty => write!(f, "frame source has no frames"),
+            FrameSourceError::InvalidRecording { path, reason } => write!(f, "{}: {}", path.display(), reason),
+            FrameSourceError::InvalidConfiguration(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FrameSourceError {}
+
+impl From<std::io::Error> for FrameSourceError {
+    fn from(e: std::io::Error) -> Self {
+        FrameSourceError::Io(e)
+    }
+}
+
+/// A stream of timestamped thermal frames, from a camera, a recording or a generator
+pub trait FrameSource: Send + Sync {
+    /// Resolution of every frame yielded by the source
+    fn resolution(&self) -> UVec2;
+
+    /// The next frame, or `None` once the source is exhausted
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError>;
+
+    /// Playback controls, for sources replaying a recording
+    fn playback(&mut self) -> Option<&mut PlaybackSource> {
+        None
+    }
+}
+
+/// Which frame source to open, as found in the camera configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FrameSourceConfig {
+    /// Raw little-endian 16-bit frames from a file, or from the `.raw` files of a directory
+    Raw {
+        path: PathBuf,
+        fps: f32,
+        /// Replay the recording forever instead of stopping after the last frame
+        #[serde(default)]
+        looping: bool,
+    },
+    /// A recording made by the terminal, played back at the given speed
+    Recording {
+        path: PathBuf,
+        #[serde(default = "default_playback_speed")]
+        speed: f32,
+        #[serde(default)]
+        looping: bool,
+    },
+    /// Generated frames with sensor noise, for development without a camera
+    Synthetic {
+        fps: f32,
+        background: u16,
+        noise: u16,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl Default for FrameSourceConfig {
+    fn default() -> Self {
+        FrameSourceConfig::Synthetic {
+            fps: 25.0,
+            // Roughly 20 °C on sensors reporting 1/64 K
+            background: 18750,
+            noise: 20,
+            seed: 0,
+        }
+    }
+}
+
+fn default_playback_speed() -> f32 {
+    1.0
+}
+
+impl FrameSourceConfig {
+    /// Open the configured source for frames of the given resolution
+    pub fn open(&self, resolution: UVec2) -> Result<Box<dyn FrameSource>, FrameSourceError> {
+        match self {
+            FrameSourceConfig::Raw { path, fps, looping: false } => {
+                Ok(Box::new(RawFrameSource::open(path, resolution, *fps)?))
+            }
+            FrameSourceConfig::Raw { path, fps, looping: true } => {
+                let mut recording = RawFrameSource::open(path, resolution, *fps)?;
+                Ok(Box::new(LoopingSource::record(&mut recording, *fps)?))
+            }
+            FrameSourceConfig::Recording { path, speed, looping } => {
+                let mut playback = PlaybackSource::open(path)?;
+                if playback.resolution() != resolution {
+                    return Err(FrameSourceError::InvalidConfiguration(format!(
+                        "recording resolution {} does not match the camera resolution {}",
+                        playback.resolution(),
+                        resolution
+                    )));
+                }
+                playback.set_speed(*speed)?;
+                playback.looping = *looping;
+                Ok(Box::new(playback))
+            }
+            FrameSourceConfig::Synthetic { fps, background, noise, seed } => {
+                let mut source = SyntheticSource::new(resolution, *fps, *background)?;
+                source.noise = *noise;
+                source.seed(*seed);
+                Ok(Box::new(source))
+            }
+        }
+    }
+}
+
+/// Time between frames at the given rate, which must be positive
+pub(crate) fn frame_interval(fps: f32) -> Result<Duration, FrameSourceError> {
+    if fps.is_finite() && fps > 0.0 {
+        Ok(Duration::from_secs_f64(1.0 / fps as f64))
+    } else {
+        Err(FrameSourceError::InvalidConfiguration(format!("invalid frame rate {}", fps)))
+    }
+}
+
+/// Xorshift generator of the sensor noise of generated frames, the same seed gives the same sequence
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NoiseRng(u64);
+
+impl NoiseRng {
+    const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+
+    pub(crate) fn new(seed: u64) -> Self {
+        // Xorshift must not start from zero, which the mix gives for one seed
+        match seed ^ Self::MIX {
+            0 => Self(Self::MIX),
+            mixed => Self(mixed),
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform sample between -1 and 1
+    pub(crate) fn next_signed_unit(&mut self) -> f32 {
+        let unit = (self.next_u64() >> 11) as f32 / (1u64 << 53) as f32;
+        unit * 2.0 - 1.0
+    }
+}
+
+/// Reads raw frames from a file or from every `.raw` file of a directory, in file name order
+///
+/// Each file holds one or more frames of little-endian 16-bit pixels in row-major order.
+pub struct RawFrameSource {
+    resolution: UVec2,
+    frame_interval: Duration,
+    pending_files: VecDeque<PathBuf>,
+    current: Option<(PathBuf, BufReader<File>)>,
+    frame_index: u32,
+}
+
+impl RawFrameSource {
+    pub fn open(path: impl AsRef<Path>, resolution: UVec2, fps: f32) -> Result<Self, FrameSourceError> {
+        let path = path.as_ref();
+        let pending_files = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| file.is_file() && file.extension().is_some_and(|ext| ext == RAW_FRAME_EXTENSION))
+                .collect();
+            files.sort();
+            files.into()
+        } else {
+            // Fail early on a missing file rather than on the first frame
+            File::open(path)?;
+            VecDeque::from([path.to_path_buf()])
+        };
+
+        Ok(Self {
+            resolution,
+            frame_interval: frame_interval(fps)?,
+            pending_files,
+            current: None,
+            frame_index: 0,
+        })
+    }
+}
+
+impl FrameSource for RawFrameSource {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError> {
+        let mut bytes = vec![0; (self.resolution.x * self.resolution.y) as usize * 2];
+        loop {
+            if self.current.is_none() {
+                let Some(path) = self.pending_files.pop_front() else {
+                    return Ok(None);
+                };
+                let file = File::open(&path)?;
+                self.current = Some((path, BufReader::new(file)));
+            }
+            let Some((path, reader)) = self.current.as_mut() else {
+                continue;
+            };
+
+            match read_full(reader, &mut bytes)? {
+                0 => self.current = None,
+                read if read < bytes.len() => {
+                    return Err(FrameSourceError::TruncatedFrame { path: path.clone() });
+                }
+                _ => {
+                    let timestamp = self.frame_interval * self.frame_index;
+                    self.frame_index += 1;
+                    return ThermalFrame::from_le_bytes(self.resolution, &bytes, timestamp)
+                        .map(Some)
+                        .ok_or_else(|| FrameSourceError::TruncatedFrame { path: path.clone() });
+                }
+            }
+        }
+    }
+}
+
+/// Fill the buffer unless the end of the reader comes first, returning the number of bytes read
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Replays a recorded sequence of frames forever, with timestamps that keep increasing
+pub struct LoopingSource {
+    resolution: UVec2,
+    frame_interval: Duration,
+    frames: Vec<ThermalFrame>,
+    frame_index: u32,
+}
+
+impl LoopingSource {
+    pub fn new(frames: Vec<ThermalFrame>, fps: f32) -> Result<Self, FrameSourceError> {
+        let resolution = frames.first().ok_or(FrameSourceError::Empty)?.resolution;
+        Ok(Self {
+            resolution,
+            frame_interval: frame_interval(fps)?,
+            frames,
+            frame_index: 0,
+        })
+    }
+
+    /// Read every frame of a finite source into memory to replay them
+    pub fn record(source: &mut dyn FrameSource, fps: f32) -> Result<Self, FrameSourceError> {
+        let mut frames = Vec::new();
+        while let Some(frame) = source.next_frame()? {
+            frames.push(frame);
+        }
+        Self::new(frames, fps)
+    }
+}
+
+impl FrameSource for LoopingSource {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError> {
+        let mut frame = self.frames[self.frame_index as usize % self.frames.len()].clone();
+        frame.timestamp = self.frame_interval * self.frame_index;
+        self.frame_index += 1;
+        Ok(Some(frame))
+    }
+}
+
+/// A warm spot drawn by the synthetic source, in camera input pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntheticSpot {
+    pub center: Vec2,
+    /// Standard deviation of the gaussian heat profile
+    pub radius: f32,
+    /// Counts added to the background at the center of the spot
+    pub intensity: u16,
+    /// Index of the first frame showing the spot
+    pub first_frame: u32,
+    /// Number of frames the spot stays visible
+    pub frames: u32,
+}
+
+/// Generates frames of a uniform background with sensor noise and optional warm spots
+pub struct SyntheticSource {
+    resolution: UVec2,
+    frame_interval: Duration,
+    pub background: u16,
+    /// Maximum deviation of the uniform sensor noise, in counts
+    pub noise: u16,
+    pub spots: Vec<SyntheticSpot>,
+    rng: NoiseRng,
+    frame_index: u32,
+}
+
+impl SyntheticSource {
+    pub fn new(resolution: UVec2, fps: f32, background: u16) -> Result<Self, FrameSourceError> {
+        Ok(Self {
+            resolution,
+            frame_interval: frame_interval(fps)?,
+            background,
+            noise: 0,
+            spots: Vec::new(),
+            rng: NoiseRng::new(0),
+            frame_index: 0,
+        })
+    }
+
+    /// Restart the noise sequence, sources with the same seed generate the same frames
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = NoiseRng::new(seed);
+    }
+
+    fn sample_noise(&mut self) -> i32 {
+        if self.noise == 0 {
+            return 0;
+        }
+        let span = 2 * self.noise as u64 + 1;
+        (self.rng.next_u64() % span) as i32 - self.noise as i32
+    }
+}
+
+impl FrameSource for SyntheticSource {
+    fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError> {
+        let timestamp = self.frame_interval * self.frame_index;
+        let mut frame = ThermalFrame::filled(self.resolution, self.background, timestamp);
+        let visible: Vec<SyntheticSpot> = self
+            .spots
+            .iter()
+            .filter(|spot| (spot.first_frame..spot.first_frame + spot.frames).contains(&self.frame_index))
+            .copied()
+            .collect();
+
+        for y in 0..self.resolution.y {
+            for x in 0..self.resolution.x {
+                let position = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let heat: f32 = visible
+                    .iter()
+                    .map(|spot| {
+                        let distance_squared = position.distance_squared(spot.center);
+                        spot.intensity as f32 * (-distance_squared / (2.0 * spot.radius * spot.radius)).exp()
+                    })
+                    .sum();
+                let value = self.background as i32 + heat.round() as i32 + self.sample_noise();
+                frame.set(x, y, value.clamp(0, u16::MAX as i32) as u16);
+            }
+        }
+
+        self.frame_index += 1;
+        Ok(Some(frame))
+    }
+}
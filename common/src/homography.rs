@@ -0,0 +1,134 @@
+use bevy::math::{DMat3, DVec2, DVec3};
+use bevy::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::linalg::solve;
+
+/// Projective mapping of undistorted camera pixels to scene-local meters
+///
+/// Unlike a uniform scale, it holds for a camera looking at the wall from off-axis, where the far
+/// side of the scene covers fewer pixels than the near side.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Homography {
+    /// Row-major 3x3 matrix applied to homogeneous pixel coordinates
+    pub matrix: [[f32; 3]; 3],
+}
+
+impl Homography {
+    fn to_dmat3(self) -> DMat3 {
+        let rows = self.matrix.map(|row| DVec3::from(row.map(f64::from)));
+        DMat3::from_cols(rows[0], rows[1], rows[2]).transpose()
+    }
+
+    fn from_dmat3(matrix: DMat3) -> Self {
+        // Scale so that the bottom right element is one, as it is in the fit
+        let matrix = matrix / matrix.z_axis.z;
+        let rows = matrix.transpose().to_cols_array_2d();
+        Self { matrix: rows.map(|row| row.map(|value| value as f32)) }
+    }
+
+    /// Scene-local position of a pixel
+    pub fn apply(&self, pixel: Vec2) -> Vec2 {
+        project(self.to_dmat3(), pixel.as_dvec2()).as_vec2()
+    }
+
+    /// Mapping of scene-local positions back to pixels, none if the homography is singular
+    pub fn inverse(&self) -> Option<Homography> {
+        let matrix = self.to_dmat3();
+        (matrix.determinant().abs() > f64::EPSILON).then(|| Self::from_dmat3(matrix.inverse()))
+    }
+}
+
+fn project(matrix: DMat3, point: DVec2) -> DVec2 {
+    let projected = matrix * point.extend(1.0);
+    projected.truncate() / projected.z
+}
+
+/// Errors raised while fitting a homography to reference points
+#[derive(Debug, Clone, PartialEq)]
+pub enum HomographyError {
+    /// At least four reference points are needed, the number given
+    TooFewPoints(usize),
+    /// The points do not pin down a homography, as when three of four are on one line
+    Degenerate,
+}
+
+impl std::fmt::Display for HomographyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HomographyError::TooFewPoints(count) => write!(f, "{} reference points given, at least 4 are needed", count),
+            HomographyError::Degenerate => write!(f, "reference points are collinear or coincide"),
+        }
+    }
+}
+
+impl std::error::Error for HomographyError {}
+
+/// A homography fitted to reference points, with how well it reprojects them
+#[derive(Debug, Clone, PartialEq)]
+pub struct HomographyFit {
+    pub homography: Homography,
+    /// Distance between the known position of each reference point and where its pixel maps, in meters
+    pub errors: Vec<f32>,
+    pub rms_error: f32,
+}
+
+/// Fit the homography mapping the pixels of reference points to their known scene-local positions
+///
+/// Uses the normalized direct linear transform: both point sets are centered and scaled before the
+/// least squares fit, which keeps it well conditioned with pixel and meter coordinates.
+pub fn estimate_homography(pairs: &[(Vec2, Vec2)]) -> Result<HomographyFit, HomographyError> {
+    if pairs.len() < 4 {
+        return Err(HomographyError::TooFewPoints(pairs.len()));
+    }
+    let pixels: Vec<DVec2> = pairs.iter().map(|(pixel, _)| pixel.as_dvec2()).collect();
+    let positions: Vec<DVec2> = pairs.iter().map(|(_, position)| position.as_dvec2()).collect();
+    let pixel_normalization = normalization(&pixels).ok_or(HomographyError::Degenerate)?;
+    let position_normalization = normalization(&positions).ok_or(HomographyError::Degenerate)?;
+
+    // Least squares of the 8 unknown elements, the ninth being one
+    let mut normal = [[0.0; 8]; 8];
+    let mut target = [0.0; 8];
+    for (pixel, position) in pixels.iter().zip(&positions) {
+        let p = project(pixel_normalization, *pixel);
+        let s = project(position_normalization, *position);
+        let rows = [
+            ([p.x, p.y, 1.0, 0.0, 0.0, 0.0, -s.x * p.x, -s.x * p.y], s.x),
+            ([0.0, 0.0, 0.0, p.x, p.y, 1.0, -s.y * p.x, -s.y * p.y], s.y),
+        ];
+        for (row, value) in rows {
+            for i in 0..8 {
+                for j in 0..8 {
+                    normal[i][j] += row[i] * row[j];
+                }
+                target[i] += row[i] * value;
+            }
+        }
+    }
+    let h = solve(normal, target).ok_or(HomographyError::Degenerate)?;
+    let normalized = DMat3::from_cols_array(&[h[0], h[3], h[6], h[1], h[4], h[7], h[2], h[5], 1.0]);
+    let matrix = position_normalization.inverse() * normalized * pixel_normalization;
+    if !matrix.is_finite() || matrix.z_axis.z.abs() < f64::EPSILON || matrix.determinant().abs() < f64::EPSILON {
+        return Err(HomographyError::Degenerate);
+    }
+
+    let homography = Homography::from_dmat3(matrix);
+    let errors: Vec<f32> = pairs.iter().map(|(pixel, position)| homography.apply(*pixel).distance(*position)).collect();
+    let rms_error = (errors.iter().map(|error| error * error).sum::<f32>() / errors.len() as f32).sqrt();
+    Ok(HomographyFit { homography, errors, rms_error })
+}
+
+/// Similarity moving the points' centroid to the origin and their mean distance to it to √2
+fn normalization(points: &[DVec2]) -> Option<DMat3> {
+    let centroid = points.iter().copied().sum::<DVec2>() / points.len() as f64;
+    let mean_distance = points.iter().map(|point| point.distance(centroid)).sum::<f64>() / points.len() as f64;
+    if mean_distance < 1e-9 {
+        return None;
+    }
+    let scale = std::f64::consts::SQRT_2 / mean_distance;
+    Some(DMat3::from_cols(
+        DVec3::new(scale, 0.0, 0.0),
+        DVec3::new(0.0, scale, 0.0),
+        DVec3::new(-scale * centroid.x, -scale * centroid.y, 1.0),
+    ))
+}
@@ -0,0 +1,391 @@
+use bevy::prelude::{Transform, UVec2, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::homography::{estimate_homography, Homography};
+use crate::projector::UNIT_CORNERS;
+
+/// Projector or camera at a pose in the world, as a pinhole with square pixels
+///
+/// Like a bevy camera, the device looks down its local -z with y up. Image pixels are counted
+/// from the top left with y down, and the scene is the z = 0 plane of the scene transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub transform: Transform,
+    /// Full horizontal angle of view in degrees
+    pub angle: f32,
+    pub resolution: UVec2,
+}
+
+impl Frustum {
+    /// Tangents of the half angles across and down the image
+    fn half_extent(&self) -> Vec2 {
+        let tan = (self.angle.to_radians() / 2.0).tan();
+        Vec2::new(tan, tan * self.resolution.y as f32 / self.resolution.x as f32)
+    }
+
+    /// World direction of the ray through an image pixel
+    pub fn ray(&self, pixel: Vec2) -> Vec3 {
+        let device = pixel / self.resolution.as_vec2() * 2.0 - 1.0;
+        let extent = self.half_extent();
+        self.transform.rotation * Vec3::new(device.x * extent.x, -device.y * extent.y, -1.0)
+    }
+
+    /// Scene-local position an image pixel lands on, none if its ray misses the scene plane
+    pub fn to_scene(&self, pixel: Vec2, scene: &Transform) -> Option<Vec2> {
+        let to_scene = scene.compute_affine().inverse();
+        let origin = to_scene.transform_point3(self.transform.translation);
+        let direction = to_scene.transform_vector3(self.ray(pixel));
+        if direction.z.abs() < 1e-6 {
+            return None;
+        }
+        let distance = -origin.z / direction.z;
+        (distance > 0.0).then(|| (origin + direction * distance).truncate())
+    }
+
+    /// Image pixel of a scene-local position, none if the position is behind the device
+    pub fn to_pixel(&self, position: Vec2, scene: &Transform) -> Option<Vec2> {
+        let world = scene.transform_point(position.extend(0.0));
+        let local = self.transform.compute_affine().inverse().transform_point3(world);
+        if local.z > -1e-6 {
+            return None;
+        }
+        let device = local.truncate() / -local.z / self.half_extent();
+        Some(Vec2::new(device.x + 1.0, 1.0 - device.y) / 2.0 * self.resolution.as_vec2())
+    }
+
+    /// Scene-local corners of the image, clockwise from the top left, none if one misses the scene plane
+    pub fn footprint(&self, scene: &Transform) -> Option<[Vec2; 4]> {
+        let size = self.resolution.as_vec2();
+        let [top_left, top_right, bottom_right, bottom_left] = UNIT_CORNERS.map(|corner| self.to_scene(corner * size, scene));
+        Some([top_left?, top_right?, bottom_right?, bottom_left?])
+    }
+
+    /// Mapping of the image pixels to scene-local meters, exact as the scene is a plane
+    pub fn homography(&self, scene: &Transform) -> Option<Homography> {
+        let size = self.resolution.as_vec2();
+        let pairs: Vec<(Vec2, Vec2)> = UNIT_CORNERS.map(|corner| corner * size).into_iter().zip(self.footprint(scene)?).collect();
+        estimate_homography(&pairs).ok().map(|fit| fit.homography)
+    }
+
+    /// Angle in degrees at which the image just covers the scene of the given size centered on its
+    /// origin, none if part of the scene is behind the device
+    pub fn angle_to_cover(&self, scene: &Transform, dimensions: Vec2) -> Option<f32> {
+        let to_device = self.transform.compute_affine().inverse();
+        let aspect = self.resolution.x as f32 / self.resolution.y as f32;
+        let mut tan: f32 = 0.0;
+        // The frustum is convex, so it covers the scene once it holds its corners
+        for corner in [Vec2::new(-1.0, 1.0), Vec2::ONE, Vec2::new(1.0, -1.0), Vec2::NEG_ONE] {
+            let local = to_device.transform_point3(scene.transform_point((corner * dimensions / 2.0).extend(0.0)));
+            if local.z > -1e-6 {
+                return None;
+            }
+            tan = tan.max(local.x.abs() / -local.z).max(local.y.abs() / -local.z * aspect);
+        }
+        Some(2.0 * tan.atan().to_degrees())
+    }
+}
+
+/// Part of the convex polygon `subject` inside the convex polygon `clip`, both wound the same way
+pub fn convex_intersection(subject: &[Vec2], clip: &[Vec2]) -> Vec<Vec2> {
+    let winding = polygon_area(clip).signum();
+    let mut polygon = subject.to_vec();
+    for (&a, &b) in clip.iter().zip(clip.iter().cycle().skip(1)) {
+        if polygon.is_empty() {
+            break;
+        }
+        let inside = |point: Vec2| (b - a).perp_dot(point - a) * winding >= 0.0;
+        let input = std::mem::take(&mut polygon);
+        for (&current, &next) in input.iter().zip(input.iter().cycle().skip(1)) {
+            if inside(current) {
+                polygon.push(current);
+            }
+            if inside(current) != inside(next) {
+                let edge = b - a;
+                let t = edge.perp_dot(a - current) / edge.perp_dot(next - current);
+                polygon.push(current + (next - current) * t);
+            }
+        }
+    }
+    polygon
+}
+
+/// Signed area of a polygon, positive when counterclockwise with y up
+pub fn polygon_area(polygon: &[Vec2]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Diagonal lines a convex polygon is shaded with, `spacing` meters apart
+pub fn hatch(polygon: &[Vec2], spacing: f32) -> Vec<(Vec2, Vec2)> {
+    if polygon.len() < 3 || spacing <= 0.0 {
+        return Vec::new();
+    }
+    // Lines x + y = c for every step of c across the polygon
+    let (low, high) = polygon
+        .iter()
+        .map(|point| point.x + point.y)
+        .fold((f32::MAX, f32::MIN), |(low, high), c| (low.min(c), high.max(c)));
+    let mut lines = Vec::new();
+    let mut c = (low / spacing).ceil() * spacing;
+    while c <= high {
+        let crossings: Vec<Vec2> = polygon
+            .iter()
+            .zip(polygon.iter().cycle().skip(1))
+            .filter_map(|(&a, &b)| {
+                let (da, db) = (a.x + a.y - c, b.x + b.y - c);
+                (da.signum() != db.signum() && da != db).then(|| a + (b - a) * (da / (da - db)))
+            })
+            .collect();
+        // Lines through a corner only touch the polygon
+        if let [first, .., last] = crossings[..]
+            && first.distance(last) > 1e-6
+        {
+            lines.push((first, last));
+        }
+        c += spacing;
+    }
+    lines
+}
+
+/// Where a ray first meets a plane of the given pose and size, as the index of the plane, the
+/// distance along the ray and the plane-local position
+///
+/// Planes are the z = 0 plane of their transform, centered on its origin, and can be hit from
+/// either side.
+pub fn nearest_plane_hit(
+    planes: impl IntoIterator<Item = (Transform, Vec2)>,
+    origin: Vec3,
+    direction: Vec3,
+) -> Option<(usize, f32, Vec2)> {
+    planes
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, (transform, size))| {
+            let to_plane = transform.compute_affine().inverse();
+            let local_origin = to_plane.transform_point3(origin);
+            let local_direction = to_plane.transform_vector3(direction);
+            if local_direction.z.abs() < 1e-6 {
+                return None;
+            }
+            let distance = -local_origin.z / local_direction.z;
+            let local = (local_origin + local_direction * distance).truncate();
+            (distance > 0.0 && local.abs().cmple(size / 2.0).all()).then_some((index, distance, local))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Height above the shooter a device is mounted at, so that it stays out of the line of fire
+const HEAD_CLEARANCE: f32 = 0.5;
+/// Distance between the camera and the projector mounted side by side
+const DEVICE_SPACING: f32 = 0.5;
+
+/// Venue and devices the layout planner places the devices for
+///
+/// Room coordinates are in meters with x across the target wall from its middle, y up from the
+/// floor and z away from the wall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutInput {
+    /// Width, height and depth of the room
+    pub room: Vec3,
+    /// Position of the shooter across the room and away from the wall
+    pub shooter: Vec2,
+    /// Height of the shooter's head
+    pub shooter_height: f32,
+    /// Width and height of the target area on the wall
+    pub target_size: Vec2,
+    /// Height of the middle of the target area above the floor
+    pub target_height: f32,
+    /// Diameter of the smallest target that must be scored
+    pub smallest_target: f32,
+    /// Camera pixels needed across the smallest target to tell hits apart
+    pub min_pixels_per_target: f32,
+    pub camera_resolution: UVec2,
+    /// Horizontal field of view of the camera in degrees
+    pub camera_fov: f32,
+    pub projector_resolution: UVec2,
+    /// Horizontal throw angle of the projector in degrees
+    pub projector_angle: f32,
+}
+
+impl Default for LayoutInput {
+    fn default() -> Self {
+        Self {
+            room: Vec3::new(8.0, 3.0, 12.0),
+            shooter: Vec2::new(0.0, 7.0),
+            shooter_height: 1.8,
+            target_size: Vec2::new(4.0, 3.0),
+            target_height: 1.6,
+            smallest_target: 0.1,
+            min_pixels_per_target: 3.0,
+            camera_resolution: UVec2::new(256, 192),
+            camera_fov: 25.0,
+            projector_resolution: UVec2::new(1920, 1080),
+            projector_angle: 30.0,
+        }
+    }
+}
+
+/// Problems with a planned layout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanWarning {
+    /// The camera would have to be further from the wall than the room is deep
+    CameraBeyondRoom { needed: f32, depth: f32 },
+    /// The projector would have to be further from the wall than the room is deep
+    ProjectorBeyondRoom { needed: f32, depth: f32 },
+    /// The ceiling is too low to mount the devices above the shooter
+    CeilingTooLow { needed: f32, height: f32 },
+    /// A device hangs between the shooter and the wall, at the given distance from the wall
+    AheadOfShooter { distance: f32 },
+    /// The camera sees the smallest target with too few pixels to score it
+    CoarseDetection { pixels: f32, needed: f32 },
+    /// The projector draws the smallest target with fewer pixels than the camera sees it with
+    CoarseProjection { pixels: f32 },
+}
+
+impl std::fmt::Display for PlanWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanWarning::CameraBeyondRoom { needed, depth } => {
+                write!(f, "the camera needs {:.1} m to see the target area, the room is {:.1} m deep", needed, depth)
+            }
+            PlanWarning::ProjectorBeyondRoom { needed, depth } => {
+                write!(f, "the projector needs {:.1} m to cover the target area, the room is {:.1} m deep", needed, depth)
+            }
+            PlanWarning::CeilingTooLow { needed, height } => {
+                write!(f, "the devices should hang at {:.1} m above the shooter, the ceiling is at {:.1} m", needed, height)
+            }
+            PlanWarning::AheadOfShooter { distance } => {
+                write!(f, "a device hangs {:.1} m from the wall, in front of the shooter, keep it out of the line of sight", distance)
+            }
+            PlanWarning::CoarseDetection { pixels, needed } => {
+                write!(f, "the smallest target is {:.1} camera pixels across, at least {:.1} are needed", pixels, needed)
+            }
+            PlanWarning::CoarseProjection { pixels } => {
+                write!(f, "the smallest target is only {:.1} projector pixels across", pixels)
+            }
+        }
+    }
+}
+
+/// Where the planner mounts the devices, in room coordinates, and how finely they resolve the wall
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutPlan {
+    /// Position of the camera, aimed at the middle of the target area
+    pub camera: Vec3,
+    /// Position of the projector, aimed at the middle of the target area
+    pub projector: Vec3,
+    /// Width and height of the wall the camera sees
+    pub camera_coverage: Vec2,
+    /// Width and height of the wall the projector covers
+    pub projector_coverage: Vec2,
+    /// Meters of wall per camera pixel in the middle of the target area
+    pub camera_units_per_pixel: f32,
+    /// Meters of wall per projector pixel in the middle of the target area
+    pub projector_units_per_pixel: f32,
+    pub warnings: Vec<PlanWarning>,
+}
+
+/// Distance from a wall at which a device with the given horizontal angle and image aspect covers the area
+fn covering_distance(angle: f32, resolution: UVec2, area: Vec2) -> f32 {
+    let tan = (angle.to_radians() / 2.0).tan();
+    let half_extent = Vec2::new(tan, tan * resolution.y as f32 / resolution.x as f32);
+    (area / 2.0 / half_extent).max_element()
+}
+
+/// Size of the wall a device covers and the meters per pixel in its middle
+fn resolve(frustum: &Frustum, wall: &Transform) -> (Vec2, f32) {
+    let middle = frustum.resolution.as_vec2() / 2.0;
+    let units_per_pixel = match (frustum.to_scene(middle, wall), frustum.to_scene(middle + Vec2::X, wall)) {
+        (Some(a), Some(b)) => a.distance(b),
+        _ => f32::INFINITY,
+    };
+    let coverage = frustum.footprint(wall).map_or(Vec2::ZERO, |footprint| {
+        let [top_left, top_right, bottom_right, bottom_left] = footprint;
+        Vec2::new(
+            top_right.distance(top_left).min(bottom_right.distance(bottom_left)),
+            top_left.distance(bottom_left).min(top_right.distance(bottom_right)),
+        )
+    });
+    (coverage, units_per_pixel)
+}
+
+/// Mount the camera and the projector above the shooter so that each covers the target area, and
+/// report how finely they resolve it
+///
+/// Both devices are aimed at the middle of the target area from as far as their angle needs to
+/// cover it, limited by the depth of the room.
+pub fn plan_layout(input: &LayoutInput) -> LayoutPlan {
+    let mut warnings = Vec::new();
+    let target = Vec3::new(0.0, input.target_height, 0.0);
+    let wall = Transform::from_translation(target);
+
+    let mut height = target.y.max(input.shooter_height + HEAD_CLEARANCE);
+    if height > input.room.y {
+        warnings.push(PlanWarning::CeilingTooLow { needed: height, height: input.room.y });
+        height = input.room.y;
+    }
+
+    let mut place = |angle: f32, resolution: UVec2, side: f32, beyond: fn(f32, f32) -> PlanWarning| {
+        let x = input.shooter.x + side * DEVICE_SPACING / 2.0;
+        let frustum = |distance: f32| Frustum {
+            transform: Transform::from_translation(Vec3::new(x, height, distance)).looking_at(target, Vec3::Y),
+            angle,
+            resolution,
+        };
+        // Seen at a slant the near edge of the area shrinks, so back off until the whole area is covered
+        let mut needed = covering_distance(angle, resolution, input.target_size);
+        for _ in 0..10 {
+            let (coverage, _) = resolve(&frustum(needed), &wall);
+            let shortfall = (input.target_size / coverage).max_element();
+            if !shortfall.is_finite() || shortfall <= 1.0 + 1e-4 {
+                break;
+            }
+            needed *= shortfall;
+        }
+        if needed > input.room.z {
+            warnings.push(beyond(needed, input.room.z));
+        }
+        let distance = needed.min(input.room.z);
+        if distance < input.shooter.y {
+            warnings.push(PlanWarning::AheadOfShooter { distance });
+        }
+        let (coverage, units_per_pixel) = resolve(&frustum(distance), &wall);
+        (frustum(distance).transform.translation, coverage, units_per_pixel)
+    };
+    let (camera, camera_coverage, camera_units_per_pixel) = place(
+        input.camera_fov,
+        input.camera_resolution,
+        -1.0,
+        |needed, depth| PlanWarning::CameraBeyondRoom { needed, depth },
+    );
+    let (projector, projector_coverage, projector_units_per_pixel) = place(
+        input.projector_angle,
+        input.projector_resolution,
+        1.0,
+        |needed, depth| PlanWarning::ProjectorBeyondRoom { needed, depth },
+    );
+
+    let camera_pixels = input.smallest_target / camera_units_per_pixel;
+    if camera_pixels < input.min_pixels_per_target {
+        warnings.push(PlanWarning::CoarseDetection { pixels: camera_pixels, needed: input.min_pixels_per_target });
+    }
+    let projector_pixels = input.smallest_target / projector_units_per_pixel;
+    if projector_pixels < camera_pixels {
+        warnings.push(PlanWarning::CoarseProjection { pixels: projector_pixels });
+    }
+
+    LayoutPlan {
+        camera,
+        projector,
+        camera_coverage,
+        projector_coverage,
+        camera_units_per_pixel,
+        projector_units_per_pixel,
+        warnings,
+    }
+}
@@ -0,0 +1,398 @@
+use bevy::prelude::{UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::config::SceneConfiguration;
+use crate::detection::connected_components;
+use crate::frame::ThermalFrame;
+use crate::linalg::solve;
+use crate::radiometry::{median, RadiometricCalibration};
+
+/// Focal length and sensor size of a camera, which give the field of view it sees the scene with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraOptics {
+    /// Focal length of the lens in millimeters
+    pub focal_length: f32,
+    /// Width and height of the sensor in millimeters
+    pub sensor_size: Vec2,
+}
+
+impl Default for CameraOptics {
+    /// A 256x192 sensor with 12 µm pixels behind a lens seeing 10 m across at 25 m
+    fn default() -> Self {
+        Self {
+            focal_length: 7.68,
+            sensor_size: Vec2::new(3.072, 2.304),
+        }
+    }
+}
+
+impl CameraOptics {
+    /// Optics with the given horizontal field of view in degrees
+    pub fn from_fov(horizontal_fov: f32, sensor_size: Vec2) -> Self {
+        Self {
+            focal_length: sensor_size.x / 2.0 / (horizontal_fov.to_radians() / 2.0).tan(),
+            sensor_size,
+        }
+    }
+
+    /// Optics of a lens whose focal length in pixels was calibrated, on a sensor of the given size
+    pub fn from_intrinsics(lens: &LensIntrinsics, resolution: UVec2, sensor_size: Vec2) -> Self {
+        Self {
+            focal_length: lens.focal_length[0] * sensor_size.x / resolution.x as f32,
+            sensor_size,
+        }
+    }
+
+    /// Horizontal and vertical field of view in degrees
+    pub fn fov(&self) -> Vec2 {
+        (self.sensor_size / 2.0 / self.focal_length).map(|tan| 2.0 * tan.atan().to_degrees())
+    }
+
+    /// Width of the scene seen at the given distance in meters
+    pub fn scene_width(&self, distance: f32) -> f32 {
+        distance * self.sensor_size.x / self.focal_length
+    }
+
+    /// The same sensor behind the lens that sees a scene of the given width at the given distance
+    pub fn for_scene_width(&self, width: f32, distance: f32) -> Self {
+        Self {
+            focal_length: distance * self.sensor_size.x / width,
+            ..*self
+        }
+    }
+
+    /// Bring the optics and the scene width into agreement
+    ///
+    /// A scene width changed since `previous` sets the field of view, anything else, such as a new
+    /// distance, makes the width follow the field of view.
+    pub fn sync_scene(&mut self, scene: &mut SceneConfiguration, previous: &SceneConfiguration) {
+        let distance = scene.target_projection_distance;
+        if distance <= 0.0 || scene.scene_width <= 0.0 {
+            return;
+        }
+        if scene.scene_width != previous.scene_width && distance == previous.target_projection_distance {
+            *self = self.for_scene_width(scene.scene_width, distance);
+        } else {
+            scene.scene_width = self.scene_width(distance);
+        }
+    }
+}
+
+/// Pinhole intrinsics of a camera with Brown-Conrady lens distortion, in camera input pixels
+///
+/// Distortion moves an ideal image point `(x, y)`, normalized by the focal length around the
+/// principal point, to `(x, y) * (1 + k1 r² + k2 r⁴ + k3 r⁶)` plus the tangential terms.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LensIntrinsics {
+    /// Horizontal and vertical focal length in pixels
+    pub focal_length: [f32; 2],
+    /// Pixel the optical axis goes through
+    pub principal_point: [f32; 2],
+    /// Radial distortion coefficients k1, k2 and k3, negative for barrel distortion
+    pub radial: [f32; 3],
+    /// Tangential distortion coefficients p1 and p2, from a lens not parallel to the sensor
+    pub tangential: [f32; 2],
+}
+
+impl LensIntrinsics {
+    /// A lens without distortion with the principal point at the center of the image
+    pub fn ideal(resolution: UVec2, focal_length: f32) -> Self {
+        let center = resolution.as_vec2() / 2.0;
+        Self {
+            focal_length: [focal_length; 2],
+            principal_point: center.to_array(),
+            radial: [0.0; 3],
+            tangential: [0.0; 2],
+        }
+    }
+
+    /// Pixel an ideal pinhole camera would see at the given distorted pixel
+    pub fn undistort(&self, pixel: Vec2) -> Vec2 {
+        let distorted = self.normalize(pixel);
+        // The distortion is close to the identity, so the fixed point iteration converges quickly
+        let mut point = distorted;
+        for _ in 0..20 {
+            let (scale, shift) = self.distortion(point);
+            point = (distorted - shift) / scale;
+        }
+        self.denormalize(point)
+    }
+
+    /// Pixel the lens shows the given ideal pinhole pixel at
+    pub fn distort(&self, pixel: Vec2) -> Vec2 {
+        let point = self.normalize(pixel);
+        let (scale, shift) = self.distortion(point);
+        self.denormalize(point * scale + shift)
+    }
+
+    fn normalize(&self, pixel: Vec2) -> Vec2 {
+        (pixel - Vec2::from(self.principal_point)) / Vec2::from(self.focal_length)
+    }
+
+    fn denormalize(&self, point: Vec2) -> Vec2 {
+        point * Vec2::from(self.focal_length) + Vec2::from(self.principal_point)
+    }
+
+    /// Radial scale and tangential shift of a normalized point
+    fn distortion(&self, point: Vec2) -> (f32, Vec2) {
+        let [k1, k2, k3] = self.radial;
+        let [p1, p2] = self.tangential;
+        let (x, y) = (point.x, point.y);
+        let r2 = x * x + y * y;
+        let scale = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+        let shift = Vec2::new(
+            2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+            p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+        );
+        (scale, shift)
+    }
+}
+
+/// Errors raised while estimating the lens distortion from a heated grid
+#[derive(Debug, Clone, PartialEq)]
+pub enum LensCalibrationError {
+    /// The grid needs at least three rows and three columns of points
+    GridTooSmall(UVec2),
+    /// The capture does not show one hot point per grid crossing
+    PointCount { expected: usize, found: usize },
+    /// The fit did not settle on a distortion explaining the grid
+    NotConverged,
+}
+
+impl std::fmt::Display for LensCalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LensCalibrationError::GridTooSmall(grid) => {
+                write!(f, "a {}x{} grid is too small, at least 3x3 points are needed", grid.x, grid.y)
+            }
+            LensCalibrationError::PointCount { expected, found } => {
+                write!(f, "expected {} grid points but found {} hot spots", expected, found)
+            }
+            LensCalibrationError::NotConverged => write!(f, "lens distortion fit did not converge"),
+        }
+    }
+}
+
+impl std::error::Error for LensCalibrationError {}
+
+/// Lens intrinsics estimated from a heated grid, with how well they explain it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LensFit {
+    pub lens: LensIntrinsics,
+    /// Root mean square distance between the observed grid points and the fitted ones, in pixels
+    pub rms_error: f32,
+}
+
+/// Centroids of the points of a heated grid, ordered row by row from the top left
+///
+/// The grid is a regular pattern of heat pads or wire crossings, at least `min_rise` kelvin warmer
+/// than the wall. Rows are told apart by height, which holds as long as the distortion bends them
+/// by less than half the row spacing.
+pub fn find_grid_points(
+    frame: &ThermalFrame,
+    calibration: &RadiometricCalibration,
+    min_rise: f32,
+    grid: UVec2,
+) -> Result<Vec<Vec2>, LensCalibrationError> {
+    if grid.x < 3 || grid.y < 3 {
+        return Err(LensCalibrationError::GridTooSmall(grid));
+    }
+    let mut points = find_hot_spots(frame, calibration, min_rise);
+
+    let expected = (grid.x * grid.y) as usize;
+    if points.len() != expected {
+        return Err(LensCalibrationError::PointCount { expected, found: points.len() });
+    }
+    points.sort_by(|a, b| a.y.total_cmp(&b.y));
+    for row in points.chunks_mut(grid.x as usize) {
+        row.sort_by(|a, b| a.x.total_cmp(&b.x));
+    }
+    Ok(points)
+}
+
+/// Centroids of the areas warmer than the median of the frame by at least `min_rise` kelvin
+///
+/// Pixels are weighted by their rise, so the centroid of a heat pad lands on its warmest point.
+pub fn find_hot_spots(frame: &ThermalFrame, calibration: &RadiometricCalibration, min_rise: f32) -> Vec<Vec2> {
+    if frame.pixels.is_empty() {
+        return Vec::new();
+    }
+    let level = median(&frame.pixels);
+    let rise: Vec<f32> = frame.pixels.iter().map(|&pixel| (pixel as f32 - level) * calibration.gain).collect();
+    warm_areas(&rise, frame.resolution, min_rise).into_iter().map(|(centroid, _)| centroid).collect()
+}
+
+/// Rise-weighted pixel centroids of the areas that rose by at least `min_rise` kelvin, with their total rise
+pub(crate) fn warm_areas(rise: &[f32], resolution: UVec2, min_rise: f32) -> Vec<(Vec2, f32)> {
+    let hot: Vec<bool> = rise.iter().map(|&rise| rise >= min_rise).collect();
+
+    connected_components(&hot, resolution)
+        .into_iter()
+        .filter_map(|component| {
+            let mut weighted_position = Vec2::ZERO;
+            let mut weight = 0.0;
+            for index in component {
+                let pixel = Vec2::new((index as u32 % resolution.x) as f32, (index as u32 / resolution.x) as f32);
+                weighted_position += (pixel + 0.5) * rise[index];
+                weight += rise[index];
+            }
+            // Areas that did not rise overall have no centroid
+            (weight > 0.0).then(|| (weighted_position / weight, weight))
+        })
+        .collect()
+}
+
+/// Number of parameters fitted: grid origin, column and row steps, k1, k2, p1 and p2
+const PARAMETERS: usize = 10;
+
+/// Estimate the lens distortion from the points of a regular grid hung flat on the target wall
+///
+/// The points come row by row as returned by [`find_grid_points`]. As the camera faces the wall, the
+/// undistorted grid is regular: the fit looks for the grid origin and steps along with the radial
+/// coefficients k1, k2 and the tangential ones that best explain the observed points. Focal length
+/// and principal point are not observable from a single flat grid, so they are given.
+pub fn estimate_lens(
+    points: &[Vec2],
+    grid: UVec2,
+    focal_length: Vec2,
+    principal_point: Vec2,
+) -> Result<LensFit, LensCalibrationError> {
+    if grid.x < 3 || grid.y < 3 {
+        return Err(LensCalibrationError::GridTooSmall(grid));
+    }
+    let expected = (grid.x * grid.y) as usize;
+    if points.len() != expected {
+        return Err(LensCalibrationError::PointCount { expected, found: points.len() });
+    }
+
+    let model = GridModel { points, columns: grid.x, focal_length, principal_point };
+    let mut parameters = model.initial_guess();
+    let mut cost = model.cost(&parameters);
+    let mut damping = 1e-3;
+
+    // Levenberg-Marquardt with a numeric Jacobian
+    for _ in 0..100 {
+        let residuals = model.residuals(&parameters);
+        let jacobian: Vec<Vec<f64>> = (0..PARAMETERS)
+            .map(|parameter| {
+                let step = 1e-6 * parameters[parameter].abs().max(1.0);
+                let mut moved = parameters;
+                moved[parameter] += step;
+                model.residuals(&moved).iter().zip(&residuals).map(|(a, b)| (a - b) / step).collect()
+            })
+            .collect();
+
+        let mut normal = [[0.0; PARAMETERS]; PARAMETERS];
+        let mut gradient = [0.0; PARAMETERS];
+        for row in 0..PARAMETERS {
+            for column in 0..PARAMETERS {
+                normal[row][column] = jacobian[row].iter().zip(&jacobian[column]).map(|(a, b)| a * b).sum();
+            }
+            gradient[row] = -jacobian[row].iter().zip(&residuals).map(|(a, b)| a * b).sum::<f64>();
+        }
+
+        let mut improved = false;
+        while damping < 1e9 {
+            let mut damped = normal;
+            for (index, row) in damped.iter_mut().enumerate() {
+                row[index] += damping * normal[index][index].max(1e-12);
+            }
+            let Some(delta) = solve(damped, gradient) else {
+                damping *= 10.0;
+                continue;
+            };
+            let mut candidate = parameters;
+            for (parameter, change) in candidate.iter_mut().zip(delta) {
+                *parameter += change;
+            }
+            let candidate_cost = model.cost(&candidate);
+            if candidate_cost < cost {
+                improved = cost - candidate_cost > 1e-12 * cost.max(1e-12);
+                parameters = candidate;
+                cost = candidate_cost;
+                damping = (damping / 10.0).max(1e-9);
+                break;
+            }
+            damping *= 10.0;
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    let lens = model.lens(&parameters);
+    let rms_error = (cost / points.len() as f64).sqrt() as f32;
+    if !rms_error.is_finite() || lens.radial.iter().chain(&lens.tangential).any(|value| !value.is_finite()) {
+        return Err(LensCalibrationError::NotConverged);
+    }
+    Ok(LensFit { lens, rms_error })
+}
+
+/// Regular grid seen through a distorting lens, fitted to the observed grid points
+struct GridModel<'a> {
+    points: &'a [Vec2],
+    columns: u32,
+    focal_length: Vec2,
+    principal_point: Vec2,
+}
+
+impl GridModel<'_> {
+    /// Regular grid fitted to the distorted points, with no distortion
+    fn initial_guess(&self) -> [f64; PARAMETERS] {
+        // Least squares of point = origin + column * step + row * step, one axis at a time
+        let mut normal = [[0.0; 3]; 3];
+        let mut targets = [[0.0; 3]; 2];
+        for (index, point) in self.points.iter().enumerate() {
+            let basis = [1.0, (index as u32 % self.columns) as f64, (index as u32 / self.columns) as f64];
+            for row in 0..3 {
+                for column in 0..3 {
+                    normal[row][column] += basis[row] * basis[column];
+                }
+                targets[0][row] += basis[row] * point.x as f64;
+                targets[1][row] += basis[row] * point.y as f64;
+            }
+        }
+        let x = solve(normal, targets[0]).unwrap_or_default();
+        let y = solve(normal, targets[1]).unwrap_or_default();
+        [x[0], y[0], x[1], y[1], x[2], y[2], 0.0, 0.0, 0.0, 0.0]
+    }
+
+    fn lens(&self, parameters: &[f64; PARAMETERS]) -> LensIntrinsics {
+        LensIntrinsics {
+            focal_length: self.focal_length.to_array(),
+            principal_point: self.principal_point.to_array(),
+            radial: [parameters[6] as f32, parameters[7] as f32, 0.0],
+            tangential: [parameters[8] as f32, parameters[9] as f32],
+        }
+    }
+
+    /// Differences between the distorted grid and the observed points, in pixels
+    fn residuals(&self, parameters: &[f64; PARAMETERS]) -> Vec<f64> {
+        let focal = [self.focal_length.x as f64, self.focal_length.y as f64];
+        let center = [self.principal_point.x as f64, self.principal_point.y as f64];
+        let [k1, k2, p1, p2] = [parameters[6], parameters[7], parameters[8], parameters[9]];
+
+        let mut residuals = Vec::with_capacity(self.points.len() * 2);
+        for (index, point) in self.points.iter().enumerate() {
+            let column = (index as u32 % self.columns) as f64;
+            let row = (index as u32 / self.columns) as f64;
+            let ideal_x = parameters[0] + column * parameters[2] + row * parameters[4];
+            let ideal_y = parameters[1] + column * parameters[3] + row * parameters[5];
+
+            let x = (ideal_x - center[0]) / focal[0];
+            let y = (ideal_y - center[1]) / focal[1];
+            let r2 = x * x + y * y;
+            let scale = 1.0 + r2 * (k1 + r2 * k2);
+            let distorted_x = x * scale + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+            let distorted_y = y * scale + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+
+            residuals.push(distorted_x * focal[0] + center[0] - point.x as f64);
+            residuals.push(distorted_y * focal[1] + center[1] - point.y as f64);
+        }
+        residuals
+    }
+
+    fn cost(&self, parameters: &[f64; PARAMETERS]) -> f64 {
+        self.residuals(parameters).iter().map(|residual| residual * residual).sum()
+    }
+}
@@ -1,3 +1,20 @@
 pub mod config;
 pub mod path;
 pub mod network;
+pub mod frame;
+pub mod detection;
+pub mod tracking;
+pub mod palette;
+pub mod simulator;
+pub mod radiometry;
+pub mod recording;
+pub mod masks;
+pub mod venue;
+pub mod lens;
+pub mod stitching;
+pub mod homography;
+pub mod projector;
+pub mod layout;
+pub mod validation;
+pub mod shooter;
+mod linalg;
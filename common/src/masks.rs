@@ -0,0 +1,164 @@
+use bevy::prelude::{UVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::detection::{connected_components, CameraMapping};
+use crate::frame::ThermalFrame;
+use crate::radiometry::{median, RadiometricCalibration};
+
+/// How an exclusion mask was made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskOrigin {
+    /// Drawn by the operator
+    #[default]
+    Drawn,
+    /// Found by learning the background, learning again replaces it
+    Learned,
+}
+
+/// An area of the scene where warm spots are never shots, such as a radiator or a lamp
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionMask {
+    pub name: String,
+    /// Vertices of the polygon in scene-local meters
+    pub polygon: Vec<[f32; 2]>,
+    #[serde(default)]
+    pub origin: MaskOrigin,
+}
+
+impl ExclusionMask {
+    pub fn new(name: impl Into<String>, polygon: &[Vec2], origin: MaskOrigin) -> Self {
+        Self {
+            name: name.into(),
+            polygon: polygon.iter().map(|vertex| vertex.to_array()).collect(),
+            origin,
+        }
+    }
+
+    /// An axis aligned rectangle between two scene-local corners
+    pub fn rectangle(name: impl Into<String>, a: Vec2, b: Vec2, origin: MaskOrigin) -> Self {
+        let (min, max) = (a.min(b), a.max(b));
+        Self::new(name, &[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)], origin)
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.polygon.iter().map(|&vertex| Vec2::from_array(vertex))
+    }
+
+    /// Whether a scene-local position lies inside the polygon, with the even-odd rule
+    pub fn contains(&self, position: Vec2) -> bool {
+        if self.polygon.len() < 3 {
+            return false;
+        }
+        let mut inside = false;
+        let mut previous = Vec2::from_array(self.polygon[self.polygon.len() - 1]);
+        for vertex in self.vertices() {
+            if (vertex.y > position.y) != (previous.y > position.y) {
+                let crossing = previous.x + (position.y - previous.y) / (vertex.y - previous.y) * (vertex.x - previous.x);
+                if position.x < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = vertex;
+        }
+        inside
+    }
+}
+
+/// Exclusion masks rasterized to the camera input pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelMask {
+    resolution: UVec2,
+    excluded: Vec<bool>,
+}
+
+impl PixelMask {
+    /// Exclude every pixel whose center lies inside one of the masks
+    pub fn new(masks: &[ExclusionMask], mapping: &CameraMapping) -> Self {
+        let resolution = mapping.resolution;
+        let mut excluded = Vec::with_capacity((resolution.x * resolution.y) as usize);
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let position = mapping.to_scene(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+                excluded.push(masks.iter().any(|mask| mask.contains(position)));
+            }
+        }
+        Self { resolution, excluded }
+    }
+
+    pub fn resolution(&self) -> UVec2 {
+        self.resolution
+    }
+
+    /// Whether the pixel at the given row-major index is excluded
+    pub fn excludes(&self, index: usize) -> bool {
+        self.excluded.get(index).copied().unwrap_or(false)
+    }
+
+    /// Number of excluded pixels
+    pub fn count(&self) -> usize {
+        self.excluded.iter().filter(|&&excluded| excluded).count()
+    }
+}
+
+/// Finds the permanent hot spots of a venue from frames of the empty scene
+///
+/// Pixels that got warmer than the bulk of the wall by at least the threshold while learning, such
+/// as radiators, lamps or flickering reflections, are grouped into rectangular masks.
+pub struct HotSpotLearner {
+    resolution: UVec2,
+    /// Highest reading of each pixel above the median of its frame, in counts
+    hottest: Vec<f32>,
+    frames: u32,
+}
+
+impl HotSpotLearner {
+    pub fn new(resolution: UVec2) -> Self {
+        Self {
+            resolution,
+            hottest: vec![f32::MIN; (resolution.x * resolution.y) as usize],
+            frames: 0,
+        }
+    }
+
+    /// Number of frames learned from
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Learn from a frame, frames of another resolution are ignored
+    pub fn add(&mut self, frame: &ThermalFrame) {
+        if frame.resolution != self.resolution || frame.pixels.is_empty() {
+            return;
+        }
+        let level = median(&frame.pixels);
+        for (hottest, &pixel) in self.hottest.iter_mut().zip(&frame.pixels) {
+            *hottest = hottest.max(pixel as f32 - level);
+        }
+        self.frames += 1;
+    }
+
+    /// Masks covering the hot spots with one pixel of margin, warmer than the wall by the threshold in kelvin
+    pub fn masks(&self, threshold: f32, calibration: &RadiometricCalibration, mapping: &CameraMapping) -> Vec<ExclusionMask> {
+        if self.frames == 0 {
+            return Vec::new();
+        }
+        let hot: Vec<bool> = self.hottest.iter().map(|&rise| rise * calibration.gain >= threshold).collect();
+        connected_components(&hot, self.resolution)
+            .into_iter()
+            .enumerate()
+            .map(|(number, component)| {
+                let mut min = UVec2::MAX;
+                let mut max = UVec2::ZERO;
+                for index in component {
+                    let pixel = UVec2::new(index as u32 % self.resolution.x, index as u32 / self.resolution.x);
+                    min = min.min(pixel);
+                    max = max.max(pixel);
+                }
+                let corner = mapping.to_scene(min.as_vec2() - 1.0);
+                let opposite = mapping.to_scene(max.as_vec2() + 2.0);
+                ExclusionMask::rectangle(format!("Hot spot {}", number + 1), corner, opposite, MaskOrigin::Learned)
+            })
+            .collect()
+    }
+}
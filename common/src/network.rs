@@ -1,5 +1,13 @@
 use serde::{Deserialize, Serialize};
 
+use crate::config::SceneConfiguration;
+
+/// Identifier of a shooting lane, lanes are numbered from 1
+pub type LaneId = u32;
+
+/// Lane used by single-lane setups and clients that do not choose one
+pub const DEFAULT_LANE: LaneId = 1;
+
 /// Network messages exchanged between server and terminal
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NetworkMessage {
@@ -7,8 +15,235 @@ pub enum NetworkMessage {
     Ping { timestamp: u64 },
     /// Pong response from client
     Pong { timestamp: u64 },
+    /// Sent by a terminal right after connecting to receive the state of its lane
+    JoinLane { lane: LaneId },
+    /// Scene configuration of a lane, broadcast by the server whenever it changes
+    SceneConfiguration { lane: LaneId, configuration: SceneConfiguration },
+    /// Match phase of a lane, broadcast by the server whenever it changes
+    MatchStateChanged { lane: LaneId, phase: MatchPhase, elapsed_secs: f32 },
+    /// A target was placed on the scene of a lane
+    TargetPlaced { lane: LaneId, target: TargetInfo },
+    /// A target was removed from the scene of a lane
+    TargetRemoved { lane: LaneId, id: u32 },
+    /// Current score of the match running on a lane
+    ScoreUpdated { lane: LaneId, score: ScoreSummary },
+    /// Whether the terminals of a lane should record their thermal frames
+    RecordingChanged { lane: LaneId, recording: bool },
+    /// A shot detected by the terminal of a lane, in scene-local meters, with the milliseconds since the unix
+    /// epoch at which it was detected
+    ShotReported { lane: LaneId, x: f32, y: f32, detected_at_ms: u64 },
+    /// A shot was registered on the scene of a lane, in scene-local meters
+    ShotRegistered { lane: LaneId, x: f32, y: f32, target_id: Option<u32> },
+    /// Command sent by an operator client such as `ltctl`, with the operator token the server was started with
+    Operator { token: String, command: OperatorCommand },
+    /// Answer to an operator command, sent only to the client that issued it
+    OperatorReply(OperatorReply),
+}
+
+impl NetworkMessage {
+    /// Name of the message variant, used to label statistics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NetworkMessage::Ping { .. } => "Ping",
+            NetworkMessage::Pong { .. } => "Pong",
+            NetworkMessage::JoinLane { .. } => "JoinLane",
+            NetworkMessage::SceneConfiguration { .. } => "SceneConfiguration",
+            NetworkMessage::MatchStateChanged { .. } => "MatchStateChanged",
+            NetworkMessage::TargetPlaced { .. } => "TargetPlaced",
+            NetworkMessage::TargetRemoved { .. } => "TargetRemoved",
+            NetworkMessage::ScoreUpdated { .. } => "ScoreUpdated",
+            NetworkMessage::RecordingChanged { .. } => "RecordingChanged",
+            NetworkMessage::ShotReported { .. } => "ShotReported",
+            NetworkMessage::ShotRegistered { .. } => "ShotRegistered",
+            NetworkMessage::Operator { .. } => "Operator",
+            NetworkMessage::OperatorReply(_) => "OperatorReply",
+        }
+    }
+
+    /// Lane the message is about, if it only concerns one lane
+    pub fn lane(&self) -> Option<LaneId> {
+        match self {
+            NetworkMessage::JoinLane { lane }
+            | NetworkMessage::SceneConfiguration { lane, .. }
+            | NetworkMessage::MatchStateChanged { lane, .. }
+            | NetworkMessage::TargetPlaced { lane, .. }
+            | NetworkMessage::TargetRemoved { lane, .. }
+            | NetworkMessage::ScoreUpdated { lane, .. }
+            | NetworkMessage::RecordingChanged { lane, .. }
+            | NetworkMessage::ShotReported { lane, .. }
+            | NetworkMessage::ShotRegistered { lane, .. } => Some(*lane),
+            NetworkMessage::Ping { .. }
+            | NetworkMessage::Pong { .. }
+            | NetworkMessage::Operator { .. }
+            | NetworkMessage::OperatorReply(_) => None,
+        }
+    }
+}
+
+/// Phase of a match run by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MatchPhase {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+    Stopped,
+}
+
+/// A target placed on the scene, positioned in scene-local meters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+}
+
+/// Shots and hits counted in the current match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ScoreSummary {
+    pub shots: u32,
+    pub hits: u32,
+}
+
+/// A client connected to the server
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub id: u64,
+    /// Lane the client joined, operator and spectator clients follow all lanes
+    pub lane: Option<LaneId>,
+    /// Round trip time of the last ping in milliseconds, if a pong has been received yet.
+    pub rtt_ms: Option<f32>,
+}
+
+/// Commands an operator can run on the server, over QUIC or the admin API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OperatorCommand {
+    ListClients,
+    GetStatus,
+    /// Command acting on a single lane
+    Lane { lane: LaneId, command: LaneCommand },
+}
+
+/// Operator commands acting on the state of one lane
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LaneCommand {
+    GetScene,
+    UpdateScene(SceneUpdate),
+    GetMatch,
+    StartMatch,
+    PauseMatch,
+    StopMatch,
+    ListTargets,
+    PlaceTarget(TargetSpec),
+    RemoveTarget { id: u32 },
+    /// Replace all targets of the lane with the given layout
+    LoadTargets(Vec<TargetSpec>),
+    GetScores,
+    GetRecording,
+    /// Ask the terminals of the lane to record their thermal frames
+    StartRecording,
+    StopRecording,
+}
+
+/// Answer of the server to an operator command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OperatorReply {
+    Clients(Vec<ClientInfo>),
+    Status(ServerStatus),
+    Scene(SceneConfiguration),
+    Match(MatchStatus),
+    Targets(Vec<TargetInfo>),
+    Target(TargetInfo),
+    Scores(ScoreSummary),
+    Recording(RecordingStatus),
+    Rejected(OperatorError),
+}
+
+/// Reason an operator command was not applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperatorError {
+    InvalidArgument(String),
+    NotFound(String),
+    /// The command does not apply to the current state, such as pausing a stopped match
+    Conflict(String),
+    /// The client did not give the operator token
+    Unauthorized(String),
+}
+
+impl std::fmt::Display for OperatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperatorError::InvalidArgument(message)
+            | OperatorError::NotFound(message)
+            | OperatorError::Conflict(message)
+            | OperatorError::Unauthorized(message) => f.write_str(message),
+        }
+    }
+}
+
+/// Scene values to change, fields left empty keep their current value
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneUpdate {
+    pub target_projection_distance: Option<f32>,
+    pub scene_width: Option<f32>,
+}
+
+/// A target to place on the scene, as found in target layout files
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetSpec {
+    pub x: f32,
+    pub y: f32,
+    #[serde(default = "default_target_radius")]
+    pub radius: f32,
+}
+
+/// Radius of targets placed without an explicit one, in meters
+pub const DEFAULT_TARGET_RADIUS: f32 = 0.5;
+
+fn default_target_radius() -> f32 {
+    DEFAULT_TARGET_RADIUS
+}
+
+/// Match phase and time spent running
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchStatus {
+    pub phase: MatchPhase,
+    pub elapsed_secs: f32,
+}
+
+/// Whether the terminals of a lane are asked to record their thermal frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub recording: bool,
+}
+
+/// Overview of one lane shown by operator tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LaneStatus {
+    pub lane: LaneId,
+    #[serde(rename = "match")]
+    pub match_status: MatchStatus,
+    pub scene: SceneConfiguration,
+    pub targets: u32,
+    pub scores: ScoreSummary,
+    pub recording: bool,
+}
+
+/// Overview of the server state shown by operator tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub clients: u32,
+    pub lanes: Vec<LaneStatus>,
 }
 
 /// Server configuration
 pub const SERVER_PORT: u16 = 6000;
 pub const SERVER_HOST: &str = "0.0.0.0";
+/// Default port of the local HTTP admin API
+pub const ADMIN_PORT: u16 = 6080;
+/// Default port of the spectator web scoreboard
+pub const SCOREBOARD_PORT: u16 = 6081;
+/// Default port of the metrics endpoint
+pub const METRICS_PORT: u16 = 9464;
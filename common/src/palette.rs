@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::ThermalFrame;
+use crate::radiometry::{clipped_range, RadiometricCalibration};
+
+/// Share of the coldest and hottest pixels ignored by the automatic range, so dead pixels do not flatten it
+const AUTO_RANGE_CLIP: f32 = 0.01;
+
+/// False-color palette used to show thermal frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    #[default]
+    Iron,
+    Rainbow,
+    Grayscale,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Iron, Palette::Rainbow, Palette::Grayscale];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Iron => "Iron",
+            Palette::Rainbow => "Rainbow",
+            Palette::Grayscale => "Grayscale",
+        }
+    }
+
+    fn stops(&self) -> &'static [[u8; 3]] {
+        match self {
+            Palette::Iron => &[[0, 0, 0], [32, 0, 140], [180, 0, 150], [255, 110, 0], [255, 220, 0], [255, 255, 255]],
+            Palette::Rainbow => &[[0, 0, 255], [0, 255, 255], [0, 255, 0], [255, 255, 0], [255, 0, 0]],
+            Palette::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Color of a value between 0 (coldest) and 1 (hottest)
+    pub fn color(&self, value: f32) -> [u8; 3] {
+        let stops = self.stops();
+        let position = value.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position.floor() as usize).min(stops.len() - 2);
+        let blend = position - index as f32;
+        let (from, to) = (stops[index], stops[index + 1]);
+        std::array::from_fn(|channel| {
+            (from[channel] as f32 + (to[channel] as f32 - from[channel] as f32) * blend).round() as u8
+        })
+    }
+}
+
+/// Range of temperatures spread over the palette
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum TemperatureRange {
+    /// Follow the coldest and hottest parts of each frame
+    #[default]
+    Auto,
+    /// Fixed bounds in degrees Celsius
+    Manual { min: f32, max: f32 },
+}
+
+impl TemperatureRange {
+    /// Bounds in sensor counts used to show the given frame, read through the calibration of its camera
+    pub fn bounds(&self, frame: &ThermalFrame, calibration: &RadiometricCalibration) -> (f32, f32) {
+        match *self {
+            TemperatureRange::Manual { min, max } => (calibration.to_counts(min), calibration.to_counts(max)),
+            TemperatureRange::Auto => {
+                let clip = (frame.pixels.len() as f32 * AUTO_RANGE_CLIP) as usize;
+                clipped_range(&frame.pixels, clip).unwrap_or((0.0, 1.0))
+            }
+        }
+    }
+}
+
+/// Render a frame as RGBA8 pixels in row-major order
+pub fn colorize(frame: &ThermalFrame, palette: Palette, range: TemperatureRange, calibration: &RadiometricCalibration) -> Vec<u8> {
+    let (min, max) = range.bounds(frame, calibration);
+    let span = (max - min).max(1.0);
+    frame
+        .pixels
+        .iter()
+        .flat_map(|&pixel| {
+            let [r, g, b] = palette.color((pixel as f32 - min) / span);
+            [r, g, b, 255]
+        })
+        .collect()
+}
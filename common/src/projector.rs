@@ -0,0 +1,300 @@
+use bevy::prelude::{Mat3, Mat4, UVec2, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::detection::CameraMapping;
+use crate::frame::ThermalFrame;
+use crate::homography::{estimate_homography, Homography, HomographyError};
+use crate::lens::warm_areas;
+use crate::radiometry::RadiometricCalibration;
+
+/// Tuning of the automatic projector calibration
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PatternConfig {
+    /// Number of dots per row and per column
+    pub grid: UVec2,
+    /// Distance of the outer dots from the edges of the output, as a fraction of its size
+    pub margin: f32,
+    /// Time each dot is projected before the warming of the wall is measured
+    pub dwell_secs: f32,
+    /// Kelvin a dot must warm the wall by to be found on the camera image
+    pub min_rise: f32,
+    /// Largest reprojection error in meters for the fit to be trusted
+    pub max_rms_error: f32,
+}
+
+impl Default for PatternConfig {
+    fn default() -> Self {
+        Self {
+            grid: UVec2::new(4, 3),
+            margin: 0.1,
+            dwell_secs: 1.5,
+            min_rise: 1.0,
+            max_rms_error: 0.02,
+        }
+    }
+}
+
+/// Output pixels of the dots of the pattern, row by row from the top left
+pub fn pattern_dots(resolution: UVec2, config: &PatternConfig) -> Vec<Vec2> {
+    let grid = config.grid.max(UVec2::splat(2));
+    let size = resolution.as_vec2();
+    let first = size * config.margin;
+    let step = (size - first * 2.0) / (grid - 1).as_vec2();
+    (0..grid.y)
+        .flat_map(|row| (0..grid.x).map(move |column| first + step * Vec2::new(column as f32, row as f32)))
+        .collect()
+}
+
+/// Errors raised when the projected dots cannot be turned into a mapping
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectorCalibrationError {
+    /// Fewer than four dots were seen by the camera
+    TooFewDots { detected: usize, projected: usize },
+    Fit(HomographyError),
+}
+
+impl std::fmt::Display for ProjectorCalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectorCalibrationError::TooFewDots { detected, projected } => {
+                write!(f, "the camera found {} of {} projected dots, at least 4 are needed", detected, projected)
+            }
+            ProjectorCalibrationError::Fit(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProjectorCalibrationError {}
+
+/// Mapping of projector output pixels to the scene solved from the dots, with its quality
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectorFit {
+    pub homography: Homography,
+    pub detected: usize,
+    pub projected: usize,
+    /// Distance between where each detected dot was seen and where the mapping puts it, in meters
+    pub errors: Vec<f32>,
+    pub rms_error: f32,
+    pub max_error: f32,
+}
+
+impl ProjectorFit {
+    /// Whether every dot was seen and the mapping explains them within the configured error
+    pub fn is_acceptable(&self, config: &PatternConfig) -> bool {
+        self.detected == self.projected && self.rms_error <= config.max_rms_error
+    }
+}
+
+/// How far the calibration got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationProgress {
+    /// Dots measured so far
+    pub measured: usize,
+    /// Dots among them that the camera found
+    pub detected: usize,
+    pub projected: usize,
+}
+
+/// Projects a pattern of dots one at a time and finds where each of them warms the wall
+///
+/// The caller projects [`ProjectorCalibrator::current_dot`] and feeds every frame of the camera.
+/// The first frame after a dot is switched on is the baseline, and once the dot has dwelled the
+/// warmest area that rose since then is taken as its response. Heat left by the previous dots is
+/// part of the baseline, so the dots need not cool down in between.
+pub struct ProjectorCalibrator {
+    pub config: PatternConfig,
+    mapping: CameraMapping,
+    calibration: RadiometricCalibration,
+    dots: Vec<Vec2>,
+    /// Scene-local position each measured dot was found at
+    found: Vec<Option<Vec2>>,
+    baseline: Option<ThermalFrame>,
+}
+
+impl ProjectorCalibrator {
+    /// A calibration of a projector with the given output resolution, seen through the camera mapping
+    pub fn new(
+        resolution: UVec2,
+        config: PatternConfig,
+        mapping: CameraMapping,
+        calibration: RadiometricCalibration,
+    ) -> Self {
+        Self {
+            config,
+            mapping,
+            calibration,
+            dots: pattern_dots(resolution, &config),
+            found: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    /// Output pixel of the dot to project now, none once every dot was measured
+    pub fn current_dot(&self) -> Option<Vec2> {
+        self.dots.get(self.found.len()).copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.found.len() == self.dots.len()
+    }
+
+    pub fn progress(&self) -> CalibrationProgress {
+        CalibrationProgress {
+            measured: self.found.len(),
+            detected: self.found.iter().flatten().count(),
+            projected: self.dots.len(),
+        }
+    }
+
+    /// Take a camera frame captured while the current dot is projected
+    pub fn update(&mut self, frame: &ThermalFrame) {
+        if self.is_finished() {
+            return;
+        }
+        let dwell = Duration::from_secs_f32(self.config.dwell_secs.max(0.0));
+        let baseline = match &self.baseline {
+            Some(baseline) if frame.timestamp >= baseline.timestamp && baseline.resolution == frame.resolution => baseline,
+            _ => {
+                self.baseline = Some(frame.clone());
+                return;
+            }
+        };
+        if frame.timestamp - baseline.timestamp < dwell {
+            return;
+        }
+        let found = find_response(baseline, frame, &self.calibration, self.config.min_rise)
+            .map(|pixel| self.mapping.to_scene(pixel));
+        self.found.push(found);
+        self.baseline = None;
+    }
+
+    /// Mapping of the output pixels to the scene that explains the dots found
+    pub fn fit(&self) -> Result<ProjectorFit, ProjectorCalibrationError> {
+        let pairs: Vec<(Vec2, Vec2)> = self
+            .dots
+            .iter()
+            .zip(&self.found)
+            .filter_map(|(dot, found)| found.map(|position| (*dot, position)))
+            .collect();
+        if pairs.len() < 4 {
+            return Err(ProjectorCalibrationError::TooFewDots { detected: pairs.len(), projected: self.dots.len() });
+        }
+        let fit = estimate_homography(&pairs).map_err(ProjectorCalibrationError::Fit)?;
+        Ok(ProjectorFit {
+            homography: fit.homography,
+            detected: pairs.len(),
+            projected: self.dots.len(),
+            max_error: fit.errors.iter().copied().fold(0.0, f32::max),
+            errors: fit.errors,
+            rms_error: fit.rms_error,
+        })
+    }
+}
+
+/// Pixel centroid of the area that warmed the most between the two frames, if any rose by `min_rise` kelvin
+fn find_response(
+    baseline: &ThermalFrame,
+    frame: &ThermalFrame,
+    calibration: &RadiometricCalibration,
+    min_rise: f32,
+) -> Option<Vec2> {
+    let rise: Vec<f32> = frame
+        .pixels
+        .iter()
+        .zip(&baseline.pixels)
+        .map(|(&pixel, &before)| (pixel as f32 - before as f32) * calibration.gain)
+        .collect();
+
+    warm_areas(&rise, frame.resolution, min_rise)
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(centroid, _)| centroid)
+}
+
+/// Corners of the unit square, clockwise from the top left with y down
+pub(crate) const UNIT_CORNERS: [Vec2; 4] = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+
+/// Keystone correction of the projector output
+///
+/// The picture the terminal renders is drawn into the quad given by `corners` instead of the whole
+/// output, so that a projector hitting the wall at an angle still throws a rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keystone {
+    /// Where the corners of the picture go, clockwise from the top left, as fractions of the output size with y down
+    pub corners: [Vec2; 4],
+}
+
+impl Default for Keystone {
+    fn default() -> Self {
+        Self { corners: UNIT_CORNERS }
+    }
+}
+
+impl Keystone {
+    pub fn is_identity(&self) -> bool {
+        self.corners == UNIT_CORNERS
+    }
+
+    /// Mapping of picture fractions to output fractions, none if the corners do not form a quad
+    pub fn homography(&self) -> Option<Homography> {
+        let pairs: Vec<(Vec2, Vec2)> = UNIT_CORNERS.into_iter().zip(self.corners).collect();
+        estimate_homography(&pairs).ok().map(|fit| fit.homography)
+    }
+
+    /// Warp of clip space that draws the picture rendered by a camera into the corners
+    ///
+    /// Depth is left alone, so it is scaled by the same factor for every fragment of an output pixel
+    /// and keeps its order.
+    pub fn clip_warp(&self) -> Option<Mat4> {
+        let homography = Mat3::from_cols_array_2d(&self.homography()?.matrix).transpose();
+        // Normalized device coordinates have y up and span -1 to 1
+        let to_fraction = Mat3::from_cols(Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, -0.5, 0.0), Vec3::new(0.5, 0.5, 1.0));
+        let to_device = Mat3::from_cols(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, -2.0, 0.0), Vec3::new(-1.0, 1.0, 1.0));
+        let warp = to_device * homography * to_fraction;
+        Some(Mat4::from_cols(
+            Vec4::new(warp.x_axis.x, warp.x_axis.y, 0.0, warp.x_axis.z),
+            Vec4::new(warp.y_axis.x, warp.y_axis.y, 0.0, warp.y_axis.z),
+            Vec4::Z,
+            Vec4::new(warp.z_axis.x, warp.z_axis.y, 0.0, warp.z_axis.z),
+        ))
+    }
+
+    /// Keystone that throws every scene-local position of the picture onto the output pixel the mapping
+    /// puts it at, the picture spanning `extent` meters centered on the scene origin
+    pub fn through(mapping: &Homography, resolution: UVec2, extent: Vec2) -> Option<Keystone> {
+        let inverse = mapping.inverse()?;
+        let corners = [Vec2::new(-1.0, 1.0), Vec2::ONE, Vec2::new(1.0, -1.0), Vec2::NEG_ONE]
+            .map(|corner| inverse.apply(corner * extent / 2.0) / resolution.as_vec2());
+        let keystone = Keystone { corners };
+        keystone.homography().is_some().then_some(keystone)
+    }
+
+    /// Keystone that throws the picture as the largest upright rectangle of the output aspect ratio
+    /// inside the area the projector covers, given the mapping of its output pixels to the scene
+    pub fn square_up(mapping: &Homography, resolution: UVec2) -> Option<Keystone> {
+        let size = resolution.as_vec2();
+        let [top_left, top_right, bottom_right, bottom_left] = UNIT_CORNERS.map(|corner| mapping.apply(corner * size));
+        let left = top_left.x.max(bottom_left.x);
+        let right = top_right.x.min(bottom_right.x);
+        let top = top_left.y.min(top_right.y);
+        let bottom = bottom_left.y.max(bottom_right.y);
+        if right <= left || top <= bottom {
+            return None;
+        }
+
+        let center = Vec2::new(left + right, top + bottom) / 2.0;
+        let aspect = size.x / size.y;
+        let mut half = Vec2::new(right - left, top - bottom) / 2.0;
+        if half.x > half.y * aspect {
+            half.x = half.y * aspect;
+        } else {
+            half.y = half.x / aspect;
+        }
+        let inverse = mapping.inverse()?;
+        let corners = [Vec2::new(-1.0, 1.0), Vec2::ONE, Vec2::new(1.0, -1.0), Vec2::NEG_ONE]
+            .map(|corner| inverse.apply(center + corner * half) / size);
+        Some(Keystone { corners })
+    }
+}
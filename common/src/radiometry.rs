@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::frame::ThermalFrame;
+
+/// Linear mapping of raw sensor counts to temperatures for one camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RadiometricCalibration {
+    /// Kelvin per sensor count
+    pub gain: f32,
+    /// Temperature in degrees Celsius read for zero counts
+    pub offset: f32,
+}
+
+impl Default for RadiometricCalibration {
+    fn default() -> Self {
+        // Sensors reporting 1/64 K starting from absolute zero
+        Self {
+            gain: 1.0 / 64.0,
+            offset: -273.15,
+        }
+    }
+}
+
+impl RadiometricCalibration {
+    pub fn to_celsius(&self, counts: f32) -> f32 {
+        counts * self.gain + self.offset
+    }
+
+    pub fn to_counts(&self, celsius: f32) -> f32 {
+        (celsius - self.offset) / self.gain
+    }
+}
+
+/// Compensation of changes of the whole sensor reading that do not come from the scene
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriftConfig {
+    pub enabled: bool,
+    /// Weight of each new frame in the tracked sensor level, between 0 and 1
+    pub rate: f32,
+    /// Change of the sensor level from one frame to the next, in kelvin, taken as a flat-field shutter event
+    pub shutter_jump: f32,
+}
+
+impl Default for DriftConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate: 0.2,
+            shutter_jump: 0.5,
+        }
+    }
+}
+
+/// Correction to apply to the pixels of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DriftCorrection {
+    /// Counts to add to every pixel to bring the frame back to the reference level
+    pub offset: f32,
+    /// The sensor level jumped since the previous frame, as after a flat-field shutter event
+    pub shutter: bool,
+}
+
+/// Keeps frames at the sensor level of the first one despite ambient drift and shutter events
+///
+/// The camera body warming up or cooling down shifts the readings of all pixels, and a flat-field
+/// correction shifts them at once. The median of a frame follows these shifts but not the laser
+/// spots or people covering less than half of the image, so it is tracked as the sensor level.
+pub struct DriftCompensator {
+    pub config: DriftConfig,
+    /// Sensor level of the first frame, in counts
+    reference: Option<f32>,
+    /// Sensor level tracked over the frames, in counts
+    level: f32,
+}
+
+impl DriftCompensator {
+    pub fn new(config: DriftConfig) -> Self {
+        Self { config, reference: None, level: 0.0 }
+    }
+
+    /// Forget the reference level, the next frame sets a new one
+    pub fn reset(&mut self) {
+        self.reference = None;
+    }
+
+    /// Counts currently added to the pixels of each frame
+    pub fn offset(&self) -> f32 {
+        self.reference.map_or(0.0, |reference| reference - self.level)
+    }
+
+    /// Track the sensor level with a new frame and return the correction for it
+    pub fn update(&mut self, frame: &ThermalFrame, calibration: &RadiometricCalibration) -> DriftCorrection {
+        if !self.config.enabled || frame.pixels.is_empty() {
+            return DriftCorrection::default();
+        }
+
+        let level = median(&frame.pixels);
+        if self.reference.is_none() {
+            self.reference = Some(level);
+            self.level = level;
+            return DriftCorrection::default();
+        }
+
+        let shutter = ((level - self.level) * calibration.gain).abs() >= self.config.shutter_jump;
+        if shutter {
+            self.level = level;
+        } else {
+            self.level += (level - self.level) * self.config.rate.clamp(0.0, 1.0);
+        }
+        DriftCorrection { offset: self.offset(), shutter }
+    }
+}
+
+/// Median reading of a frame's pixels
+pub(crate) fn median(pixels: &[u16]) -> f32 {
+    let mut pixels = pixels.to_vec();
+    let middle = pixels.len() / 2;
+    *pixels.select_nth_unstable(middle).1 as f32
+}
+
+/// Readings of the pixels ranked `clip` from the coldest and from the hottest, found without sorting the frame
+pub(crate) fn clipped_range(pixels: &[u16], clip: usize) -> Option<(f32, f32)> {
+    let mut pixels = pixels.to_vec();
+    let high_index = pixels.len().checked_sub(clip + 1)?;
+    let low_index = clip.min(high_index);
+    let (_, &mut low, hotter) = pixels.select_nth_unstable(low_index);
+    // The high reading is among the pixels left hotter than the low one
+    let high = match high_index - low_index {
+        0 => low,
+        offset => *hotter.select_nth_unstable(offset - 1).1,
+    };
+    Some((low as f32, high as f32))
+}
@@ -0,0 +1,328 @@
+use bevy::prelude::UVec2;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::frame::{FrameSource, FrameSourceError, ThermalFrame};
+use crate::radiometry::RadiometricCalibration;
+
+/// Extension of thermal recording files
+pub const RECORDING_EXTENSION: &str = "ltrec";
+
+const MAGIC: &[u8; 4] = b"LTRC";
+const VERSION: u16 = 1;
+const HEADER_LEN: u64 = 24;
+/// Frame interval assumed for recordings too short to measure one
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(40);
+
+/// What a recording file says about the camera that produced it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordingHeader {
+    pub resolution: UVec2,
+    pub radiometry: RadiometricCalibration,
+}
+
+impl RecordingHeader {
+    /// Size of one frame record, its timestamp followed by its pixels
+    fn record_len(&self) -> u64 {
+        Self::checked_record_len(self.resolution).expect("header resolutions are checked on read")
+    }
+
+    /// Record size of a resolution, `None` when it does not fit a file offset
+    fn checked_record_len(resolution: UVec2) -> Option<u64> {
+        (resolution.x as u64)
+            .checked_mul(resolution.y as u64)?
+            .checked_mul(2)?
+            .checked_add(8)
+            .filter(|len| usize::try_from(*len).is_ok())
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN as usize);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&self.resolution.x.to_le_bytes());
+        bytes.extend_from_slice(&self.resolution.y.to_le_bytes());
+        bytes.extend_from_slice(&self.radiometry.gain.to_le_bytes());
+        bytes.extend_from_slice(&self.radiometry.offset.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; HEADER_LEN as usize]) -> Result<Self, String> {
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+        if &bytes[0..4] != MAGIC {
+            return Err("not a thermal recording".to_string());
+        }
+        if u16_at(4) != VERSION {
+            return Err(format!("unsupported recording version {}", u16_at(4)));
+        }
+        let resolution = UVec2::new(u32_at(8), u32_at(12));
+        if resolution.x == 0 || resolution.y == 0 {
+            return Err("recording has an empty resolution".to_string());
+        }
+        if Self::checked_record_len(resolution).is_none() {
+            return Err(format!("recording resolution {}x{} is too large", resolution.x, resolution.y));
+        }
+        Ok(Self {
+            resolution,
+            radiometry: RadiometricCalibration {
+                gain: f32::from_bits(u32_at(16)),
+                offset: f32::from_bits(u32_at(20)),
+            },
+        })
+    }
+}
+
+/// Writes thermal frames to a recording file
+///
+/// Timestamps are stored relative to the first recorded frame, so every recording starts at zero.
+pub struct FrameRecorder {
+    path: PathBuf,
+    header: RecordingHeader,
+    writer: BufWriter<File>,
+    first_timestamp: Option<Duration>,
+    frames: u32,
+}
+
+impl FrameRecorder {
+    pub fn create(path: impl AsRef<Path>, header: RecordingHeader) -> Result<Self, FrameSourceError> {
+        let path = path.as_ref().to_path_buf();
+        let mut writer = BufWriter::new(File::create(&path)?);
+        writer.write_all(&header.to_bytes())?;
+        Ok(Self {
+            path,
+            header,
+            writer,
+            first_timestamp: None,
+            frames: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of frames written so far
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn write_frame(&mut self, frame: &ThermalFrame) -> Result<(), FrameSourceError> {
+        if frame.resolution != self.header.resolution {
+            return Err(FrameSourceError::InvalidConfiguration(format!(
+                "frame resolution {} does not match the recording resolution {}",
+                frame.resolution, self.header.resolution
+            )));
+        }
+        let first = *self.first_timestamp.get_or_insert(frame.timestamp);
+        let timestamp = frame.timestamp.saturating_sub(first).as_micros() as u64;
+        self.writer.write_all(&timestamp.to_le_bytes())?;
+        self.writer.write_all(&frame.to_le_bytes())?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flush the frames written so far to disk
+    pub fn finish(mut self) -> Result<PathBuf, FrameSourceError> {
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// A recording file opened for random access to its frames
+pub struct Recording {
+    path: PathBuf,
+    header: RecordingHeader,
+    file: File,
+    timestamps: Vec<Duration>,
+}
+
+impl Recording {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FrameSourceError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let mut header_bytes = [0; HEADER_LEN as usize];
+        file.read_exact(&mut header_bytes)
+            .map_err(|_| FrameSourceError::InvalidRecording { path: path.clone(), reason: "missing header".to_string() })?;
+        let header = RecordingHeader::from_bytes(&header_bytes)
+            .map_err(|reason| FrameSourceError::InvalidRecording { path: path.clone(), reason })?;
+
+        let body_len = file.metadata()?.len() - HEADER_LEN;
+        if !body_len.is_multiple_of(header.record_len()) {
+            return Err(FrameSourceError::TruncatedFrame { path });
+        }
+
+        let frames = body_len / header.record_len();
+        let mut timestamps = Vec::with_capacity(frames as usize);
+        let mut timestamp = [0; 8];
+        for index in 0..frames {
+            file.seek(SeekFrom::Start(HEADER_LEN + index * header.record_len()))?;
+            file.read_exact(&mut timestamp)?;
+            timestamps.push(Duration::from_micros(u64::from_le_bytes(timestamp)));
+        }
+
+        Ok(Self { path, header, file, timestamps })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> RecordingHeader {
+        self.header
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    /// Timestamp of the last frame
+    pub fn duration(&self) -> Duration {
+        self.timestamps.last().copied().unwrap_or_default()
+    }
+
+    pub fn timestamp(&self, index: usize) -> Option<Duration> {
+        self.timestamps.get(index).copied()
+    }
+
+    /// Index of the first frame at or after the given time
+    pub fn index_at(&self, time: Duration) -> usize {
+        self.timestamps.partition_point(|timestamp| *timestamp < time)
+    }
+
+    pub fn read_frame(&mut self, index: usize) -> Result<ThermalFrame, FrameSourceError> {
+        let timestamp = self.timestamp(index).ok_or(FrameSourceError::Empty)?;
+        let offset = HEADER_LEN + index as u64 * self.header.record_len() + 8;
+        let mut bytes = vec![0; (self.header.record_len() - 8) as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut bytes)?;
+        ThermalFrame::from_le_bytes(self.header.resolution, &bytes, timestamp)
+            .ok_or_else(|| FrameSourceError::TruncatedFrame { path: self.path.clone() })
+    }
+}
+
+/// Plays a recording back as a frame source, with pause, seek and speed control
+///
+/// Frames keep the spacing they were recorded with, divided by the speed. Their timestamps
+/// keep increasing across pauses, seeks and loops, as if they came from a live camera.
+pub struct PlaybackSource {
+    recording: Recording,
+    /// Index of the next frame to play
+    cursor: usize,
+    /// Timestamp given to the next frame
+    clock: Duration,
+    speed: f32,
+    paused: bool,
+    pub looping: bool,
+    /// Frame shown again while paused
+    current: Option<ThermalFrame>,
+}
+
+impl PlaybackSource {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, FrameSourceError> {
+        let recording = Recording::open(path)?;
+        if recording.is_empty() {
+            return Err(FrameSourceError::Empty);
+        }
+        Ok(Self {
+            recording,
+            cursor: 0,
+            clock: Duration::ZERO,
+            speed: 1.0,
+            paused: false,
+            looping: false,
+            current: None,
+        })
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Recording time of the frame shown last
+    pub fn position(&self) -> Duration {
+        self.current.as_ref().map(|frame| frame.timestamp).unwrap_or_default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) -> Result<(), FrameSourceError> {
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(FrameSourceError::InvalidConfiguration(format!("invalid playback speed {}", speed)));
+        }
+        self.speed = speed;
+        Ok(())
+    }
+
+    /// Continue playing from the first frame at or after the given recording time
+    pub fn seek(&mut self, time: Duration) {
+        self.cursor = self.recording.index_at(time).min(self.recording.len() - 1);
+        self.current = None;
+    }
+
+    /// Recording time between the given frame and the next one
+    fn interval_after(&self, index: usize) -> Duration {
+        match (self.recording.timestamp(index), self.recording.timestamp(index + 1)) {
+            (Some(current), Some(next)) if next > current => next - current,
+            _ if self.recording.len() > 1 => self.recording.duration() / (self.recording.len() as u32 - 1),
+            _ => DEFAULT_FRAME_INTERVAL,
+        }
+    }
+
+    fn advance_clock(&mut self, index: usize) -> Duration {
+        let timestamp = self.clock;
+        let interval = self.interval_after(index).as_nanos() as f64 / self.speed as f64;
+        self.clock += Duration::from_nanos(interval.round() as u64);
+        timestamp
+    }
+}
+
+impl FrameSource for PlaybackSource {
+    fn resolution(&self) -> UVec2 {
+        self.recording.header.resolution
+    }
+
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError> {
+        if self.paused && let Some(current) = &self.current {
+            let mut frame = current.clone();
+            let index = self.cursor.saturating_sub(1);
+            frame.timestamp = self.advance_clock(index);
+            return Ok(Some(frame));
+        }
+
+        if self.cursor >= self.recording.len() {
+            if !self.looping {
+                return Ok(None);
+            }
+            self.cursor = 0;
+        }
+
+        let index = self.cursor;
+        let mut frame = self.recording.read_frame(index)?;
+        self.current = Some(frame.clone());
+        self.cursor += 1;
+        frame.timestamp = self.advance_clock(index);
+        Ok(Some(frame))
+    }
+
+    fn playback(&mut self) -> Option<&mut PlaybackSource> {
+        Some(self)
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::prelude::{Resource, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::network::LaneId;
+use crate::validation::{moa, moa_offset};
+
+/// A shooter and the alignment of the training laser in their replica weapon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShooterProfile {
+    pub name: String,
+    /// Angle the laser points right of and above the sights, in minutes of angle
+    #[serde(default)]
+    pub zero: [f32; 2],
+}
+
+impl ShooterProfile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), zero: [0.0; 2] }
+    }
+
+    /// Scene-local offset of the laser from the point aimed at, for a scene at the given distance in meters
+    pub fn offset(&self, distance: f32) -> Vec2 {
+        Vec2::from(self.zero).map(|angle| moa_offset(angle, distance))
+    }
+
+    /// Where the sights were aimed at for a laser hit at the given scene-local position
+    pub fn correct(&self, position: Vec2, distance: f32) -> Vec2 {
+        position - self.offset(distance)
+    }
+
+    /// Zero the laser from a group fired at the given aiming point, the raw hits not being corrected
+    ///
+    /// Returns the new zero, or none when the group is empty.
+    pub fn zero_on(&mut self, group: &[Vec2], aim: Vec2, distance: f32) -> Option<Vec2> {
+        let impact = mean_point_of_impact(group)? - aim;
+        let zero = impact.map(|offset| moa(offset, distance));
+        self.zero = zero.to_array();
+        Some(zero)
+    }
+}
+
+/// Center of a group of hits
+pub fn mean_point_of_impact(group: &[Vec2]) -> Option<Vec2> {
+    (!group.is_empty()).then(|| group.iter().sum::<Vec2>() / group.len() as f32)
+}
+
+/// Shooters known to the terminal, stored as JSON next to it, and the one on each lane
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShooterProfiles {
+    pub profiles: Vec<ShooterProfile>,
+    /// Name of the profile of the shooter on each lane, shots on a lane without one are not corrected
+    pub lanes: BTreeMap<LaneId, String>,
+}
+
+impl ShooterProfiles {
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// Profile of the shooter on the lane
+    pub fn active(&self, lane: LaneId) -> Option<&ShooterProfile> {
+        let name = self.lanes.get(&lane)?;
+        self.profiles.iter().find(|profile| &profile.name == name)
+    }
+
+    pub fn active_mut(&mut self, lane: LaneId) -> Option<&mut ShooterProfile> {
+        let name = self.lanes.get(&lane)?;
+        self.profiles.iter_mut().find(|profile| &profile.name == name)
+    }
+
+    /// Put the shooter with the given profile on the lane, or nobody
+    pub fn set_active(&mut self, lane: LaneId, name: Option<String>) {
+        match name {
+            Some(name) => self.lanes.insert(lane, name),
+            None => self.lanes.remove(&lane),
+        };
+    }
+
+    /// Where the shooter on the lane aimed for a laser hit at the given scene-local position
+    pub fn correct(&self, lane: LaneId, position: Vec2, distance: f32) -> Vec2 {
+        self.active(lane).map_or(position, |profile| profile.correct(position, distance))
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::{UVec2, Vec2};
+use std::time::Duration;
+
+use crate::config::{CameraConfiguration, SceneConfiguration};
+use crate::detection::CameraMapping;
+use crate::frame::{frame_interval, FrameSource, FrameSourceError, NoiseRng, ThermalFrame};
+use crate::radiometry::RadiometricCalibration;
+
+/// Heat left on the wall by a laser hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitProfile {
+    /// Temperature rise at the center of the spot right after the hit, in kelvin
+    pub peak: f32,
+    /// Standard deviation of the gaussian heat profile, in meters
+    pub radius: f32,
+    /// Time for the spot to cool down to 1/e of its peak
+    pub decay: Duration,
+}
+
+impl Default for HitProfile {
+    fn default() -> Self {
+        Self {
+            peak: 25.0,
+            radius: 0.05,
+            decay: Duration::from_millis(800),
+        }
+    }
+}
+
+/// A laser hit at a scene-local position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LaserHit {
+    pub position: Vec2,
+    /// Time of the hit since the start of the simulation
+    pub at: Duration,
+    pub profile: HitProfile,
+}
+
+impl LaserHit {
+    /// Temperature rise at the given scene-local position and time
+    fn heat(&self, position: Vec2, time: Duration) -> f32 {
+        let Some(age) = time.checked_sub(self.at) else {
+            return 0.0;
+        };
+        let cooling = (-age.as_secs_f32() / self.profile.decay.as_secs_f32().max(f32::EPSILON)).exp();
+        let spread = 2.0 * self.profile.radius * self.profile.radius;
+        self.profile.peak * cooling * (-position.distance_squared(self.position) / spread).exp()
+    }
+}
+
+/// A person or any warm object crossing the scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WarmBody {
+    /// Scene-local position of the center of the body when it appears
+    pub start: Vec2,
+    /// Speed in meters per second
+    pub velocity: Vec2,
+    /// Width and height in meters
+    pub size: Vec2,
+    pub temperature: f32,
+    pub appears_at: Duration,
+    pub leaves_at: Duration,
+}
+
+impl WarmBody {
+    fn covers(&self, position: Vec2, time: Duration) -> bool {
+        if time < self.appears_at || time >= self.leaves_at {
+            return false;
+        }
+        let center = self.start + self.velocity * (time - self.appears_at).as_secs_f32();
+        let offset = (position - center).abs();
+        offset.x <= self.size.x / 2.0 && offset.y <= self.size.y / 2.0
+    }
+}
+
+/// Renders thermal frames of a scene with laser hits, warm bodies and sensor defects
+///
+/// Hits and bodies are placed in scene-local meters, so scripted shots can be compared with the
+/// positions the detector reports. The scene is seen through the lens of the camera configuration.
+pub struct ThermalSimulator {
+    mapping: CameraMapping,
+    frame_interval: Duration,
+    /// Conversion of the rendered temperatures to sensor counts
+    pub radiometry: RadiometricCalibration,
+    /// Temperature of the wall in degrees Celsius
+    pub background_temperature: f32,
+    /// Maximum deviation of the uniform sensor noise, in kelvin
+    pub noise: f32,
+    /// Pixels that always read zero
+    pub dead_pixels: Vec<UVec2>,
+    /// Change of the reading of the whole sensor as the camera warms up, in kelvin per second
+    pub drift: f32,
+    /// Times at which a flat-field correction shifts the reading of the whole sensor, by the given kelvin
+    pub shutter_steps: Vec<(Duration, f32)>,
+    pub bodies: Vec<WarmBody>,
+    pub hits: Vec<LaserHit>,
+    /// Heat profile of the hits added with [`ThermalSimulator::shoot`]
+    pub hit_profile: HitProfile,
+    rng: NoiseRng,
+    frame_index: u32,
+}
+
+impl ThermalSimulator {
+    /// A simulator for the camera and scene width of the given configurations
+    pub fn new(camera: &CameraConfiguration, scene: &SceneConfiguration, fps: f32) -> Result<Self, FrameSourceError> {
+        Ok(Self {
+            mapping: camera.mapping(scene.scene_width),
+            frame_interval: frame_interval(fps)?,
+            radiometry: camera.radiometry,
+            background_temperature: 20.0,
+            noise: 0.0,
+            dead_pixels: Vec::new(),
+            drift: 0.0,
+            shutter_steps: Vec::new(),
+            bodies: Vec::new(),
+            hits: Vec::new(),
+            hit_profile: HitProfile::default(),
+            rng: NoiseRng::new(0),
+            frame_index: 0,
+        })
+    }
+
+    pub fn mapping(&self) -> CameraMapping {
+        self.mapping
+    }
+
+    /// Script a laser hit at the given scene-local position
+    pub fn shoot(&mut self, at: Duration, position: Vec2) {
+        self.hits.push(LaserHit { position, at, profile: self.hit_profile });
+    }
+
+    /// Restart the noise sequence, simulators with the same seed render the same frames
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = NoiseRng::new(seed);
+    }
+
+    fn sample_noise(&mut self) -> f32 {
+        if self.noise <= 0.0 {
+            return 0.0;
+        }
+        self.rng.next_signed_unit() * self.noise
+    }
+
+    /// Temperature in degrees Celsius at a scene-local position, without sensor noise
+    pub fn temperature_at(&self, position: Vec2, time: Duration) -> f32 {
+        let surface = self
+            .bodies
+            .iter()
+            .filter(|body| body.covers(position, time))
+            .map(|body| body.temperature)
+            .fold(self.background_temperature, f32::max);
+        surface + self.hits.iter().map(|hit| hit.heat(position, time)).sum::<f32>()
+    }
+
+    /// Error of the whole sensor at the given time, in kelvin
+    pub fn sensor_offset(&self, time: Duration) -> f32 {
+        let steps: f32 = self.shutter_steps.iter().filter(|(at, _)| *at <= time).map(|(_, step)| step).sum();
+        self.drift * time.as_secs_f32() + steps
+    }
+}
+
+impl FrameSource for ThermalSimulator {
+    fn resolution(&self) -> UVec2 {
+        self.mapping.resolution
+    }
+
+    fn next_frame(&mut self) -> Result<Option<ThermalFrame>, FrameSourceError> {
+        let time = self.frame_interval * self.frame_index;
+        let resolution = self.mapping.resolution;
+        let sensor_offset = self.sensor_offset(time);
+        let mut frame = ThermalFrame::filled(resolution, 0, time);
+
+        for y in 0..resolution.y {
+            for x in 0..resolution.x {
+                let position = self.mapping.to_scene(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+                let temperature = self.temperature_at(position, time) + sensor_offset + self.sample_noise();
+                let counts = self.radiometry.to_counts(temperature).round().clamp(0.0, u16::MAX as f32);
+                frame.set(x, y, counts as u16);
+            }
+        }
+        for pixel in &self.dead_pixels {
+            if pixel.x < resolution.x && pixel.y < resolution.y {
+                frame.set(pixel.x, pixel.y, 0);
+            }
+        }
+
+        self.frame_index += 1;
+        Ok(Some(frame))
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::{Rect, Vec2};
+use std::time::Duration;
+
+use crate::detection::CameraMapping;
+
+/// Scene-local area covered by the images of all cameras
+pub fn footprint_union(mappings: &[CameraMapping]) -> Rect {
+    mappings
+        .iter()
+        .map(CameraMapping::footprint)
+        .reduce(|union, footprint| union.union(footprint))
+        .unwrap_or_default()
+}
+
+/// When hits reported by different cameras are the same shot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeConfig {
+    /// Distance between the reported positions, in meters
+    pub distance: f32,
+    /// Time between the reports, cameras deliver their frames at their own pace
+    pub window: Duration,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            distance: 0.15,
+            window: Duration::from_millis(250),
+        }
+    }
+}
+
+/// A hit kept to recognize the same shot seen by another camera
+#[derive(Debug, Clone, Copy)]
+struct RecentHit {
+    camera: usize,
+    position: Vec2,
+    at: Duration,
+}
+
+/// Counts a hit seen by the overlapping images of several cameras once
+///
+/// Each camera's tracker already reports a spot once, so only hits of other cameras are merged: two
+/// quick shots at the same place seen by a single camera both count.
+#[derive(Debug, Default)]
+pub struct HitMerger {
+    pub config: MergeConfig,
+    recent: Vec<RecentHit>,
+}
+
+impl HitMerger {
+    pub fn new(config: MergeConfig) -> Self {
+        Self { config, recent: Vec::new() }
+    }
+
+    /// Whether a hit reported by the camera at the given time is a new shot
+    pub fn accept(&mut self, camera: usize, position: Vec2, at: Duration) -> bool {
+        let window = self.config.window;
+        self.recent.retain(|hit| at.saturating_sub(hit.at) <= window);
+
+        let duplicate = self.recent.iter().any(|hit| {
+            hit.camera != camera && hit.position.distance(position) <= self.config.distance
+        });
+        if !duplicate {
+            self.recent.push(RecentHit { camera, position, at });
+        }
+        !duplicate
+    }
+
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+}
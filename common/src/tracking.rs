@@ -0,0 +1,129 @@
+use bevy::prelude::Vec2;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::detection::Blob;
+
+/// How long hit spots stay warm on the wall, which depends on the wall material of the venue
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// Largest distance between the centroids of the same spot in consecutive frames, in pixels
+    pub match_radius: f32,
+    /// Minimum time between two shots at the same location, in seconds
+    pub refractory_secs: f32,
+    /// Time for a hit spot to cool down to 1/e of its peak, in seconds
+    pub cool_down_secs: f32,
+    /// Modeled peak in kelvin above the background below which a spot no longer seen is considered cold
+    pub cooled_intensity: f32,
+    /// Relative rise of the peak of a warm spot from one frame to the next that counts as a new shot
+    pub retrigger_jump: f32,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            match_radius: 3.0,
+            refractory_secs: 0.2,
+            cool_down_secs: 1.5,
+            cooled_intensity: 2.5,
+            retrigger_jump: 0.5,
+        }
+    }
+}
+
+/// A hit spot followed across frames
+#[derive(Debug, Clone, Copy)]
+struct Track {
+    position: Vec2,
+    /// Peak of the spot when it was last fired
+    fired_peak: f32,
+    fired_at: Duration,
+    /// Peak of the spot in the last frame it was seen
+    last_peak: f32,
+}
+
+impl Track {
+    /// Intensity the spot is expected to have left at the given time if nothing hits it again
+    fn modeled_intensity(&self, config: &TrackingConfig, time: Duration) -> f32 {
+        let age = time.saturating_sub(self.fired_at).as_secs_f32();
+        self.fired_peak * (-age / config.cool_down_secs.max(f32::EPSILON)).exp()
+    }
+}
+
+/// Follows detected spots across frames so a hit is only reported on its rising edge
+///
+/// A spot that stays warm over several frames is reported once. A spot at the same location is
+/// reported again when its peak jumps, or after it cooled down and disappeared.
+pub struct SpotTracker {
+    pub config: TrackingConfig,
+    tracks: Vec<Track>,
+}
+
+impl SpotTracker {
+    pub fn new(config: TrackingConfig) -> Self {
+        Self { config, tracks: Vec::new() }
+    }
+
+    pub fn reset(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Blobs of a frame taken at the given time that are new shots
+    pub fn update(&mut self, blobs: &[Blob], time: Duration) -> Vec<Blob> {
+        let refractory = Duration::from_secs_f32(self.config.refractory_secs.max(0.0));
+        let mut seen = vec![false; self.tracks.len()];
+        let mut shots = Vec::new();
+
+        for blob in blobs {
+            let nearest = self
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(index, track)| {
+                    !seen[*index] && track.position.distance(blob.centroid) <= self.config.match_radius
+                })
+                .min_by(|(_, a), (_, b)| {
+                    a.position.distance(blob.centroid).total_cmp(&b.position.distance(blob.centroid))
+                })
+                .map(|(index, _)| index);
+
+            let Some(index) = nearest else {
+                self.tracks.push(Track {
+                    position: blob.centroid,
+                    fired_peak: blob.peak,
+                    fired_at: time,
+                    last_peak: blob.peak,
+                });
+                seen.push(true);
+                shots.push(*blob);
+                continue;
+            };
+
+            seen[index] = true;
+            let track = &mut self.tracks[index];
+            let jumped = blob.peak > track.last_peak * (1.0 + self.config.retrigger_jump);
+            if jumped && time.saturating_sub(track.fired_at) >= refractory {
+                track.fired_peak = blob.peak;
+                track.fired_at = time;
+                shots.push(*blob);
+            }
+            track.position = blob.centroid;
+            track.last_peak = blob.peak;
+        }
+
+        // Spots that vanished are kept until the cool-down model says they are cold, so a spot
+        // flickering around the detection threshold is not reported again
+        let config = self.config;
+        let mut index = 0;
+        self.tracks.retain(|track| {
+            let keep = seen[index]
+                || track.modeled_intensity(&config, time) >= config.cooled_intensity
+                || time.saturating_sub(track.fired_at) < refractory;
+            index += 1;
+            keep
+        });
+
+        shots
+    }
+}
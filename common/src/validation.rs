@@ -0,0 +1,126 @@
+use bevy::prelude::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// A projected reference cross and the shot fired at it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationPoint {
+    /// Where the cross was shown, in scene-local meters
+    pub reference: [f32; 2],
+    /// Where the shot at it was detected, in scene-local meters, none until it was fired
+    pub detected: Option<[f32; 2]>,
+}
+
+impl ValidationPoint {
+    /// Offset of the detected shot from the reference, in meters
+    pub fn error(&self) -> Option<Vec2> {
+        self.detected.map(|detected| Vec2::from(detected) - Vec2::from(self.reference))
+    }
+}
+
+/// Accuracy of the calibration, proven by shots fired at projected reference crosses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Seconds since the Unix epoch at which the routine was started
+    pub timestamp: u64,
+    /// Distance from the shooter to the scene in meters, the angular errors are seen from there
+    pub distance: f32,
+    pub points: Vec<ValidationPoint>,
+}
+
+impl ValidationReport {
+    pub fn new(references: &[Vec2], distance: f32, timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            distance,
+            points: references
+                .iter()
+                .map(|reference| ValidationPoint { reference: reference.to_array(), detected: None })
+                .collect(),
+        }
+    }
+
+    /// Index of the first cross not fired at yet
+    pub fn next_point(&self) -> Option<usize> {
+        self.points.iter().position(|point| point.detected.is_none())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.next_point().is_none()
+    }
+
+    /// Record a detected shot as the one fired at the next cross, returning its index
+    pub fn record(&mut self, position: Vec2) -> Option<usize> {
+        let index = self.next_point()?;
+        self.points[index].detected = Some(position.to_array());
+        Some(index)
+    }
+
+    /// Errors of the points fired at, in meters
+    pub fn errors(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.points.iter().filter_map(ValidationPoint::error)
+    }
+
+    /// Root mean square of the error lengths in meters, once a point was fired at
+    pub fn rms_error(&self) -> Option<f32> {
+        let (count, sum) = self.errors().fold((0, 0.0), |(count, sum), error| (count + 1, sum + error.length_squared()));
+        (count > 0).then(|| (sum / count as f32).sqrt())
+    }
+
+    /// Largest error length in meters
+    pub fn max_error(&self) -> Option<f32> {
+        self.errors().map(Vec2::length).max_by(f32::total_cmp)
+    }
+
+    /// Mean of the errors in meters, a systematic shift of the whole calibration when it is most of the rms error
+    pub fn mean_error(&self) -> Option<Vec2> {
+        let (count, sum) = self.errors().fold((0, Vec2::ZERO), |(count, sum), error| (count + 1, sum + error));
+        (count > 0).then(|| sum / count as f32)
+    }
+
+    /// Minutes of angle an error in meters subtends from the shooter
+    pub fn moa(&self, error: f32) -> f32 {
+        moa(error, self.distance)
+    }
+
+    /// The points and their errors as CSV, ending with the rms error
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("point,reference_x_m,reference_y_m,detected_x_m,detected_y_m,error_mm,error_moa\n");
+        for (index, point) in self.points.iter().enumerate() {
+            let [x, y] = point.reference;
+            let _ = write!(csv, "{},{:.4},{:.4}", index + 1, x, y);
+            match (point.detected, point.error()) {
+                (Some([dx, dy]), Some(error)) => {
+                    let _ = writeln!(csv, ",{:.4},{:.4},{:.1},{:.2}", dx, dy, error.length() * 1000.0, self.moa(error.length()));
+                }
+                _ => csv.push_str(",,,,\n"),
+            }
+        }
+        if let Some(rms) = self.rms_error() {
+            let _ = writeln!(csv, "rms,,,,,{:.1},{:.2}", rms * 1000.0, self.moa(rms));
+        }
+        csv
+    }
+}
+
+/// Minutes of angle an offset across the line of sight subtends at the given distance, both in meters
+pub fn moa(offset: f32, distance: f32) -> f32 {
+    offset.atan2(distance).to_degrees() * 60.0
+}
+
+/// Offset across the line of sight in meters that subtends the given minutes of angle at the distance
+pub fn moa_offset(moa: f32, distance: f32) -> f32 {
+    distance * (moa / 60.0).to_radians().tan()
+}
+
+/// Reference crosses in the middle of the cells of a grid over the area, row by row from the top left
+pub fn reference_grid(area: Rect, columns: u32, rows: u32) -> Vec<Vec2> {
+    let cell = area.size() / Vec2::new(columns as f32, rows as f32);
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| Vec2::new(
+            area.min.x + (column as f32 + 0.5) * cell.x,
+            area.max.y - (row as f32 + 0.5) * cell.y,
+        ))
+        .collect()
+}
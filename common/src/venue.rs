@@ -0,0 +1,113 @@
+use bevy::prelude::{EulerRot, Quat, Resource, Transform, UVec2, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::config::{CameraConfiguration, CameraFootprint};
+use crate::frame::FrameSourceConfig;
+use crate::homography::Homography;
+use crate::lens::LensIntrinsics;
+use crate::masks::{ExclusionMask, MaskOrigin};
+use crate::radiometry::RadiometricCalibration;
+use crate::validation::ValidationReport;
+
+/// Setup of a venue that outlives a session, stored as JSON next to the terminal
+#[derive(Resource, Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VenueConfiguration {
+    /// Areas of the scene where warm spots are never shots
+    pub masks: Vec<ExclusionMask>,
+    /// Thermal cameras covering parts of the scene next to the main camera
+    pub cameras: Vec<CameraPlacement>,
+    /// Distortion of the main camera lens, estimated from a heated grid
+    pub lens: Option<LensIntrinsics>,
+    /// Mapping of the main camera pixels to the scene, measured with the calibration mode
+    pub homography: Option<Homography>,
+    /// Lens the pixels were undistorted with when the homography was fitted, it only holds through that lens
+    pub homography_lens: Option<LensIntrinsics>,
+    /// Surfaces targets can be placed on besides the scene, such as angled walls or the floor
+    pub planes: Vec<TargetPlane>,
+    /// Accuracy of the calibration measured by the latest validation shots
+    pub validation: Option<ValidationReport>,
+}
+
+/// A named flat surface of the venue that targets can be placed on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetPlane {
+    pub name: String,
+    /// World position of the middle of the plane
+    pub position: [f32; 3],
+    /// Yaw, pitch and roll in degrees, the plane faces +z like the scene when all are zero and the floor at a pitch of -90
+    pub rotation: [f32; 3],
+    /// Width and height in meters
+    pub size: [f32; 2],
+}
+
+impl TargetPlane {
+    pub fn transform(&self) -> Transform {
+        let [yaw, pitch, roll] = self.rotation.map(f32::to_radians);
+        Transform::from_translation(Vec3::from(self.position)).with_rotation(Quat::from_euler(EulerRot::YXZ, yaw, pitch, roll))
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::from(self.size)
+    }
+}
+
+/// A thermal camera watching part of the scene, as set up in the venue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPlacement {
+    pub name: String,
+    pub frame_source: FrameSourceConfig,
+    /// Size of the camera image in pixels
+    pub resolution: [u32; 2],
+    pub footprint: CameraFootprint,
+    /// World position of the camera looking at the scene, the main camera's position when none
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+    #[serde(default)]
+    pub radiometry: RadiometricCalibration,
+    #[serde(default)]
+    pub lens: Option<LensIntrinsics>,
+    #[serde(default)]
+    pub homography: Option<Homography>,
+}
+
+impl CameraPlacement {
+    /// Configuration of the camera, detecting spots the same way as the main camera
+    pub fn configuration(&self, main: &CameraConfiguration) -> CameraConfiguration {
+        CameraConfiguration {
+            input_resolution: UVec2::from(self.resolution),
+            transform: self.position.map_or(main.transform, |position| Transform::from_translation(Vec3::from(position))),
+            frame_source: self.frame_source.clone(),
+            detection: main.detection,
+            radiometry: self.radiometry,
+            lens: self.lens,
+            optics: None,
+            footprint: Some(self.footprint),
+            homography: self.homography,
+        }
+    }
+}
+
+impl VenueConfiguration {
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// Homography of the main camera, none when it was fitted through another lens than the current one
+    pub fn main_homography(&self) -> Option<Homography> {
+        self.homography.filter(|_| self.homography_lens == self.lens)
+    }
+
+    /// Replace the masks found by a previous background learning
+    pub fn replace_learned_masks(&mut self, learned: Vec<ExclusionMask>) {
+        self.masks.retain(|mask| mask.origin != MaskOrigin::Learned);
+        self.masks.extend(learned);
+    }
+}
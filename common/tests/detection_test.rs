@@ -0,0 +1,152 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::detection::{Blob, CameraMapping, DetectionConfig, SpotDetector};
+use common::frame::{FrameSource, SyntheticSource, SyntheticSpot};
+
+const RESOLUTION: UVec2 = UVec2::new(64, 48);
+const BACKGROUND: u16 = 18750;
+
+fn source(noise: u16, spots: Vec<SyntheticSpot>) -> SyntheticSource {
+    let mut source = SyntheticSource::new(RESOLUTION, 25.0, BACKGROUND).unwrap();
+    source.noise = noise;
+    source.spots = spots;
+    source
+}
+
+fn spot(center: Vec2, first_frame: u32) -> SyntheticSpot {
+    SyntheticSpot {
+        center,
+        radius: 1.2,
+        intensity: 1500,
+        first_frame,
+        frames: 3,
+    }
+}
+
+/// Run the detector over the given number of frames, returning the blobs found on each
+fn detect(source: &mut SyntheticSource, frames: usize) -> Vec<Vec<Blob>> {
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    (0..frames)
+        .map(|_| detector.process(&source.next_frame().unwrap().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_single_shot_sub_pixel_centroid() {
+    let center = Vec2::new(20.3, 30.7);
+    let mut source = source(0, vec![spot(center, 5)]);
+    let detections = detect(&mut source, 6);
+
+    assert!(detections[..5].iter().all(|blobs| blobs.is_empty()));
+    assert_eq!(detections[5].len(), 1);
+    let blob = detections[5][0];
+    assert!(blob.centroid.distance(center) < 0.1, "centroid {:?} should be close to {:?}", blob.centroid, center);
+    // 1500 counts are over 23 K with the default calibration
+    assert!(blob.peak > 15.0, "peak {} K", blob.peak);
+}
+
+#[test]
+fn test_simultaneous_shots() {
+    let centers = [Vec2::new(10.5, 10.5), Vec2::new(50.2, 12.0), Vec2::new(32.0, 40.4)];
+    let mut source = source(0, centers.iter().map(|&center| spot(center, 3)).collect());
+    let detections = detect(&mut source, 4);
+
+    let blobs = &detections[3];
+    assert_eq!(blobs.len(), 3);
+    for center in centers {
+        assert!(
+            blobs.iter().any(|blob| blob.centroid.distance(center) < 0.1),
+            "missing shot at {:?} in {:?}",
+            center,
+            blobs
+        );
+    }
+}
+
+#[test]
+fn test_noise_is_not_a_shot() {
+    let mut source = source(60, Vec::new());
+    source.seed(7);
+    let detections = detect(&mut source, 50);
+
+    assert!(detections.iter().all(|blobs| blobs.is_empty()));
+}
+
+#[test]
+fn test_zero_threshold_gives_no_empty_centroids() {
+    // Accepting any blob, the whole unchanged frame becomes one blob that did not warm up
+    let mut source = source(0, Vec::new());
+    let mut detector = SpotDetector::new(DetectionConfig {
+        threshold: 0.0,
+        min_intensity: 0.0,
+        max_pixels: u32::MAX,
+        max_elongation: f32::MAX,
+        ..Default::default()
+    });
+    let detections: Vec<Vec<Blob>> = (0..8)
+        .map(|_| detector.process(&source.next_frame().unwrap().unwrap()))
+        .collect();
+
+    assert!(detections.iter().flatten().all(|blob| blob.centroid.is_finite()), "{:?}", detections);
+}
+
+#[test]
+fn test_shot_found_through_noise() {
+    let center = Vec2::new(40.0, 20.0);
+    let mut source = source(60, vec![spot(center, 20)]);
+    source.seed(3);
+    let detections = detect(&mut source, 21);
+
+    assert!(detections[..20].iter().all(|blobs| blobs.is_empty()));
+    assert_eq!(detections[20].len(), 1);
+    assert!(detections[20][0].centroid.distance(center) < 0.5);
+}
+
+#[test]
+fn test_blobs_filtered_by_size_and_intensity() {
+    let warm_body = SyntheticSpot {
+        center: Vec2::new(16.0, 24.0),
+        radius: 8.0,
+        intensity: 1500,
+        first_frame: 2,
+        frames: 1,
+    };
+    let faint = SyntheticSpot {
+        intensity: 200,
+        ..spot(Vec2::new(48.0, 24.0), 2)
+    };
+    let mut source = source(0, vec![warm_body, faint]);
+    // A thin warm streak, such as a body entering the image from the side
+    source.spots.extend((0..8).map(|step| SyntheticSpot {
+        radius: 0.8,
+        ..spot(Vec2::new(60.5, 10.0 + 2.0 * step as f32), 2)
+    }));
+    let detections = detect(&mut source, 3);
+
+    assert!(detections[2].is_empty(), "unexpected blobs {:?}", detections[2]);
+}
+
+#[test]
+fn test_blob_bounding_box() {
+    let mut source = source(0, vec![spot(Vec2::new(20.0, 20.0), 1)]);
+    let blob = detect(&mut source, 2)[1][0];
+
+    assert_eq!(blob.size.x, blob.size.y);
+    assert!(blob.size.x >= 3);
+    assert_eq!(blob.elongation(), 1.0);
+}
+
+#[test]
+fn test_camera_mapping_to_scene_meters() {
+    // A 10 m wide scene seen by a 64 pixel wide camera
+    let mapping = CameraMapping {
+        resolution: RESOLUTION,
+        units_per_pixel: 10.0 / 64.0,
+        center: Vec2::ZERO,
+        lens: None,
+        homography: None,
+    };
+
+    assert_eq!(mapping.to_scene(Vec2::new(32.0, 24.0)), Vec2::ZERO);
+    assert_eq!(mapping.to_scene(Vec2::new(64.0, 0.0)), Vec2::new(5.0, 3.75));
+    assert_eq!(mapping.to_scene(Vec2::new(0.0, 48.0)), Vec2::new(-5.0, -3.75));
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::frame::{
+    FrameSource, FrameSourceConfig, FrameSourceError, LoopingSource, RawFrameSource, SyntheticSource, SyntheticSpot,
+    ThermalFrame,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const RESOLUTION: UVec2 = UVec2::new(8, 6);
+
+/// A fresh directory under the system temp dir for one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lasertargets-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn frame(value: u16) -> ThermalFrame {
+    ThermalFrame::filled(RESOLUTION, value, Duration::ZERO)
+}
+
+#[test]
+fn test_frame_from_bytes_checks_length() {
+    let original = frame(1234);
+    let bytes = original.to_le_bytes();
+
+    assert_eq!(ThermalFrame::from_le_bytes(RESOLUTION, &bytes, Duration::ZERO), Some(original));
+    assert_eq!(ThermalFrame::from_le_bytes(RESOLUTION, &bytes[1..], Duration::ZERO), None);
+    assert_eq!(ThermalFrame::from_le_bytes(RESOLUTION, &bytes[2..], Duration::ZERO), None);
+}
+
+#[test]
+fn test_raw_file_with_several_frames() {
+    let dir = temp_dir("raw-file");
+    let path = dir.join("sequence.bin");
+    let mut bytes = frame(100).to_le_bytes();
+    bytes.extend(frame(200).to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+
+    let mut source = RawFrameSource::open(&path, RESOLUTION, 10.0).unwrap();
+    let first = source.next_frame().unwrap().unwrap();
+    let second = source.next_frame().unwrap().unwrap();
+
+    assert_eq!(first.get(3, 2), 100);
+    assert_eq!(second.get(7, 5), 200);
+    assert_eq!(second.timestamp, Duration::from_millis(100));
+    assert!(source.next_frame().unwrap().is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_raw_directory_in_name_order() {
+    let dir = temp_dir("raw-dir");
+    std::fs::write(dir.join("0002.raw"), frame(2).to_le_bytes()).unwrap();
+    std::fs::write(dir.join("0001.raw"), frame(1).to_le_bytes()).unwrap();
+    std::fs::write(dir.join("notes.txt"), "not a frame").unwrap();
+
+    let mut source = RawFrameSource::open(&dir, RESOLUTION, 25.0).unwrap();
+    let values: Vec<u16> = std::iter::from_fn(|| source.next_frame().unwrap()).map(|frame| frame.get(0, 0)).collect();
+
+    assert_eq!(values, vec![1, 2]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_raw_truncated_frame_is_an_error() {
+    let dir = temp_dir("raw-truncated");
+    let path = dir.join("broken.raw");
+    let mut bytes = frame(100).to_le_bytes();
+    bytes.truncate(bytes.len() - 3);
+    std::fs::write(&path, bytes).unwrap();
+
+    let mut source = RawFrameSource::open(&path, RESOLUTION, 25.0).unwrap();
+    assert!(matches!(source.next_frame(), Err(FrameSourceError::TruncatedFrame { .. })));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_looping_source_replays_with_increasing_timestamps() {
+    let mut source = LoopingSource::new(vec![frame(1), frame(2)], 10.0).unwrap();
+    let frames: Vec<ThermalFrame> = (0..5).map(|_| source.next_frame().unwrap().unwrap()).collect();
+
+    let values: Vec<u16> = frames.iter().map(|frame| frame.get(0, 0)).collect();
+    assert_eq!(values, vec![1, 2, 1, 2, 1]);
+    assert_eq!(frames[4].timestamp, Duration::from_millis(400));
+    assert!(matches!(LoopingSource::new(Vec::new(), 10.0), Err(FrameSourceError::Empty)));
+}
+
+#[test]
+fn test_synthetic_noise_is_bounded_and_reproducible() {
+    let open = || {
+        let mut source = SyntheticSource::new(RESOLUTION, 25.0, 1000).unwrap();
+        source.noise = 5;
+        source.seed(42);
+        source
+    };
+    let first = open().next_frame().unwrap().unwrap();
+    let again = open().next_frame().unwrap().unwrap();
+
+    assert_eq!(first, again, "Same seed should give the same frames");
+    assert!(first.pixels.iter().all(|pixel| (995..=1005).contains(pixel)));
+    assert!(first.pixels.iter().any(|pixel| *pixel != 1000), "Noise should vary the pixels");
+}
+
+#[test]
+fn test_synthetic_seed_mixing_to_zero_still_makes_noise() {
+    let mut source = SyntheticSource::new(RESOLUTION, 25.0, 1000).unwrap();
+    source.noise = 5;
+    source.seed(0x9E37_79B9_7F4A_7C15);
+    let frame = source.next_frame().unwrap().unwrap();
+
+    assert!(frame.pixels.iter().any(|pixel| *pixel != 1000), "Noise should not get stuck at zero");
+}
+
+#[test]
+fn test_synthetic_spot_is_visible_during_its_frames() {
+    let mut source = SyntheticSource::new(RESOLUTION, 25.0, 1000).unwrap();
+    source.spots.push(SyntheticSpot {
+        center: Vec2::new(4.5, 3.5),
+        radius: 1.0,
+        intensity: 500,
+        first_frame: 1,
+        frames: 1,
+    });
+
+    let before = source.next_frame().unwrap().unwrap();
+    let during = source.next_frame().unwrap().unwrap();
+    let after = source.next_frame().unwrap().unwrap();
+
+    assert_eq!(before.get(4, 3), 1000);
+    assert_eq!(during.get(4, 3), 1500);
+    assert!(during.get(5, 3) > 1000 && during.get(5, 3) < 1500);
+    assert_eq!(during.get(0, 0), 1000);
+    assert_eq!(after.get(4, 3), 1000);
+}
+
+#[test]
+fn test_source_selected_from_config() {
+    let config: FrameSourceConfig =
+        serde_json::from_str(r#"{"type": "synthetic", "fps": 9.0, "background": 300, "noise": 0}"#).unwrap();
+    let mut source = config.open(RESOLUTION).unwrap();
+    assert_eq!(source.resolution(), RESOLUTION);
+    assert_eq!(source.next_frame().unwrap().unwrap().get(1, 1), 300);
+
+    let dir = temp_dir("config");
+    std::fs::write(dir.join("0001.raw"), frame(7).to_le_bytes()).unwrap();
+    let config = FrameSourceConfig::Raw { path: dir.clone(), fps: 10.0, looping: true };
+    let mut source = config.open(RESOLUTION).unwrap();
+    for _ in 0..3 {
+        assert_eq!(source.next_frame().unwrap().unwrap().get(0, 0), 7, "Looping recording should not end");
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let missing = FrameSourceConfig::Raw { path: PathBuf::from("/nonexistent/frames"), fps: 10.0, looping: false };
+    assert!(matches!(missing.open(RESOLUTION), Err(FrameSourceError::Io(_))));
+}
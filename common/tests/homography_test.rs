@@ -0,0 +1,115 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::FrameSource;
+use common::homography::{estimate_homography, Homography, HomographyError};
+use common::lens::{find_grid_points, LensIntrinsics};
+use common::simulator::{HitProfile, ThermalSimulator};
+use common::venue::VenueConfiguration;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+
+/// Image corners of a camera looking at the scene from the side, its image covers a trapezoid
+const CORNERS: [(Vec2, Vec2); 4] = [
+    (Vec2::new(0.0, 0.0), Vec2::new(-5.0, 4.0)),
+    (Vec2::new(256.0, 0.0), Vec2::new(5.0, 3.2)),
+    (Vec2::new(256.0, 192.0), Vec2::new(5.0, -3.2)),
+    (Vec2::new(0.0, 192.0), Vec2::new(-5.0, -4.0)),
+];
+
+fn off_axis() -> Homography {
+    estimate_homography(&CORNERS).unwrap().homography
+}
+
+fn simulator() -> ThermalSimulator {
+    let camera = CameraConfiguration { homography: Some(off_axis()), ..Default::default() };
+    ThermalSimulator::new(&camera, &SceneConfiguration::default(), 25.0).unwrap()
+}
+
+/// Heated markers on a 3 by 3 grid, row by row from the top left
+fn markers() -> Vec<Vec2> {
+    [2.5, 0.0, -2.5]
+        .into_iter()
+        .flat_map(|y| [-3.5, 0.0, 3.5].map(|x| Vec2::new(x, y)))
+        .collect()
+}
+
+#[test]
+fn test_homography_maps_reference_points() {
+    let fit = estimate_homography(&CORNERS).unwrap();
+    let inverse = fit.homography.inverse().unwrap();
+
+    assert!(fit.rms_error < 1e-4, "rms error {}", fit.rms_error);
+    assert_eq!(fit.errors.len(), 4);
+    for (pixel, position) in CORNERS {
+        assert!(fit.homography.apply(pixel).distance(position) < 1e-3, "{} mapped to {}", pixel, fit.homography.apply(pixel));
+        assert!(inverse.apply(position).distance(pixel) < 1e-2, "{} mapped back to {}", position, inverse.apply(position));
+    }
+    // The image center lands where the diagonals of the trapezoid cross, towards its shorter side
+    assert!(fit.homography.apply(Vec2::new(128.0, 96.0)).x > 0.0);
+}
+
+#[test]
+fn test_homography_errors() {
+    assert_eq!(estimate_homography(&CORNERS[..3]), Err(HomographyError::TooFewPoints(3)));
+    let coincident = [(Vec2::new(10.0, 10.0), Vec2::ZERO); 4];
+    assert_eq!(estimate_homography(&coincident), Err(HomographyError::Degenerate));
+}
+
+#[test]
+fn test_off_axis_camera_mapping() {
+    let simulator = simulator();
+    let mapping = simulator.mapping();
+
+    let footprint = mapping.footprint();
+    assert!(footprint.min.distance(Vec2::new(-5.0, -4.0)) < 1e-3 && footprint.max.distance(Vec2::new(5.0, 4.0)) < 1e-3, "{:?}", footprint);
+    assert!(mapping.to_pixel(Vec2::new(5.0, 3.2)).distance(Vec2::new(256.0, 0.0)) < 1e-2);
+}
+
+#[test]
+fn test_homography_estimated_from_heated_markers() {
+    let mut simulator = simulator();
+    simulator.hit_profile = HitProfile {
+        peak: 20.0,
+        radius: 0.08,
+        decay: Duration::from_secs(3600),
+    };
+    for marker in markers() {
+        simulator.shoot(Duration::ZERO, marker);
+    }
+    let frame = simulator.next_frame().unwrap().unwrap();
+    let pixels = find_grid_points(&frame, &simulator.radiometry, 5.0, UVec2::new(3, 3)).unwrap();
+    let pairs: Vec<(Vec2, Vec2)> = pixels.into_iter().zip(markers()).collect();
+
+    let fit = estimate_homography(&pairs).unwrap();
+    assert!(fit.rms_error < 0.03, "rms error {}", fit.rms_error);
+
+    // A later hit is placed on target through the fitted homography, and off it without
+    let target = Vec2::new(-4.5, 3.5);
+    simulator.hits.clear();
+    simulator.shoot(FRAME * 5, target);
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    let spot = (0..10)
+        .flat_map(|_| detector.process(&simulator.next_frame().unwrap().unwrap()))
+        .next()
+        .expect("hit not detected");
+
+    let mapping = simulator.mapping().with_homography(Some(fit.homography));
+    assert!(mapping.to_scene(spot.centroid).distance(target) < 0.05, "{}", mapping.to_scene(spot.centroid));
+    let uniform = CameraConfiguration::default().mapping(10.0);
+    assert!(uniform.to_scene(spot.centroid).distance(target) > 0.15, "{}", uniform.to_scene(spot.centroid));
+}
+
+#[test]
+fn test_homography_only_holds_through_its_lens() {
+    let lens = LensIntrinsics { radial: [-0.2, 0.0, 0.0], ..LensIntrinsics::ideal(UVec2::new(256, 192), 256.0) };
+    let mut venue = VenueConfiguration { homography: Some(off_axis()), homography_lens: Some(lens), ..Default::default() };
+    assert_eq!(venue.main_homography(), None, "Fitted through a lens that is not applied");
+
+    venue.lens = Some(lens);
+    assert_eq!(venue.main_homography(), Some(off_axis()));
+
+    venue.lens = Some(LensIntrinsics::ideal(UVec2::new(256, 192), 256.0));
+    assert_eq!(venue.main_homography(), None, "Fitted through another lens");
+}
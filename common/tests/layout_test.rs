@@ -0,0 +1,152 @@
+use bevy::prelude::{Transform, Vec2, Vec3};
+use common::config::{CameraConfiguration, ProjectorConfiguration};
+use common::layout::{convex_intersection, hatch, nearest_plane_hit, plan_layout, polygon_area, LayoutInput, PlanWarning};
+use common::venue::TargetPlane;
+
+fn square(min: Vec2, max: Vec2) -> Vec<Vec2> {
+    vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+}
+
+/// Scene plane 25 m in front of the devices
+fn scene() -> Transform {
+    Transform::from_xyz(0.0, 3.75, -20.0)
+}
+
+#[test]
+fn test_convex_intersection() {
+    let overlap = convex_intersection(&square(Vec2::ZERO, Vec2::splat(2.0)), &square(Vec2::ONE, Vec2::splat(3.0)));
+    assert!((polygon_area(&overlap) - 1.0).abs() < 1e-5, "{:?}", overlap);
+
+    // A diamond inside a square keeps its shape, whichever way the square is wound
+    let diamond = vec![Vec2::new(0.0, -1.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0), Vec2::new(-1.0, 0.0)];
+    let mut clockwise = square(Vec2::splat(-2.0), Vec2::splat(2.0));
+    clockwise.reverse();
+    assert!((polygon_area(&convex_intersection(&diamond, &clockwise)) - 2.0).abs() < 1e-5);
+
+    assert!(convex_intersection(&square(Vec2::ZERO, Vec2::ONE), &square(Vec2::splat(2.0), Vec2::splat(3.0))).is_empty());
+}
+
+#[test]
+fn test_hatch_stays_inside() {
+    let lines = hatch(&square(Vec2::ZERO, Vec2::new(2.0, 1.0)), 0.25);
+
+    assert_eq!(lines.len(), 11);
+    for (a, b) in lines {
+        for point in [a, b] {
+            assert!(point.cmpge(Vec2::splat(-1e-5)).all() && point.cmple(Vec2::new(2.0, 1.0) + 1e-5).all(), "{}", point);
+        }
+    }
+    assert!(hatch(&[Vec2::ZERO, Vec2::ONE], 0.25).is_empty());
+}
+
+#[test]
+fn test_camera_frustum_sees_its_footprint() {
+    let camera = CameraConfiguration {
+        transform: Transform::from_xyz(0.0, 3.75, 5.0),
+        ..Default::default()
+    };
+    let mapping = camera.mapping(10.0);
+    let footprint = camera.frustum(&mapping, &scene()).footprint(&scene()).unwrap();
+    let rect = mapping.footprint();
+
+    assert!(footprint[0].distance(Vec2::new(rect.min.x, rect.max.y)) < 1e-3, "{:?}", footprint);
+    assert!(footprint[2].distance(Vec2::new(rect.max.x, rect.min.y)) < 1e-3, "{:?}", footprint);
+
+    // Moved to the right, the camera still aims at the middle of its footprint, which is taller on the far side
+    let moved = CameraConfiguration { transform: Transform::from_xyz(6.0, 1.0, 5.0), ..camera };
+    let frustum = moved.frustum(&mapping, &scene());
+    assert!(frustum.to_scene(frustum.resolution.as_vec2() / 2.0, &scene()).unwrap().length() < 1e-3);
+    let footprint = frustum.footprint(&scene()).unwrap();
+    assert!((footprint[0].y - footprint[3].y) - (footprint[1].y - footprint[2].y) > 0.1, "{:?}", footprint);
+}
+
+#[test]
+fn test_projector_and_camera_overlap() {
+    let projector = ProjectorConfiguration {
+        transform: Transform::from_xyz(0.0, 3.75, 5.0).looking_at(Vec3::new(0.0, 3.75, -20.0), Vec3::Y),
+        angle: 20.0,
+        ..Default::default()
+    };
+    let camera = CameraConfiguration::default();
+    let covered = projector.frustum().footprint(&scene()).unwrap();
+    let seen = square(Vec2::new(-5.0, -3.75), Vec2::new(5.0, 3.75));
+    let overlap = convex_intersection(&covered, &seen);
+
+    // The square output, 8.8 m wide, fits across the scene but not down it
+    let side = 2.0 * 25.0 * 10.0_f32.to_radians().tan();
+    assert!((polygon_area(&overlap).abs() - side * 7.5).abs() < 1e-2, "{}", polygon_area(&overlap));
+    assert_eq!(camera.frustum(&camera.mapping(10.0), &scene()).resolution, camera.input_resolution);
+}
+
+#[test]
+fn test_plan_layout_covers_the_target_area() {
+    let input = LayoutInput::default();
+    let plan = plan_layout(&input);
+
+    assert!(plan.warnings.is_empty(), "{:?}", plan.warnings);
+    // Above the shooter's head, side by side behind the shooter
+    assert!((plan.camera.y - 2.3).abs() < 1e-4 && (plan.projector.y - 2.3).abs() < 1e-4);
+    assert!(plan.camera.x < plan.projector.x);
+    assert!(plan.camera.z > input.shooter.y && plan.camera.z <= input.room.z, "{}", plan.camera);
+    // Just covering the area, the 4:3 camera on both sides and the 16:9 projector on its height
+    assert!(plan.camera_coverage.cmpge(input.target_size - 1e-3).all(), "{}", plan.camera_coverage);
+    assert!((plan.camera_coverage.x - input.target_size.x).abs() < 0.1, "{}", plan.camera_coverage);
+    assert!((plan.projector_coverage.y - input.target_size.y).abs() < 0.1, "{}", plan.projector_coverage);
+    assert!((plan.camera_units_per_pixel - 4.0 / 256.0).abs() < 1e-3, "{}", plan.camera_units_per_pixel);
+    assert!(plan.projector_units_per_pixel < plan.camera_units_per_pixel);
+}
+
+#[test]
+fn test_plan_layout_warnings() {
+    let input = LayoutInput {
+        room: Vec3::new(8.0, 2.0, 6.0),
+        smallest_target: 0.03,
+        ..Default::default()
+    };
+    let plan = plan_layout(&input);
+
+    assert!(matches!(plan.warnings[0], PlanWarning::CeilingTooLow { height: 2.0, .. }), "{:?}", plan.warnings);
+    assert!(plan.warnings.iter().any(|warning| matches!(warning, PlanWarning::CameraBeyondRoom { depth: 6.0, .. })));
+    assert!(plan.warnings.iter().any(|warning| matches!(warning, PlanWarning::AheadOfShooter { distance: 6.0 })));
+    assert!(plan.warnings.iter().any(|warning| matches!(warning, PlanWarning::CoarseDetection { .. })));
+    // Pulled in to the back wall, the camera sees less than the target area
+    assert_eq!(plan.camera.z, 6.0);
+    assert!(plan.camera_coverage.x < input.target_size.x);
+    assert!(plan.warnings.iter().all(|warning| !warning.to_string().is_empty()));
+}
+
+#[test]
+fn test_nearest_plane_hit() {
+    let wall = (scene(), Vec2::new(10.0, 7.5));
+    let floor = TargetPlane {
+        name: "Floor".to_string(),
+        position: [0.0, 0.0, -10.0],
+        rotation: [0.0, -90.0, 0.0],
+        size: [6.0, 20.0],
+    };
+    // Turned 30° towards the shooter on the right, 15 m away
+    let side = TargetPlane {
+        name: "Side wall".to_string(),
+        position: [6.0, 2.0, -10.0],
+        rotation: [-30.0, 0.0, 0.0],
+        size: [4.0, 3.0],
+    };
+    assert!(floor.transform().forward().dot(Vec3::NEG_Y) > 0.999, "the floor faces up");
+    let planes = [wall, (floor.transform(), floor.size()), (side.transform(), side.size())];
+    let eye = Vec3::new(0.0, 1.5, 5.0);
+
+    // Straight ahead the wall, looking down the floor in front of it
+    let (index, _, local) = nearest_plane_hit(planes, eye, Vec3::new(0.0, 2.25, -25.0)).unwrap();
+    assert_eq!(index, 0);
+    assert!(local.length() < 1e-3, "{}", local);
+    let (index, distance, local) = nearest_plane_hit(planes, eye, Vec3::new(0.0, -1.5, -15.0)).unwrap();
+    assert_eq!(index, 1);
+    assert!((distance - 1.0).abs() < 1e-4 && local.distance(Vec2::ZERO) < 1e-3, "{} {}", distance, local);
+
+    // The side wall hides the part of the scene behind it
+    let (index, _, local) = nearest_plane_hit(planes, eye, Vec3::new(6.0, 0.5, -15.0)).unwrap();
+    assert_eq!(index, 2);
+    assert!(local.length() < 1e-3, "{}", local);
+
+    assert_eq!(nearest_plane_hit(planes, eye, Vec3::Z), None);
+}
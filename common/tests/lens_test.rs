@@ -0,0 +1,160 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::{FrameSource, ThermalFrame};
+use common::lens::{estimate_lens, find_grid_points, CameraOptics, LensCalibrationError, LensIntrinsics};
+use common::simulator::{HitProfile, ThermalSimulator};
+use std::time::Duration;
+
+const RESOLUTION: UVec2 = UVec2::new(256, 192);
+const FRAME: Duration = Duration::from_millis(40);
+
+/// A cheap wide lens with strong barrel distortion
+fn barrel_lens() -> LensIntrinsics {
+    LensIntrinsics {
+        radial: [-0.15, 0.02, 0.0],
+        tangential: [0.002, -0.001],
+        ..LensIntrinsics::ideal(RESOLUTION, 256.0)
+    }
+}
+
+fn simulator(lens: Option<LensIntrinsics>) -> ThermalSimulator {
+    let camera = CameraConfiguration { lens, ..Default::default() };
+    ThermalSimulator::new(&camera, &SceneConfiguration::default(), 25.0).unwrap()
+}
+
+/// Heat pads on a 7 by 5 grid covering most of the default 10 by 7.5 m scene
+fn heated_grid(simulator: &mut ThermalSimulator) {
+    simulator.hit_profile = HitProfile {
+        peak: 20.0,
+        radius: 0.08,
+        decay: Duration::from_secs(3600),
+    };
+    for row in 0..5 {
+        for column in 0..7 {
+            simulator.shoot(Duration::ZERO, Vec2::new(-4.2 + column as f32 * 1.4, 3.0 - row as f32 * 1.5));
+        }
+    }
+}
+
+#[test]
+fn test_undistort_inverts_distort() {
+    let lens = barrel_lens();
+
+    for pixel in [Vec2::new(128.0, 96.0), Vec2::new(10.0, 5.0), Vec2::new(250.0, 180.0), Vec2::new(60.0, 150.0)] {
+        let distorted = lens.distort(pixel);
+        assert!(lens.undistort(distorted).distance(pixel) < 1e-3, "{} came back as {}", pixel, lens.undistort(distorted));
+    }
+    // Barrel distortion pulls the corners towards the center
+    assert!(lens.distort(Vec2::ZERO).length() > 5.0);
+    assert_eq!(LensIntrinsics::ideal(RESOLUTION, 256.0).distort(Vec2::new(3.0, 4.0)), Vec2::new(3.0, 4.0));
+}
+
+#[test]
+fn test_hits_near_the_edges_are_undistorted() {
+    let mut simulator = simulator(Some(barrel_lens()));
+    let target = Vec2::new(4.2, -3.2);
+    simulator.shoot(FRAME * 5, target);
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+
+    let spot = (0..10)
+        .flat_map(|_| detector.process(&simulator.next_frame().unwrap().unwrap()))
+        .next()
+        .expect("hit not detected");
+    let mapping = simulator.mapping();
+
+    assert!(mapping.to_scene(spot.centroid).distance(target) < 0.05, "{}", mapping.to_scene(spot.centroid));
+    // Without the correction the hit is reported well off the target
+    let uncorrected = mapping.with_lens(None).to_scene(spot.centroid).distance(target);
+    assert!(uncorrected > 0.15, "{}", uncorrected);
+}
+
+#[test]
+fn test_lens_estimated_from_a_heated_grid() {
+    let lens = barrel_lens();
+    let mut simulator = simulator(Some(lens));
+    heated_grid(&mut simulator);
+    let frame = simulator.next_frame().unwrap().unwrap();
+
+    let points = find_grid_points(&frame, &simulator.radiometry, 5.0, UVec2::new(7, 5)).unwrap();
+    let fit = estimate_lens(&points, UVec2::new(7, 5), Vec2::splat(256.0), Vec2::new(128.0, 96.0)).unwrap();
+
+    assert_eq!(points.len(), 35);
+    assert!(points[0].x < points[1].x && points[0].y < points[7].y);
+    assert!(fit.rms_error < 0.2, "rms error {}", fit.rms_error);
+    assert!((fit.lens.radial[0] - lens.radial[0]).abs() < 0.03, "{:?}", fit.lens);
+
+    // The estimated lens brings a corner hit back on target
+    let mut mapping = simulator.mapping().with_lens(Some(fit.lens));
+    let corner = mapping.to_pixel(Vec2::new(-4.5, 3.4));
+    mapping.lens = Some(lens);
+    assert!(mapping.to_scene(corner).distance(Vec2::new(-4.5, 3.4)) < 0.05);
+}
+
+#[test]
+fn test_grid_capture_errors() {
+    let mut simulator = simulator(None);
+    heated_grid(&mut simulator);
+    simulator.hits.pop();
+    let frame = simulator.next_frame().unwrap().unwrap();
+
+    assert_eq!(
+        find_grid_points(&frame, &simulator.radiometry, 5.0, UVec2::new(7, 5)),
+        Err(LensCalibrationError::PointCount { expected: 35, found: 34 })
+    );
+    assert_eq!(
+        find_grid_points(&frame, &simulator.radiometry, 5.0, UVec2::new(2, 17)),
+        Err(LensCalibrationError::GridTooSmall(UVec2::new(2, 17)))
+    );
+    // A frame without pixels finds no points instead of panicking
+    let empty = ThermalFrame::filled(UVec2::ZERO, 0, Duration::ZERO);
+    assert_eq!(
+        find_grid_points(&empty, &simulator.radiometry, 5.0, UVec2::new(7, 5)),
+        Err(LensCalibrationError::PointCount { expected: 35, found: 0 })
+    );
+
+    // Without distortion the fitted coefficients stay close to zero
+    simulator.hits.clear();
+    heated_grid(&mut simulator);
+    let frame = simulator.next_frame().unwrap().unwrap();
+    let points = find_grid_points(&frame, &simulator.radiometry, 5.0, UVec2::new(7, 5)).unwrap();
+    let fit = estimate_lens(&points, UVec2::new(7, 5), Vec2::splat(256.0), Vec2::new(128.0, 96.0)).unwrap();
+    assert!(fit.lens.radial[0].abs() < 0.01, "{:?}", fit.lens);
+}
+
+#[test]
+fn test_camera_optics_field_of_view() {
+    let optics = CameraOptics::default();
+    let fov = optics.fov();
+
+    // The default lens sees the default scene
+    assert!((optics.scene_width(25.0) - 10.0).abs() < 1e-4);
+    assert!((fov.x - 2.0 * (5.0_f32 / 25.0).atan().to_degrees()).abs() < 1e-3, "{}", fov);
+    assert!(fov.y < fov.x);
+    assert!((CameraOptics::from_fov(fov.x, optics.sensor_size).focal_length - optics.focal_length).abs() < 1e-4);
+
+    // 256 px across a 3.072 mm sensor, so a 256 px focal length is 3.072 mm
+    let calibrated = CameraOptics::from_intrinsics(&barrel_lens(), RESOLUTION, optics.sensor_size);
+    assert!((calibrated.focal_length - 3.072).abs() < 1e-4);
+    assert!((calibrated.fov().x - 2.0 * 0.5_f32.atan().to_degrees()).abs() < 1e-3);
+}
+
+#[test]
+fn test_scene_width_and_optics_follow_each_other() {
+    let mut optics = CameraOptics::default();
+    let previous = SceneConfiguration::default();
+
+    // A new distance keeps the field of view and widens the scene
+    let mut scene = SceneConfiguration { target_projection_distance: 30.0, ..previous.clone() };
+    optics.sync_scene(&mut scene, &previous);
+    assert!((scene.scene_width - 12.0).abs() < 1e-4, "{}", scene.scene_width);
+    assert_eq!(optics, CameraOptics::default());
+
+    // A new width keeps the distance and narrows the field of view
+    let previous = scene.clone();
+    scene.scene_width = 6.0;
+    optics.sync_scene(&mut scene, &previous);
+    assert_eq!(scene.scene_width, 6.0);
+    assert!((optics.scene_width(30.0) - 6.0).abs() < 1e-4);
+    assert!(optics.fov().x < CameraOptics::default().fov().x);
+}
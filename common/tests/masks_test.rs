@@ -0,0 +1,197 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::{FrameSource, ThermalFrame};
+use common::masks::{ExclusionMask, HotSpotLearner, MaskOrigin, PixelMask};
+use common::simulator::{ThermalSimulator, WarmBody};
+use common::tracking::{SpotTracker, TrackingConfig};
+use common::venue::VenueConfiguration;
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+
+fn simulator() -> ThermalSimulator {
+    ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), 25.0).unwrap()
+}
+
+/// Run frames through detection and tracking, returning where shots were reported
+fn shots(source: &mut dyn FrameSource, detector: &mut SpotDetector, frames: usize) -> Vec<Vec2> {
+    let mapping = simulator().mapping();
+    let mut tracker = SpotTracker::new(TrackingConfig::default());
+    let mut shots = Vec::new();
+    for _ in 0..frames {
+        let frame = source.next_frame().unwrap().unwrap();
+        let spots = detector.process(&frame);
+        shots.extend(tracker.update(&spots, frame.timestamp).iter().map(|blob| mapping.to_scene(blob.centroid)));
+    }
+    shots
+}
+
+/// A radiator on the left of the wall
+fn radiator() -> WarmBody {
+    WarmBody {
+        start: Vec2::new(-3.0, -2.0),
+        velocity: Vec2::ZERO,
+        size: Vec2::new(0.8, 0.4),
+        temperature: 45.0,
+        appears_at: Duration::ZERO,
+        leaves_at: Duration::MAX,
+    }
+}
+
+#[test]
+fn test_polygon_contains() {
+    let square = ExclusionMask::rectangle("square", Vec2::new(1.0, 1.0), Vec2::new(-1.0, -1.0), MaskOrigin::Drawn);
+    // An L shape, concave around (1.5, 1.5)
+    let corner = ExclusionMask::new(
+        "corner",
+        &[Vec2::ZERO, Vec2::new(2.0, 0.0), Vec2::new(2.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(1.0, 2.0), Vec2::new(0.0, 2.0)],
+        MaskOrigin::Drawn,
+    );
+
+    assert!(square.contains(Vec2::ZERO));
+    assert!(!square.contains(Vec2::new(1.5, 0.0)));
+    assert!(corner.contains(Vec2::new(0.5, 1.5)));
+    assert!(corner.contains(Vec2::new(1.5, 0.5)));
+    assert!(!corner.contains(Vec2::new(1.5, 1.5)));
+    assert!(!ExclusionMask::new("line", &[Vec2::ZERO, Vec2::ONE], MaskOrigin::Drawn).contains(Vec2::splat(0.5)));
+}
+
+#[test]
+fn test_spots_inside_masks_are_ignored() {
+    let mut simulator = simulator();
+    let mask = ExclusionMask::rectangle("lamp", Vec2::new(1.0, 1.0), Vec2::new(2.0, 2.0), MaskOrigin::Drawn);
+    let pixel_mask = PixelMask::new(std::slice::from_ref(&mask), &simulator.mapping());
+    simulator.shoot(FRAME * 5, Vec2::new(1.5, 1.5));
+    simulator.shoot(FRAME * 10, Vec2::new(-1.5, 1.5));
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    detector.mask = Some(pixel_mask.clone());
+
+    let shots = shots(&mut simulator, &mut detector, 20);
+
+    // A square meter covers about 25 by 25 pixels of the default camera
+    assert!((600..=700).contains(&pixel_mask.count()), "{} masked pixels", pixel_mask.count());
+    assert_eq!(shots.len(), 1, "got {:?}", shots);
+    assert!(shots[0].distance(Vec2::new(-1.5, 1.5)) < 0.05);
+}
+
+#[test]
+fn test_slowly_warming_area_is_rejected() {
+    let resolution = UVec2::new(64, 48);
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    let mut blobs = Vec::new();
+    for index in 0..60u32 {
+        let mut frame = ThermalFrame::filled(resolution, 18000, FRAME * index);
+        // A heater warming up by 0.2 K per frame
+        let heater = 18000 + 13 * index as u16;
+        for (x, y) in (10..14).flat_map(|x| (10..14).map(move |y| (x, y))) {
+            frame.set(x, y, heater);
+        }
+        // A laser hit on the heater, then one on the wall
+        if (40..44).contains(&index) {
+            frame.set(12, 12, heater + 1500);
+        }
+        if (50..54).contains(&index) {
+            frame.set(40, 30, 19500);
+            frame.set(41, 30, 19500);
+        }
+        blobs.push(detector.process(&frame));
+    }
+
+    let found: Vec<u32> = (0..60).filter(|&index| !blobs[index as usize].is_empty()).collect();
+    assert_eq!(found, vec![50, 51, 52, 53]);
+}
+
+#[test]
+fn test_spot_next_to_a_person_is_rejected() {
+    let mut simulator = simulator();
+    simulator.bodies.push(WarmBody {
+        start: Vec2::new(2.0, -1.0),
+        velocity: Vec2::new(0.05, 0.0),
+        size: Vec2::new(0.5, 1.7),
+        temperature: 32.0,
+        appears_at: FRAME * 2,
+        leaves_at: FRAME * 40,
+    });
+    // A warm hand against the wall, then a real hit far from the person
+    simulator.shoot(FRAME * 10, Vec2::new(2.3, -0.2));
+    simulator.shoot(FRAME * 20, Vec2::new(-2.0, 1.0));
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+
+    let shots = shots(&mut simulator, &mut detector, 60);
+
+    assert_eq!(shots.len(), 1, "got {:?}", shots);
+    assert!(shots[0].distance(Vec2::new(-2.0, 1.0)) < 0.05);
+}
+
+#[test]
+fn test_moving_spot_is_rejected() {
+    let mut simulator = simulator();
+    // A small warm object sliding along the wall, half a pixel per frame
+    simulator.bodies.push(WarmBody {
+        start: Vec2::new(-1.0, 0.0),
+        velocity: Vec2::new(0.5, 0.0),
+        size: Vec2::new(0.1, 0.1),
+        temperature: 35.0,
+        appears_at: FRAME * 5,
+        leaves_at: FRAME * 100,
+    });
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+
+    let frames_with_spots = (0..100)
+        .filter(|_| !detector.process(&simulator.next_frame().unwrap().unwrap()).is_empty())
+        .count();
+
+    // Only until it moved farther than the warm region margin
+    assert!((1..=8).contains(&frames_with_spots), "spots on {} frames", frames_with_spots);
+}
+
+#[test]
+fn test_learned_hot_spots_mask_the_radiator() {
+    let mut simulator = simulator();
+    simulator.noise = 0.3;
+    simulator.bodies.push(radiator());
+    let mut learner = HotSpotLearner::new(simulator.resolution());
+    for _ in 0..50 {
+        learner.add(&simulator.next_frame().unwrap().unwrap());
+    }
+
+    let config = DetectionConfig::default();
+    let masks = learner.masks(config.threshold, &simulator.radiometry, &simulator.mapping());
+
+    assert_eq!(learner.frames(), 50);
+    assert_eq!(masks.len(), 1, "got {:?}", masks);
+    assert_eq!(masks[0].origin, MaskOrigin::Learned);
+    assert!(masks[0].contains(Vec2::new(-3.35, -1.85)));
+    assert!(!masks[0].contains(Vec2::new(-2.0, -2.0)));
+
+    // Someone leaning on the hot radiator does not score
+    simulator.shoot(FRAME * 60, Vec2::new(-3.0, -2.0));
+    simulator.shoot(FRAME * 70, Vec2::new(0.0, -2.0));
+    let mut detector = SpotDetector::new(config);
+    detector.mask = Some(PixelMask::new(&masks, &simulator.mapping()));
+    let shots = shots(&mut simulator, &mut detector, 30);
+    assert_eq!(shots.len(), 1, "got {:?}", shots);
+}
+
+#[test]
+fn test_venue_configuration_roundtrip() {
+    let dir = std::env::temp_dir().join(format!("lasertargets-venue-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("venue.json");
+    let mut venue = VenueConfiguration::default();
+    venue.masks.push(ExclusionMask::rectangle("door", Vec2::ZERO, Vec2::ONE, MaskOrigin::Drawn));
+    venue.masks.push(ExclusionMask::rectangle("Hot spot 1", Vec2::ZERO, Vec2::ONE, MaskOrigin::Learned));
+    venue.replace_learned_masks(vec![ExclusionMask::rectangle("Hot spot 1", -Vec2::ONE, Vec2::ZERO, MaskOrigin::Learned)]);
+
+    venue.save(&path).unwrap();
+    let loaded = VenueConfiguration::load(&path).unwrap();
+
+    assert_eq!(loaded, venue);
+    assert_eq!(loaded.masks.len(), 2);
+    assert_eq!(loaded.masks[1].polygon[0], [-1.0, -1.0]);
+    assert!(VenueConfiguration::load(dir.join("missing.json")).is_err());
+    std::fs::write(&path, "{\"masks\": 3}").unwrap();
+    assert!(VenueConfiguration::load(&path).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
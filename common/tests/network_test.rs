@@ -1,4 +1,7 @@
-use common::network::{NetworkMessage, SERVER_PORT, SERVER_HOST};
+use common::network::{
+    LaneCommand, NetworkMessage, OperatorCommand, OperatorError, OperatorReply, SceneUpdate, TargetSpec, SERVER_PORT,
+    SERVER_HOST,
+};
 
 #[test]
 fn test_network_message_ping_creation() {
@@ -135,3 +138,54 @@ fn test_different_messages_different_serialization() {
     // Even with same timestamp, the variant discriminant should make them different
     assert_ne!(ping_bytes, pong_bytes, "Ping and Pong should serialize differently");
 }
+
+#[test]
+fn test_operator_messages_roundtrip() {
+    let messages = vec![
+        NetworkMessage::Operator {
+            token: "secret".to_string(),
+            command: OperatorCommand::Lane {
+                lane: 2,
+                command: LaneCommand::UpdateScene(SceneUpdate {
+                    target_projection_distance: None,
+                    scene_width: Some(6.0),
+                }),
+            },
+        },
+        NetworkMessage::Operator {
+            token: "secret".to_string(),
+            command: OperatorCommand::Lane {
+                lane: 1,
+                command: LaneCommand::LoadTargets(vec![TargetSpec { x: 1.0, y: -1.0, radius: 0.25 }]),
+            },
+        },
+        NetworkMessage::OperatorReply(OperatorReply::Rejected(OperatorError::Unauthorized("no".to_string()))),
+        NetworkMessage::OperatorReply(OperatorReply::Rejected(OperatorError::Conflict("busy".to_string()))),
+    ];
+
+    for message in messages {
+        let serialized = bincode::serialize(&message).unwrap();
+        let deserialized: NetworkMessage = bincode::deserialize(&serialized).unwrap();
+
+        match (message, deserialized) {
+            (
+                NetworkMessage::Operator { token: t1, command: c1 },
+                NetworkMessage::Operator { token: t2, command: c2 },
+            ) => {
+                assert_eq!(t1, t2);
+                assert_eq!(c1, c2);
+            }
+            (NetworkMessage::OperatorReply(r1), NetworkMessage::OperatorReply(r2)) => assert_eq!(r1, r2),
+            _ => panic!("Roundtrip failed"),
+        }
+    }
+}
+
+#[test]
+fn test_lane_of_messages() {
+    assert_eq!(NetworkMessage::ShotRegistered { lane: 3, x: 0.0, y: 0.0, target_id: None }.lane(), Some(3));
+    assert_eq!(NetworkMessage::JoinLane { lane: 2 }.lane(), Some(2));
+    assert_eq!(NetworkMessage::ShotReported { lane: 4, x: 0.0, y: 0.0, detected_at_ms: 0 }.lane(), Some(4));
+    assert_eq!(NetworkMessage::Ping { timestamp: 0 }.lane(), None);
+    assert_eq!(NetworkMessage::Operator { token: String::new(), command: OperatorCommand::GetStatus }.lane(), None);
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::UVec2;
+use common::frame::ThermalFrame;
+use common::palette::{colorize, Palette, TemperatureRange};
+use common::radiometry::RadiometricCalibration;
+use std::time::Duration;
+
+fn gradient() -> ThermalFrame {
+    let mut frame = ThermalFrame::filled(UVec2::new(100, 1), 0, Duration::ZERO);
+    for x in 0..100 {
+        frame.set(x, 0, 1000 + x as u16 * 10);
+    }
+    frame
+}
+
+#[test]
+fn test_palette_ends() {
+    assert_eq!(Palette::Grayscale.color(0.0), [0, 0, 0]);
+    assert_eq!(Palette::Grayscale.color(1.0), [255, 255, 255]);
+    assert_eq!(Palette::Grayscale.color(0.5), [128, 128, 128]);
+    assert_eq!(Palette::Iron.color(-1.0), [0, 0, 0]);
+    assert_eq!(Palette::Iron.color(2.0), [255, 255, 255]);
+    assert_eq!(Palette::Rainbow.color(0.0), [0, 0, 255]);
+    assert_eq!(Palette::Rainbow.color(1.0), [255, 0, 0]);
+}
+
+#[test]
+fn test_auto_range_ignores_dead_pixels() {
+    let mut frame = gradient();
+    frame.set(0, 0, 0);
+
+    let (min, max) = TemperatureRange::Auto.bounds(&frame, &RadiometricCalibration::default());
+
+    assert_eq!((min, max), (1010.0, 1980.0));
+}
+
+#[test]
+fn test_auto_range_of_tiny_frames() {
+    let calibration = RadiometricCalibration::default();
+    let single = ThermalFrame::filled(UVec2::new(1, 1), 1200, Duration::ZERO);
+    let empty = ThermalFrame::filled(UVec2::ZERO, 0, Duration::ZERO);
+
+    assert_eq!(TemperatureRange::Auto.bounds(&single, &calibration), (1200.0, 1200.0));
+    assert_eq!(TemperatureRange::Auto.bounds(&empty, &calibration), (0.0, 1.0));
+}
+
+#[test]
+fn test_colorize_with_manual_range() {
+    let frame = gradient();
+    // One degree per count, so the range covers counts 1000 to 1500
+    let calibration = RadiometricCalibration { gain: 1.0, offset: 0.0 };
+    let range = TemperatureRange::Manual { min: 1000.0, max: 1500.0 };
+
+    let rgba = colorize(&frame, Palette::Grayscale, range, &calibration);
+
+    assert_eq!(rgba.len(), 400);
+    assert_eq!(&rgba[0..4], &[0, 0, 0, 255]);
+    // Values above the manual range saturate
+    assert_eq!(&rgba[396..400], &[255, 255, 255, 255]);
+}
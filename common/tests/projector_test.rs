@@ -0,0 +1,224 @@
+use bevy::prelude::{Mat4, Transform, UVec2, Vec2, Vec3, Vec4};
+use common::config::{CameraConfiguration, ProjectorConfiguration, SceneConfiguration};
+use common::frame::FrameSource;
+use common::homography::{estimate_homography, Homography};
+use common::projector::{pattern_dots, Keystone, PatternConfig, ProjectorCalibrationError, ProjectorCalibrator};
+use common::layout::Frustum;
+use common::simulator::{HitProfile, ThermalSimulator};
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+const OUTPUT: UVec2 = UVec2::new(800, 800);
+
+/// Where a projector hanging slightly askew throws the corners of its output
+fn projector(half_width: f32) -> Homography {
+    let corners = [
+        (Vec2::new(0.0, 0.0), Vec2::new(-half_width, 3.4)),
+        (Vec2::new(800.0, 0.0), Vec2::new(half_width - 0.2, 3.2)),
+        (Vec2::new(800.0, 800.0), Vec2::new(half_width - 0.1, -3.5)),
+        (Vec2::new(0.0, 800.0), Vec2::new(-half_width - 0.1, -3.3)),
+    ];
+    estimate_homography(&corners).unwrap().homography
+}
+
+/// Project the pattern onto a simulated wall, each dot warming it by `peak` kelvin
+fn calibrate(projector: Homography, peak: f32) -> ProjectorCalibrator {
+    let mut simulator = ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), 25.0).unwrap();
+    simulator.hit_profile = HitProfile {
+        peak,
+        radius: 0.05,
+        decay: Duration::from_secs(10),
+    };
+    let config = PatternConfig { dwell_secs: 0.5, ..Default::default() };
+    let mut calibrator = ProjectorCalibrator::new(OUTPUT, config, simulator.mapping(), simulator.radiometry);
+
+    let mut shown = None;
+    for _ in 0..1000 {
+        let frame = simulator.next_frame().unwrap().unwrap();
+        calibrator.update(&frame);
+        if calibrator.current_dot() != shown {
+            shown = calibrator.current_dot();
+            // The wall starts warming after the baseline frame
+            if let Some(dot) = shown {
+                simulator.shoot(frame.timestamp + FRAME * 2, projector.apply(dot));
+            }
+        }
+        if calibrator.is_finished() {
+            break;
+        }
+    }
+    calibrator
+}
+
+#[test]
+fn test_pattern_dots() {
+    let dots = pattern_dots(UVec2::new(800, 600), &PatternConfig::default());
+
+    assert_eq!(dots.len(), 12);
+    assert_eq!(dots[0], Vec2::new(80.0, 60.0));
+    assert!(dots[1].distance(Vec2::new(80.0 + 640.0 / 3.0, 60.0)) < 1e-3);
+    assert_eq!(dots[4], Vec2::new(80.0, 300.0));
+    assert_eq!(dots[11], Vec2::new(720.0, 540.0));
+}
+
+#[test]
+fn test_projector_mapping_solved_from_dots() {
+    let projector = projector(3.8);
+    let calibrator = calibrate(projector, 3.0);
+    let fit = calibrator.fit().unwrap();
+
+    assert!(calibrator.is_finished());
+    assert_eq!((fit.detected, fit.projected), (12, 12));
+    assert!(fit.rms_error < 0.02, "rms error {}", fit.rms_error);
+    assert!(fit.max_error >= fit.rms_error);
+    assert!(fit.is_acceptable(&PatternConfig::default()));
+    // Corners of the output, outside of the pattern, are extrapolated onto the wall
+    for corner in [Vec2::ZERO, Vec2::new(800.0, 800.0)] {
+        let error = fit.homography.apply(corner).distance(projector.apply(corner));
+        assert!(error < 0.05, "{} is {} m off", corner, error);
+    }
+}
+
+#[test]
+fn test_dots_outside_the_camera_image() {
+    // The outer columns fall beyond the 10 m the camera sees
+    let calibrator = calibrate(projector(8.0), 3.0);
+    let progress = calibrator.progress();
+    let fit = calibrator.fit().unwrap();
+
+    assert_eq!((progress.measured, progress.detected, progress.projected), (12, 6, 12));
+    assert_eq!(fit.detected, 6);
+    assert!(fit.rms_error < 0.02, "rms error {}", fit.rms_error);
+    assert!(!fit.is_acceptable(&PatternConfig::default()));
+}
+
+#[test]
+fn test_dots_too_dim_to_be_found() {
+    let calibrator = calibrate(projector(3.8), 0.5);
+
+    assert!(calibrator.is_finished());
+    assert_eq!(calibrator.fit(), Err(ProjectorCalibrationError::TooFewDots { detected: 0, projected: 12 }));
+}
+
+#[test]
+fn test_keystone_warps_clip_space() {
+    assert!(Keystone::default().is_identity());
+    assert!(Keystone::default().clip_warp().unwrap().abs_diff_eq(Mat4::IDENTITY, 1e-5));
+
+    let keystone = Keystone {
+        corners: [Vec2::new(0.1, 0.0), Vec2::new(0.9, 0.05), Vec2::new(1.0, 1.0), Vec2::new(0.0, 0.95)],
+    };
+    let warp = keystone.clip_warp().unwrap();
+    // The top left of the picture, with a perspective w, lands on the first corner
+    let clip = warp * Vec4::new(-2.0, 2.0, 0.5, 2.0);
+    let device = clip.truncate().truncate() / clip.w;
+    assert!(device.distance(Vec2::new(-0.8, 1.0)) < 1e-4, "{}", device);
+    let clip = warp * Vec4::new(1.0, -1.0, 0.5, 1.0);
+    assert!((clip.truncate().truncate() / clip.w).distance(Vec2::new(1.0, -1.0)) < 1e-4);
+    assert!(!keystone.is_identity());
+}
+
+#[test]
+fn test_keystone_squares_up_the_picture() {
+    let mapping = projector(3.8);
+    let keystone = Keystone::square_up(&mapping, OUTPUT).unwrap();
+    let corners = keystone.corners.map(|corner| mapping.apply(corner * OUTPUT.as_vec2()));
+
+    // Upright and square, as the output is
+    assert!((corners[0].y - corners[1].y).abs() < 1e-3 && (corners[2].y - corners[3].y).abs() < 1e-3, "{:?}", corners);
+    assert!((corners[0].x - corners[3].x).abs() < 1e-3 && (corners[1].x - corners[2].x).abs() < 1e-3, "{:?}", corners);
+    let size = corners[2] - corners[0];
+    assert!((size.x + size.y).abs() < 1e-3, "{}", size);
+    // Inside the output, touching its narrowest side
+    assert!(keystone.corners.iter().all(|corner| corner.cmpge(Vec2::splat(-1e-4)).all() && corner.cmple(Vec2::splat(1.0 + 1e-4)).all()));
+    assert!((size.y.abs() - 6.5).abs() < 1e-3, "{}", size);
+}
+
+/// Scene plane 25 m away, as the terminal places it
+fn scene() -> Transform {
+    Transform::from_xyz(0.0, 3.75, -25.0)
+}
+
+#[test]
+fn test_frustum_on_axis() {
+    let frustum = Frustum {
+        transform: Transform::from_xyz(0.0, 3.75, 0.0),
+        angle: 25.0,
+        resolution: UVec2::new(800, 600),
+    };
+    let footprint = frustum.footprint(&scene()).unwrap();
+    let half = 25.0 * 12.5_f32.to_radians().tan();
+
+    assert!(footprint[0].distance(Vec2::new(-half, half * 0.75)) < 1e-3, "{:?}", footprint);
+    assert!(footprint[2].distance(Vec2::new(half, -half * 0.75)) < 1e-3, "{:?}", footprint);
+    assert!(frustum.to_scene(Vec2::new(400.0, 300.0), &scene()).unwrap().length() < 1e-3);
+}
+
+#[test]
+fn test_frustum_off_axis() {
+    // Hung low and to the side, turned towards the scene
+    let projector = ProjectorConfiguration {
+        transform: Transform::from_xyz(4.0, 0.5, 5.0).looking_at(Vec3::new(0.0, 3.75, -25.0), Vec3::Y),
+        ..Default::default()
+    };
+    let frustum = projector.frustum();
+    let footprint = frustum.footprint(&scene()).unwrap();
+
+    // The far side and the top of the picture are stretched
+    assert!(footprint[1].y - footprint[2].y < footprint[0].y - footprint[3].y, "{:?}", footprint);
+    assert!(footprint[1].x - footprint[0].x > footprint[2].x - footprint[3].x, "{:?}", footprint);
+
+    let mapping = projector.scene_mapping(&scene()).unwrap();
+    for pixel in [Vec2::new(100.0, 700.0), Vec2::new(400.0, 400.0), Vec2::new(650.0, 120.0)] {
+        let position = frustum.to_scene(pixel, &scene()).unwrap();
+        assert!(mapping.apply(pixel).distance(position) < 1e-3);
+        assert!(frustum.to_pixel(position, &scene()).unwrap().distance(pixel) < 1e-2);
+    }
+    assert_eq!(frustum.to_pixel(Vec2::ZERO, &Transform::from_xyz(0.0, 0.0, 10.0)), None);
+}
+
+#[test]
+fn test_keystone_throws_scene_points_onto_their_projector_pixels() {
+    let projector = ProjectorConfiguration {
+        transform: Transform::from_xyz(4.0, 0.5, 5.0).looking_at(Vec3::new(0.0, 3.75, -25.0), Vec3::Y),
+        ..Default::default()
+    };
+    let frustum = projector.frustum();
+    // The 2D picture spans the scene, its fractions counted from the top left with y down
+    let extent = Vec2::new(10.0, 7.5);
+    let keystone = Keystone::through(&frustum.homography(&scene()).unwrap(), OUTPUT, extent).unwrap();
+    let warp = keystone.homography().unwrap();
+
+    for position in [Vec2::ZERO, Vec2::new(-4.0, 3.0), Vec2::new(2.5, -1.5)] {
+        let fraction = Vec2::new(position.x / extent.x + 0.5, 0.5 - position.y / extent.y);
+        let pixel = warp.apply(fraction) * OUTPUT.as_vec2();
+        let expected = frustum.to_pixel(position, &scene()).unwrap();
+        assert!(pixel.distance(expected) < 0.05, "{} {}", pixel, expected);
+    }
+}
+
+#[test]
+fn test_angle_to_cover_the_scene() {
+    let dimensions = Vec2::new(10.0, 7.5);
+    let on_axis = Frustum {
+        transform: Transform::from_xyz(0.0, 3.75, 0.0),
+        angle: 0.0,
+        resolution: UVec2::new(800, 800),
+    };
+    let angle = on_axis.angle_to_cover(&scene(), dimensions).unwrap();
+    assert!((angle - 2.0 * (5.0_f32 / 25.0).atan().to_degrees()).abs() < 1e-3, "{}", angle);
+
+    let off_axis = Frustum {
+        transform: Transform::from_xyz(3.0, 1.5, 5.0).looking_at(Vec3::new(0.0, 3.75, -25.0), Vec3::Y),
+        ..on_axis
+    };
+    let angle = off_axis.angle_to_cover(&scene(), dimensions).unwrap();
+    let frustum = Frustum { angle, ..off_axis };
+    let corners = [Vec2::new(-5.0, 3.75), Vec2::new(5.0, 3.75), Vec2::new(5.0, -3.75), Vec2::new(-5.0, -3.75)];
+    let pixels: Vec<Vec2> = corners.iter().map(|&corner| frustum.to_pixel(corner, &scene()).unwrap()).collect();
+
+    // Every corner is thrown, the farthest one on the edge of the output
+    assert!(pixels.iter().all(|pixel| pixel.cmpge(Vec2::splat(-1e-2)).all() && pixel.cmple(Vec2::splat(800.01)).all()), "{:?}", pixels);
+    assert!(pixels.iter().any(|pixel| pixel.min_element() < 1e-2 || pixel.max_element() > 799.99), "{:?}", pixels);
+    assert_eq!(off_axis.angle_to_cover(&Transform::from_xyz(0.0, 0.0, 10.0), dimensions), None);
+}
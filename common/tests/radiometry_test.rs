@@ -0,0 +1,134 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::{FrameSource, ThermalFrame};
+use common::radiometry::{DriftCompensator, DriftConfig, RadiometricCalibration};
+use common::simulator::ThermalSimulator;
+use common::tracking::{SpotTracker, TrackingConfig};
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+
+fn frame(value: u16) -> ThermalFrame {
+    ThermalFrame::filled(UVec2::new(16, 12), value, Duration::ZERO)
+}
+
+/// Run the simulated frames through detection and tracking, returning the frames shots were reported on
+fn shot_frames(simulator: &mut ThermalSimulator, detection: DetectionConfig, frames: u32) -> Vec<u32> {
+    let mut detector = SpotDetector::new(detection);
+    detector.calibration = simulator.radiometry;
+    let mut tracker = SpotTracker::new(TrackingConfig::default());
+    let mut shots = Vec::new();
+    for index in 0..frames {
+        let frame = simulator.next_frame().unwrap().unwrap();
+        let spots = detector.process(&frame);
+        shots.extend(tracker.update(&spots, frame.timestamp).iter().map(|_| index));
+    }
+    shots
+}
+
+#[test]
+fn test_calibration_converts_both_ways() {
+    let calibration = RadiometricCalibration::default();
+
+    assert_eq!(calibration.to_celsius(0.0), -273.15);
+    assert!((calibration.to_celsius(calibration.to_counts(21.5)) - 21.5).abs() < 1e-3);
+
+    let camera = RadiometricCalibration { gain: 0.05, offset: -40.0 };
+    assert_eq!(camera.to_counts(10.0), 1000.0);
+}
+
+#[test]
+fn test_slow_drift_is_compensated() {
+    let calibration = RadiometricCalibration::default();
+    let mut compensator = DriftCompensator::new(DriftConfig::default());
+
+    // The camera warming up by 2 counts per frame
+    let corrections: Vec<_> =
+        (0..100).map(|index| compensator.update(&frame(18000 + index * 2), &calibration)).collect();
+
+    assert!(corrections.iter().all(|correction| !correction.shutter));
+    // The last frame read 198 counts above the first one
+    let residual = 198.0 + corrections.last().unwrap().offset;
+    assert!(residual.abs() <= 10.0, "residual drift of {} counts", residual);
+}
+
+#[test]
+fn test_shutter_event_is_compensated_at_once() {
+    let calibration = RadiometricCalibration::default();
+    let mut compensator = DriftCompensator::new(DriftConfig::default());
+    compensator.update(&frame(18000), &calibration);
+    let mut warm = frame(18000);
+    // A hot spot does not move the sensor level
+    warm.set(3, 3, 30000);
+    assert_eq!(compensator.update(&warm, &calibration).offset, 0.0);
+
+    let correction = compensator.update(&frame(18064), &calibration);
+
+    assert!(correction.shutter);
+    assert_eq!(correction.offset, -64.0);
+    assert_eq!(compensator.offset(), -64.0);
+}
+
+#[test]
+fn test_disabled_compensation() {
+    let calibration = RadiometricCalibration::default();
+    let mut compensator = DriftCompensator::new(DriftConfig { enabled: false, ..DriftConfig::default() });
+    compensator.update(&frame(18000), &calibration);
+
+    assert_eq!(compensator.update(&frame(19000), &calibration).offset, 0.0);
+}
+
+/// A camera warming up with two flat-field corrections, and a hit right after each of them
+fn drifting_simulator() -> ThermalSimulator {
+    let mut simulator =
+        ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), 25.0).unwrap();
+    simulator.noise = 0.2;
+    simulator.drift = 0.5;
+    simulator.shutter_steps = vec![(FRAME * 30, 3.0), (FRAME * 70, -2.0)];
+    simulator.shoot(FRAME * 10, Vec2::new(1.0, 1.0));
+    simulator.shoot(FRAME * 31, Vec2::new(-1.0, 0.0));
+    simulator.shoot(FRAME * 72, Vec2::new(0.0, -1.0));
+    simulator
+}
+
+#[test]
+fn test_shots_survive_drift_and_shutter_events() {
+    let shots = shot_frames(&mut drifting_simulator(), DetectionConfig::default(), 100);
+
+    assert_eq!(shots, vec![10, 31, 72]);
+}
+
+#[test]
+fn test_shot_after_shutter_event_is_missed_without_compensation() {
+    let detection = DetectionConfig {
+        drift: DriftConfig { enabled: false, ..DriftConfig::default() },
+        ..DetectionConfig::default()
+    };
+
+    // Right after the shutter event the whole image reads warmer than the background
+    let shots = shot_frames(&mut drifting_simulator(), detection, 100);
+
+    assert!(!shots.contains(&31), "got {:?}", shots);
+}
+
+#[test]
+fn test_thresholds_transfer_between_cameras() {
+    let cameras = [
+        RadiometricCalibration::default(),
+        RadiometricCalibration { gain: 0.04, offset: -40.0 },
+        RadiometricCalibration { gain: 0.01, offset: 0.0 },
+    ];
+
+    for radiometry in cameras {
+        let camera = CameraConfiguration { radiometry, ..CameraConfiguration::default() };
+        let mut simulator = ThermalSimulator::new(&camera, &SceneConfiguration::default(), 25.0).unwrap();
+        simulator.noise = 0.3;
+        simulator.shoot(FRAME * 5, Vec2::new(0.5, 0.5));
+        // Too faint to be a laser hit whatever the camera
+        simulator.hit_profile.peak = 3.0;
+        simulator.shoot(FRAME * 15, Vec2::new(-1.5, 0.5));
+
+        assert_eq!(shot_frames(&mut simulator, DetectionConfig::default(), 30), vec![5], "camera {:?}", radiometry);
+    }
+}
@@ -0,0 +1,147 @@
+use bevy::prelude::UVec2;
+use common::frame::{FrameSource, FrameSourceConfig, FrameSourceError, ThermalFrame};
+use common::radiometry::RadiometricCalibration;
+use common::recording::{FrameRecorder, PlaybackSource, Recording, RecordingHeader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const RESOLUTION: UVec2 = UVec2::new(8, 6);
+
+/// A fresh directory under the system temp dir for one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lasertargets-recording-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn header() -> RecordingHeader {
+    RecordingHeader {
+        resolution: RESOLUTION,
+        radiometry: RadiometricCalibration { gain: 0.04, offset: -40.0 },
+    }
+}
+
+/// Record frames of the given values, 100 ms apart and starting at 5 s
+fn record(path: &Path, values: &[u16]) {
+    let mut recorder = FrameRecorder::create(path, header()).unwrap();
+    for (index, value) in values.iter().enumerate() {
+        let timestamp = Duration::from_secs(5) + Duration::from_millis(100) * index as u32;
+        recorder.write_frame(&ThermalFrame::filled(RESOLUTION, *value, timestamp)).unwrap();
+    }
+    assert_eq!(recorder.frames(), values.len() as u32);
+    recorder.finish().unwrap();
+}
+
+#[test]
+fn test_recording_roundtrip() {
+    let dir = temp_dir("roundtrip");
+    let path = dir.join("session.ltrec");
+    record(&path, &[100, 200, 300]);
+
+    let mut recording = Recording::open(&path).unwrap();
+
+    assert_eq!(recording.header(), header());
+    assert_eq!(recording.len(), 3);
+    assert_eq!(recording.duration(), Duration::from_millis(200));
+    let frame = recording.read_frame(1).unwrap();
+    assert_eq!(frame.resolution, RESOLUTION);
+    assert_eq!(frame.get(7, 5), 200);
+    assert_eq!(frame.timestamp, Duration::from_millis(100));
+    assert_eq!(recording.index_at(Duration::from_millis(150)), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_recorder_rejects_other_resolutions() {
+    let dir = temp_dir("resolution");
+    let mut recorder = FrameRecorder::create(dir.join("session.ltrec"), header()).unwrap();
+
+    let result = recorder.write_frame(&ThermalFrame::filled(UVec2::new(4, 4), 0, Duration::ZERO));
+
+    assert!(matches!(result, Err(FrameSourceError::InvalidConfiguration(_))));
+    assert_eq!(recorder.frames(), 0);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_invalid_and_truncated_recordings() {
+    let dir = temp_dir("invalid");
+    let foreign = dir.join("foreign.ltrec");
+    std::fs::write(&foreign, [0u8; 64]).unwrap();
+    let truncated = dir.join("truncated.ltrec");
+    record(&truncated, &[100, 200]);
+    let mut bytes = std::fs::read(&truncated).unwrap();
+    bytes.truncate(bytes.len() - 10);
+    std::fs::write(&truncated, bytes).unwrap();
+
+    assert!(matches!(Recording::open(&foreign), Err(FrameSourceError::InvalidRecording { .. })));
+    assert!(matches!(Recording::open(&truncated), Err(FrameSourceError::TruncatedFrame { .. })));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_playback_pause_seek_and_speed() {
+    let dir = temp_dir("playback");
+    let path = dir.join("session.ltrec");
+    record(&path, &[1, 2, 3, 4, 5]);
+    let mut playback = PlaybackSource::open(&path).unwrap();
+    let mut next = || {
+        let frame = playback.next_frame().unwrap().unwrap();
+        (frame.get(0, 0), frame.timestamp)
+    };
+
+    assert_eq!(next(), (1, Duration::ZERO));
+    assert_eq!(next(), (2, Duration::from_millis(100)));
+
+    playback.set_paused(true);
+    let (value, _) = playback.next_frame().unwrap().map(|frame| (frame.get(0, 0), frame.timestamp)).unwrap();
+    assert_eq!(value, 2);
+    assert_eq!(playback.position(), Duration::from_millis(100));
+
+    playback.set_paused(false);
+    playback.set_speed(2.0).unwrap();
+    playback.seek(Duration::from_millis(300));
+    let first = playback.next_frame().unwrap().unwrap();
+    let second = playback.next_frame().unwrap().unwrap();
+    assert_eq!((first.get(0, 0), second.get(0, 0)), (4, 5));
+    // Timestamps keep increasing across seeks, at half the recorded spacing
+    assert!(first.timestamp > Duration::from_millis(200));
+    assert_eq!(second.timestamp - first.timestamp, Duration::from_millis(50));
+    assert!(playback.next_frame().unwrap().is_none());
+
+    assert!(playback.set_speed(0.0).is_err());
+    playback.looping = true;
+    assert_eq!(playback.next_frame().unwrap().unwrap().get(0, 0), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_recording_selected_from_config() {
+    let dir = temp_dir("config");
+    let path = dir.join("session.ltrec");
+    record(&path, &[7]);
+
+    let config: FrameSourceConfig =
+        serde_json::from_str(&format!(r#"{{"type":"recording","path":{:?}}}"#, path)).unwrap();
+    let mut source = config.open(RESOLUTION).unwrap();
+
+    assert_eq!(source.next_frame().unwrap().unwrap().get(0, 0), 7);
+    assert!(source.playback().is_some());
+    assert!(config.open(UVec2::new(4, 4)).is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_oversized_resolution_is_rejected() {
+    let dir = temp_dir("oversized");
+    let path = dir.join("oversized.ltrec");
+    record(&path, &[100]);
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    std::fs::write(&path, bytes).unwrap();
+
+    assert!(matches!(Recording::open(&path), Err(FrameSourceError::InvalidRecording { .. })));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::Vec2;
+use common::shooter::{mean_point_of_impact, ShooterProfile, ShooterProfiles};
+
+fn assert_near(actual: Vec2, expected: Vec2) {
+    assert!(actual.distance(expected) < 1e-4, "{} != {}", actual, expected);
+}
+
+#[test]
+fn test_mean_point_of_impact() {
+    assert_eq!(mean_point_of_impact(&[]), None);
+    let group = [Vec2::new(0.1, 0.2), Vec2::new(0.3, 0.0), Vec2::new(0.2, 0.1)];
+    assert_near(mean_point_of_impact(&group).unwrap(), Vec2::new(0.2, 0.1));
+}
+
+#[test]
+fn test_zero_scales_with_distance() {
+    let mut profile = ShooterProfile::new("Alice");
+    let aim = Vec2::new(1.0, -0.5);
+    // A group 40 mm right of and 20 mm below the aiming point at 10 m
+    let group = [aim + Vec2::new(0.05, -0.01), aim + Vec2::new(0.03, -0.03), aim + Vec2::new(0.04, -0.02)];
+    let zero = profile.zero_on(&group, aim, 10.0).unwrap();
+    assert!((zero.x - 13.75).abs() < 0.01 && (zero.y + 6.88).abs() < 0.01, "{}", zero);
+
+    // Hits land back on the aiming point at the distance zeroed at
+    assert_near(profile.correct(aim + Vec2::new(0.04, -0.02), 10.0), aim);
+    // and the same angle is twice the offset twice as far
+    assert_near(profile.offset(20.0), Vec2::new(0.08, -0.04));
+    assert_near(profile.correct(aim + Vec2::new(0.08, -0.04), 20.0), aim);
+
+    assert_eq!(profile.zero_on(&[], aim, 10.0), None);
+}
+
+#[test]
+fn test_active_profile() {
+    let mut zeroed = ShooterProfile::new("Bob");
+    zeroed.zero = [0.0, 60.0];
+    let mut profiles = ShooterProfiles { profiles: vec![ShooterProfile::new("Alice"), zeroed], ..Default::default() };
+    let hit = Vec2::new(0.5, 0.5);
+    assert_eq!(profiles.correct(1, hit, 10.0), hit);
+
+    profiles.set_active(1, Some("Bob".to_string()));
+    // One degree high at 10 m
+    assert_near(profiles.correct(1, hit, 10.0), Vec2::new(0.5, 0.5 - 10.0 * 1f32.to_radians().tan()));
+    // Only on the lane Bob is on
+    assert_eq!(profiles.correct(2, hit, 10.0), hit);
+
+    profiles.set_active(1, Some("Carol".to_string()));
+    assert!(profiles.active(1).is_none());
+    assert_eq!(profiles.correct(1, hit, 10.0), hit);
+
+    profiles.set_active(1, None);
+    assert!(profiles.lanes.is_empty());
+}
+
+#[test]
+fn test_profiles_round_trip() {
+    let path = std::env::temp_dir().join(format!("shooters-{}.json", std::process::id()));
+    let mut profile = ShooterProfile::new("Alice");
+    profile.zero = [1.5, -2.0];
+    let mut profiles = ShooterProfiles { profiles: vec![profile], ..Default::default() };
+    profiles.set_active(3, Some("Alice".to_string()));
+    profiles.save(&path).unwrap();
+    assert_eq!(ShooterProfiles::load(&path).unwrap(), profiles);
+    std::fs::remove_file(&path).unwrap();
+
+    // Profiles written by hand may leave out the zero
+    let loaded: ShooterProfiles = serde_json::from_str(r#"{"profiles": [{"name": "Bob"}]}"#).unwrap();
+    assert_eq!(loaded.profiles[0].zero, [0.0; 2]);
+    assert!(loaded.lanes.is_empty());
+}
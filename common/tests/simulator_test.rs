@@ -0,0 +1,89 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::frame::FrameSource;
+use common::simulator::{HitProfile, ThermalSimulator, WarmBody};
+use std::time::Duration;
+
+const FPS: f32 = 25.0;
+const FRAME: Duration = Duration::from_millis(40);
+
+fn simulator() -> ThermalSimulator {
+    ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), FPS).unwrap()
+}
+
+/// Counts read at the pixel seeing the given scene-local position
+fn counts_at(simulator: &ThermalSimulator, frame: &common::frame::ThermalFrame, position: Vec2) -> u16 {
+    let pixel = simulator.mapping().to_pixel(position).as_uvec2();
+    frame.get(pixel.x, pixel.y)
+}
+
+#[test]
+fn test_renders_background_at_camera_resolution() {
+    let mut simulator = simulator();
+    simulator.background_temperature = 15.0;
+    let frame = simulator.next_frame().unwrap().unwrap();
+
+    assert_eq!(frame.resolution, CameraConfiguration::default().input_resolution);
+    assert_eq!(frame.get(10, 10), simulator.radiometry.to_counts(15.0).round() as u16);
+    assert_eq!(simulator.next_frame().unwrap().unwrap().timestamp, FRAME);
+}
+
+#[test]
+fn test_noise_and_dead_pixels() {
+    let mut simulator = simulator();
+    simulator.noise = 0.5;
+    simulator.dead_pixels = vec![UVec2::new(3, 4), UVec2::new(1000, 1000)];
+    let frame = simulator.next_frame().unwrap().unwrap();
+
+    assert_eq!(frame.get(3, 4), 0);
+    let background = simulator.radiometry.to_counts(20.0);
+    let deviation = frame
+        .pixels
+        .iter()
+        .filter(|&&pixel| pixel != 0)
+        .map(|&pixel| (pixel as f32 - background).abs())
+        .fold(0.0, f32::max);
+    assert!(deviation > 0.0 && deviation <= 0.5 * 64.0 + 1.0, "noise deviation {}", deviation);
+}
+
+#[test]
+fn test_laser_hit_cools_down() {
+    let mut simulator = simulator();
+    simulator.hit_profile = HitProfile {
+        peak: 20.0,
+        radius: 0.05,
+        decay: Duration::from_millis(80),
+    };
+    let target = Vec2::new(1.0, -0.5);
+    simulator.shoot(FRAME, target);
+
+    let before = simulator.next_frame().unwrap().unwrap();
+    let hit = simulator.next_frame().unwrap().unwrap();
+    let later = simulator.next_frame().unwrap().unwrap();
+
+    let background = simulator.radiometry.to_counts(20.0).round() as u16;
+    assert_eq!(counts_at(&simulator, &before, target), background);
+    let hot = counts_at(&simulator, &hit, target);
+    let cooling = counts_at(&simulator, &later, target);
+    assert!(hot > background + 500, "hit should be warm, read {}", hot);
+    assert!(cooling > background && cooling < hot, "hit should cool down, read {}", cooling);
+    assert_eq!(counts_at(&simulator, &hit, Vec2::new(-2.0, 0.0)), background);
+}
+
+#[test]
+fn test_warm_body_walks_through() {
+    let mut simulator = simulator();
+    simulator.bodies.push(WarmBody {
+        start: Vec2::new(-4.0, 0.0),
+        velocity: Vec2::new(2.0, 0.0),
+        size: Vec2::new(0.5, 1.7),
+        temperature: 34.0,
+        appears_at: Duration::ZERO,
+        leaves_at: Duration::from_secs(5),
+    });
+
+    assert_eq!(simulator.temperature_at(Vec2::new(-4.0, 0.5), Duration::ZERO), 34.0);
+    assert_eq!(simulator.temperature_at(Vec2::new(-4.0, 0.5), Duration::from_secs(1)), 20.0);
+    assert_eq!(simulator.temperature_at(Vec2::new(-2.0, 0.5), Duration::from_secs(1)), 34.0);
+    assert_eq!(simulator.temperature_at(Vec2::new(2.0, 0.0), Duration::from_secs(6)), 20.0);
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::{Rect, UVec2, Vec2};
+use common::config::{CameraConfiguration, CameraFootprint, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::{FrameSource, FrameSourceConfig};
+use common::simulator::ThermalSimulator;
+use common::stitching::{footprint_union, HitMerger, MergeConfig};
+use common::tracking::{SpotTracker, TrackingConfig};
+use common::venue::{CameraPlacement, VenueConfiguration};
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+
+/// A camera covering 10 m of a 19 m wide wall, centered at the given x
+fn camera(center: f32) -> CameraConfiguration {
+    CameraConfiguration {
+        footprint: Some(CameraFootprint { center: [center, 0.0], width: 10.0 }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_footprints_are_placed_in_the_scene() {
+    let scene_width = 19.0;
+    let left = camera(-4.5).mapping(scene_width);
+    let right = camera(4.5).mapping(scene_width);
+
+    assert_eq!(left.to_scene(Vec2::new(128.0, 96.0)), Vec2::new(-4.5, 0.0));
+    assert_eq!(right.to_pixel(Vec2::new(4.5, 0.0)), Vec2::new(128.0, 96.0));
+    assert_eq!(right.footprint(), Rect::new(-0.5, -3.75, 9.5, 3.75));
+    assert_eq!(footprint_union(&[left, right]), Rect::new(-9.5, -3.75, 9.5, 3.75));
+    // Without a footprint the camera spans the whole scene
+    let single = CameraConfiguration::default().mapping(scene_width);
+    assert_eq!(footprint_union(&[single]), Rect::new(-9.5, -7.125, 9.5, 7.125));
+    assert_eq!(footprint_union(&[]), Rect::default());
+}
+
+#[test]
+fn test_merger_counts_hits_of_other_cameras_once() {
+    let mut merger = HitMerger::new(MergeConfig::default());
+
+    assert!(merger.accept(0, Vec2::new(0.0, 1.0), Duration::from_millis(1000)));
+    assert!(!merger.accept(1, Vec2::new(0.05, 1.02), Duration::from_millis(1080)));
+    // The same camera hitting the same place again is another shot
+    assert!(merger.accept(0, Vec2::new(0.0, 1.0), Duration::from_millis(1100)));
+    assert!(merger.accept(1, Vec2::new(2.0, 1.0), Duration::from_millis(1120)));
+    // Too late to be the same shot
+    assert!(merger.accept(1, Vec2::new(0.0, 1.0), Duration::from_millis(1400)));
+}
+
+#[test]
+fn test_hit_in_the_overlap_counts_once() {
+    let scene = SceneConfiguration { scene_width: 19.0, ..Default::default() };
+    let mut cameras: Vec<_> = [-4.5, 4.5]
+        .into_iter()
+        .map(|center| {
+            let mut simulator = ThermalSimulator::new(&camera(center), &scene, 25.0).unwrap();
+            simulator.shoot(FRAME * 5, Vec2::new(0.1, 1.0));
+            simulator.shoot(FRAME * 15, Vec2::new(-6.0, 0.0));
+            simulator.shoot(FRAME * 25, Vec2::new(7.0, -1.0));
+            let detector = SpotDetector::new(DetectionConfig::default());
+            (simulator, detector, SpotTracker::new(TrackingConfig::default()))
+        })
+        .collect();
+    let mut merger = HitMerger::new(MergeConfig::default());
+
+    let mut seen = 0;
+    let mut shots = Vec::new();
+    for _ in 0..40 {
+        for (index, (simulator, detector, tracker)) in cameras.iter_mut().enumerate() {
+            let frame = simulator.next_frame().unwrap().unwrap();
+            let spots = detector.process(&frame);
+            for blob in tracker.update(&spots, frame.timestamp) {
+                let position = simulator.mapping().to_scene(blob.centroid);
+                seen += 1;
+                if merger.accept(index, position, frame.timestamp) {
+                    shots.push(position);
+                }
+            }
+        }
+    }
+
+    assert_eq!(seen, 4);
+    assert_eq!(shots.len(), 3, "got {:?}", shots);
+    assert!(shots[0].distance(Vec2::new(0.1, 1.0)) < 0.05);
+    assert!(shots[2].distance(Vec2::new(7.0, -1.0)) < 0.05);
+}
+
+#[test]
+fn test_venue_cameras_become_configurations() {
+    let json = r#"{
+        "cameras": [{
+            "name": "right",
+            "frame_source": {"type": "synthetic", "fps": 25.0, "background": 18800, "noise": 20},
+            "resolution": [160, 120],
+            "footprint": {"center": [6.0, 0.5], "width": 8.0},
+            "position": [6.0, 1.5, 5.0]
+        }]
+    }"#;
+    let venue: VenueConfiguration = serde_json::from_str(json).unwrap();
+    let main = CameraConfiguration::default();
+
+    let placement: &CameraPlacement = &venue.cameras[0];
+    let configuration = placement.configuration(&main);
+
+    assert!(venue.masks.is_empty());
+    assert_eq!(configuration.input_resolution, UVec2::new(160, 120));
+    assert_eq!(configuration.transform.translation.x, 6.0);
+    assert_eq!(configuration.detection, main.detection);
+    assert!(matches!(configuration.frame_source, FrameSourceConfig::Synthetic { noise: 20, .. }));
+    assert_eq!(configuration.mapping(10.0).footprint(), Rect::new(2.0, -2.5, 10.0, 3.5));
+}
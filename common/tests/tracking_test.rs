@@ -0,0 +1,119 @@
+use bevy::prelude::{UVec2, Vec2};
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{Blob, DetectionConfig, SpotDetector};
+use common::frame::FrameSource;
+use common::simulator::ThermalSimulator;
+use common::tracking::{SpotTracker, TrackingConfig};
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_millis(40);
+
+fn simulator() -> ThermalSimulator {
+    ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), 25.0).unwrap()
+}
+
+/// Run the simulated frames through detection and tracking, returning when and where shots were reported
+fn shots(simulator: &mut ThermalSimulator, frames: usize) -> Vec<(Duration, Vec2)> {
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    let mut tracker = SpotTracker::new(TrackingConfig::default());
+    let mapping = simulator.mapping();
+    let mut shots = Vec::new();
+    for _ in 0..frames {
+        let frame = simulator.next_frame().unwrap().unwrap();
+        let spots = detector.process(&frame);
+        for blob in tracker.update(&spots, frame.timestamp) {
+            shots.push((frame.timestamp, mapping.to_scene(blob.centroid)));
+        }
+    }
+    shots
+}
+
+fn blob(x: f32, y: f32, peak: f32) -> Blob {
+    Blob {
+        centroid: Vec2::new(x, y),
+        pixels: 9,
+        size: UVec2::splat(3),
+        peak,
+    }
+}
+
+#[test]
+fn test_warm_spot_is_reported_once() {
+    let mut simulator = simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(1.0, 1.0));
+
+    let shots = shots(&mut simulator, 100);
+
+    assert_eq!(shots.len(), 1, "got {:?}", shots);
+    assert_eq!(shots[0].0, FRAME * 5);
+}
+
+#[test]
+fn test_second_shot_on_warm_spot_is_reported() {
+    let mut simulator = simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(1.0, 1.0));
+    simulator.shoot(FRAME * 20, Vec2::new(1.0, 1.0));
+
+    let shots = shots(&mut simulator, 60);
+
+    let times: Vec<Duration> = shots.iter().map(|(time, _)| *time).collect();
+    assert_eq!(times, vec![FRAME * 5, FRAME * 20]);
+}
+
+#[test]
+fn test_shot_after_spot_cooled_is_reported() {
+    let mut simulator = simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(-1.0, 0.5));
+    simulator.shoot(FRAME * 130, Vec2::new(-1.0, 0.5));
+
+    let shots = shots(&mut simulator, 150);
+
+    let times: Vec<Duration> = shots.iter().map(|(time, _)| *time).collect();
+    assert_eq!(times, vec![FRAME * 5, FRAME * 130]);
+}
+
+#[test]
+fn test_refractory_period() {
+    let mut simulator = simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(0.0, 0.0));
+    simulator.shoot(FRAME * 6, Vec2::new(0.0, 0.0));
+
+    assert_eq!(shots(&mut simulator, 30).len(), 1);
+}
+
+#[test]
+fn test_spots_are_tracked_independently() {
+    let mut simulator = simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(-2.0, 0.0));
+    simulator.shoot(FRAME * 5, Vec2::new(2.0, 0.0));
+    simulator.shoot(FRAME * 10, Vec2::new(0.0, 1.0));
+
+    let shots = shots(&mut simulator, 40);
+
+    assert_eq!(shots.len(), 3, "got {:?}", shots);
+    assert!(shots[2].1.distance(Vec2::new(0.0, 1.0)) < 0.05);
+}
+
+#[test]
+fn test_flickering_spot_is_not_reported_again() {
+    let mut tracker = SpotTracker::new(TrackingConfig::default());
+
+    assert_eq!(tracker.update(&[blob(10.0, 10.0, 16.0)], FRAME).len(), 1);
+    assert!(tracker.update(&[], FRAME * 2).is_empty());
+    assert!(tracker.update(&[blob(10.5, 10.0, 14.0)], FRAME * 3).is_empty());
+    assert_eq!(tracker.update(&[blob(30.0, 10.0, 14.0)], FRAME * 4).len(), 1);
+}
+
+#[test]
+fn test_cool_down_depends_on_wall_material() {
+    // A wall that cools down quickly lets a spot that reappears count as a new shot
+    let mut tracker = SpotTracker::new(TrackingConfig {
+        cool_down_secs: 0.02,
+        refractory_secs: 0.05,
+        ..TrackingConfig::default()
+    });
+
+    assert_eq!(tracker.update(&[blob(10.0, 10.0, 16.0)], FRAME).len(), 1);
+    assert!(tracker.update(&[], FRAME * 3).is_empty());
+    assert_eq!(tracker.update(&[blob(10.0, 10.0, 14.0)], FRAME * 4).len(), 1);
+}
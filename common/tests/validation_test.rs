@@ -0,0 +1,65 @@
+use bevy::prelude::{Rect, Vec2};
+use common::validation::{moa, reference_grid, ValidationReport};
+use common::venue::VenueConfiguration;
+
+#[test]
+fn test_reference_grid() {
+    let area = Rect::new(-4.0, -3.0, 4.0, 3.0);
+    let grid = reference_grid(area, 4, 3);
+    assert_eq!(grid.len(), 12);
+    // Read like text, from the top left
+    assert_eq!(grid[0], Vec2::new(-3.0, 2.0));
+    assert_eq!(grid[3], Vec2::new(3.0, 2.0));
+    assert_eq!(grid[11], Vec2::new(3.0, -2.0));
+    assert!(grid.iter().all(|point| area.contains(*point)));
+}
+
+#[test]
+fn test_moa() {
+    // One minute of angle is about 29.1 mm at 100 m
+    assert!((moa(0.0291, 100.0) - 1.0).abs() < 0.01);
+    assert!((moa(0.0291, 10.0) - 10.0).abs() < 0.05);
+    assert_eq!(moa(0.0, 10.0), 0.0);
+}
+
+#[test]
+fn test_validation_report() {
+    let references = [Vec2::new(-1.0, 1.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, -1.0)];
+    let mut report = ValidationReport::new(&references, 10.0, 1_700_000_000);
+    assert_eq!(report.rms_error(), None);
+    assert_eq!(report.next_point(), Some(0));
+
+    assert_eq!(report.record(Vec2::new(-0.97, 1.04)), Some(0));
+    assert_eq!(report.record(Vec2::new(1.0, 1.0)), Some(1));
+    assert!(!report.is_complete());
+    assert!((report.max_error().unwrap() - 0.05).abs() < 1e-4);
+    // 50 mm and 0 mm
+    assert!((report.rms_error().unwrap() - (0.05f32.powi(2) / 2.0).sqrt()).abs() < 1e-4);
+
+    assert_eq!(report.record(Vec2::new(0.0, -1.05)), Some(2));
+    assert!(report.is_complete());
+    assert_eq!(report.record(Vec2::ZERO), None);
+    let mean = report.mean_error().unwrap();
+    assert!(mean.distance(Vec2::new(0.01, -0.01 / 3.0)) < 1e-4, "{}", mean);
+
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].starts_with("1,-1.0000,1.0000,-0.9700,1.0400,50.0,17.19"), "{}", lines[1]);
+    assert!(lines[4].starts_with("rms,,,,,40.8,"), "{}", lines[4]);
+}
+
+#[test]
+fn test_report_saved_with_venue() {
+    let mut venue = VenueConfiguration::default();
+    let mut report = ValidationReport::new(&[Vec2::ZERO, Vec2::ONE], 8.0, 1);
+    report.record(Vec2::new(0.01, 0.0));
+    venue.validation = Some(report);
+    let json = serde_json::to_string(&venue).unwrap();
+    let loaded: VenueConfiguration = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, venue);
+
+    // Venues saved before validation reports existed still load
+    let loaded: VenueConfiguration = serde_json::from_str("{\"masks\": []}").unwrap();
+    assert_eq!(loaded.validation, None);
+}
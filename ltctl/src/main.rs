@@ -0,0 +1,288 @@
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::prelude::*;
+use bevy_quinnet::client::{
+    QuinnetClientPlugin, QuinnetClient,
+    certificate::CertificateVerificationMode,
+    connection::{ClientAddrConfiguration, ConnectionState},
+    ClientConnectionConfiguration,
+};
+use common::network::{
+    LaneCommand, NetworkMessage, OperatorCommand, OperatorReply, SceneUpdate, TargetSpec, DEFAULT_LANE,
+    SERVER_PORT,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: ltctl [--server <ip[:port]>] [--token <token>] [--lane <id>] [--timeout <secs>] <command>
+
+Commands:
+  clients                               List connected clients
+  status                                Show match, scene, target and score overview of all lanes
+  start | pause | stop                  Control the round on the lane
+  targets                               List the targets of the lane
+  load <layout.json>                    Replace the targets of the lane with a layout file
+  scene [--distance <m>] [--width <m>]  Show or change the scene of the lane
+  record [start | stop]                 Show or change whether the terminals of the lane record thermal frames
+  tail                                  Print game events of all lanes as JSON lines until interrupted";
+
+/// Environment variable holding the operator token unless `--token` is given
+const TOKEN_VARIABLE: &str = "LTCTL_TOKEN";
+
+/// How long to wait for the connection and the reply unless `--timeout` is given
+const DEFAULT_TIMEOUT_SECS: f32 = 5.0;
+
+/// What to do once connected to the server
+#[derive(Debug)]
+enum Action {
+    /// Send one operator command and print the reply
+    Command(OperatorCommand),
+    /// Print every game event until interrupted
+    Tail,
+}
+
+/// The parsed command line and its progress
+#[derive(Resource, Debug)]
+struct Invocation {
+    server: SocketAddr,
+    /// Operator token the server was started with
+    token: String,
+    action: Action,
+    /// Fails the invocation when connecting or waiting for the reply takes too long
+    timeout: Timer,
+    sent: bool,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Invocation, String> {
+    let mut server = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), SERVER_PORT);
+    let mut token = std::env::var(TOKEN_VARIABLE).unwrap_or_default();
+    let mut lane = DEFAULT_LANE;
+    let mut timeout_secs = DEFAULT_TIMEOUT_SECS;
+    let mut args = args.peekable();
+
+    while let Some(option) = args.next_if(|arg| arg.starts_with("--")) {
+        match option.as_str() {
+            "--server" => server = parse_server(args.next())?,
+            "--token" => token = args.next().ok_or("--token expects the operator token")?,
+            "--lane" => lane = args.next().and_then(|id| id.parse().ok()).ok_or("--lane expects a lane id")?,
+            "--timeout" => {
+                timeout_secs = args
+                    .next()
+                    .and_then(|secs| secs.parse().ok())
+                    .filter(|secs: &f32| *secs > 0.0)
+                    .ok_or("--timeout expects a number of seconds")?
+            }
+            _ => return Err(format!("Unknown option: {}", option)),
+        }
+    }
+
+    let command = args.next().ok_or("Missing command")?;
+    let on_lane = |command| Action::Command(OperatorCommand::Lane { lane, command });
+    let action = match command.as_str() {
+        "clients" => Action::Command(OperatorCommand::ListClients),
+        "status" => Action::Command(OperatorCommand::GetStatus),
+        "start" => on_lane(LaneCommand::StartMatch),
+        "pause" => on_lane(LaneCommand::PauseMatch),
+        "stop" => on_lane(LaneCommand::StopMatch),
+        "targets" => on_lane(LaneCommand::ListTargets),
+        "load" => {
+            let path = args.next().ok_or("load expects a layout file")?;
+            on_lane(LaneCommand::LoadTargets(read_layout(&path)?))
+        }
+        "scene" => {
+            let mut update = SceneUpdate::default();
+            while let Some(option) = args.next() {
+                let value = args
+                    .next()
+                    .and_then(|meters| meters.parse().ok())
+                    .ok_or_else(|| format!("{} expects a distance in meters", option))?;
+                match option.as_str() {
+                    "--distance" => update.target_projection_distance = Some(value),
+                    "--width" => update.scene_width = Some(value),
+                    _ => return Err(format!("Unknown scene option: {}", option)),
+                }
+            }
+            if update == SceneUpdate::default() {
+                on_lane(LaneCommand::GetScene)
+            } else {
+                on_lane(LaneCommand::UpdateScene(update))
+            }
+        }
+        "record" => match args.next().as_deref() {
+            None => on_lane(LaneCommand::GetRecording),
+            Some("start") => on_lane(LaneCommand::StartRecording),
+            Some("stop") => on_lane(LaneCommand::StopRecording),
+            Some(other) => return Err(format!("Unknown record action: {}", other)),
+        },
+        "tail" => Action::Tail,
+        _ => return Err(format!("Unknown command: {}", command)),
+    };
+
+    if let Some(argument) = args.next() {
+        return Err(format!("Unexpected argument: {}", argument));
+    }
+
+    Ok(Invocation {
+        server,
+        token,
+        action,
+        timeout: Timer::from_seconds(timeout_secs, TimerMode::Once),
+        sent: false,
+    })
+}
+
+/// Accept either a bare IP, using the default server port, or an IP and port
+fn parse_server(value: Option<String>) -> Result<SocketAddr, String> {
+    let value = value.ok_or("--server expects an address")?;
+    value
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, SERVER_PORT))
+        .or_else(|_| value.parse::<SocketAddr>())
+        .map_err(|_| format!("Invalid server address: {}", value))
+}
+
+/// Read a target layout, a JSON array of `{"x": 0.0, "y": 0.0, "radius": 0.5}` objects
+fn read_layout(path: &str) -> Result<Vec<TargetSpec>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid layout {}: {}", path, e))
+}
+
+fn main() -> AppExit {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return AppExit::Success;
+    }
+
+    let invocation = match parse_args(args.into_iter()) {
+        Ok(invocation) => invocation,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            return AppExit::from_code(2);
+        }
+    };
+
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+            Duration::from_secs_f64(1.0 / 60.0),
+        )))
+        .add_plugins(QuinnetClientPlugin::default())
+        .insert_resource(invocation)
+        .add_systems(Startup, connect_to_server)
+        .add_systems(Update, run_invocation)
+        .run()
+}
+
+fn connect_to_server(
+    mut client: ResMut<QuinnetClient>,
+    invocation: Res<Invocation>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let local_addr = match invocation.server {
+        SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+    };
+
+    if let Err(e) = client.open_connection(ClientConnectionConfiguration {
+        addr_config: ClientAddrConfiguration::from_addrs(invocation.server, local_addr),
+        cert_mode: CertificateVerificationMode::SkipVerification,
+        defaultables: Default::default(),
+    }) {
+        eprintln!("Failed to connect to {}: {}", invocation.server, e);
+        exit.write(AppExit::error());
+    }
+}
+
+/// Send the command once connected, then print the reply or the event stream
+fn run_invocation(
+    mut client: ResMut<QuinnetClient>,
+    mut invocation: ResMut<Invocation>,
+    time: Res<Time>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let Some(connection) = client.get_connection_mut() else {
+        return;
+    };
+
+    match connection.state() {
+        ConnectionState::Connecting => {}
+        ConnectionState::Disconnected => {
+            eprintln!("Disconnected from {}", invocation.server);
+            exit.write(AppExit::error());
+            return;
+        }
+        ConnectionState::Connected if !invocation.sent => {
+            if let Action::Command(command) = &invocation.action
+                && let Err(e) = connection.send_message(NetworkMessage::Operator {
+                    token: invocation.token.clone(),
+                    command: command.clone(),
+                })
+            {
+                eprintln!("Failed to send command: {}", e);
+                exit.write(AppExit::error());
+                return;
+            }
+            invocation.sent = true;
+        }
+        ConnectionState::Connected => {}
+    }
+
+    while let Some(message) = connection.try_receive_message::<NetworkMessage>() {
+        match (message, &invocation.action) {
+            (NetworkMessage::Ping { timestamp }, _) => {
+                // Answer pings so the operator shows up with a round trip time
+                let _ = connection.send_message(NetworkMessage::Pong { timestamp });
+            }
+            (NetworkMessage::OperatorReply(reply), Action::Command(_)) => {
+                exit.write(print_reply(&reply));
+                return;
+            }
+            (message, Action::Tail) => match serde_json::to_string(&message) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Failed to format {}: {}", message.kind(), e),
+            },
+            _ => {}
+        }
+    }
+
+    // Tailing runs until interrupted once connected
+    if matches!(invocation.action, Action::Tail) && invocation.sent {
+        return;
+    }
+
+    invocation.timeout.tick(time.delta());
+    if invocation.timeout.is_finished() {
+        eprintln!("Timed out waiting for {}", invocation.server);
+        exit.write(AppExit::error());
+    }
+}
+
+/// Print a reply as JSON, or the reason the server rejected the command
+fn print_reply(reply: &OperatorReply) -> AppExit {
+    let json = match reply {
+        OperatorReply::Clients(clients) => serde_json::to_string_pretty(clients),
+        OperatorReply::Status(status) => serde_json::to_string_pretty(status),
+        OperatorReply::Scene(scene) => serde_json::to_string_pretty(scene),
+        OperatorReply::Match(status) => serde_json::to_string_pretty(status),
+        OperatorReply::Targets(targets) => serde_json::to_string_pretty(targets),
+        OperatorReply::Target(target) => serde_json::to_string_pretty(target),
+        OperatorReply::Scores(scores) => serde_json::to_string_pretty(scores),
+        OperatorReply::Recording(status) => serde_json::to_string_pretty(status),
+        OperatorReply::Rejected(error) => {
+            eprintln!("Rejected: {}", error);
+            return AppExit::error();
+        }
+    };
+
+    match json {
+        Ok(json) => {
+            println!("{}", json);
+            AppExit::Success
+        }
+        Err(e) => {
+            eprintln!("Failed to format reply: {}", e);
+            AppExit::error()
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::server::{
+    QuinnetServerPlugin, QuinnetServer,
+    certificate::CertificateRetrievalMode,
+    EndpointAddrConfiguration, ServerEndpointConfiguration,
+};
+use common::config::SceneConfiguration;
+use common::network::{LaneId, MatchPhase, DEFAULT_LANE};
+use server::clients::{ClientsPlugin, ConnectedClients};
+use server::game::{GamePlugin, Lane, Lanes, ShotDetected};
+use server::operator::OperatorPlugin;
+use std::io::{BufRead, BufReader};
+use std::net::Ipv4Addr;
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+const TEST_PORT_BASE: u16 = 6500;
+
+/// Helper to create a server app with two lanes accepting operator clients on the given port
+fn create_server(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .insert_resource(Lanes::new(2))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(OperatorPlugin);
+    app.world_mut()
+        .resource_mut::<QuinnetServer>()
+        .start_endpoint(ServerEndpointConfiguration {
+            addr_config: EndpointAddrConfiguration::from_ip(Ipv4Addr::LOCALHOST, port),
+            cert_mode: CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: "localhost".to_string(),
+            },
+            defaultables: Default::default(),
+        })
+        .expect("Server should start");
+    app.update();
+    app
+}
+
+fn lane(app: &App, id: LaneId) -> Lane {
+    app.world().resource::<Lanes>().get(id).unwrap().clone()
+}
+
+fn lane_mut(app: &mut App, id: LaneId) -> Mut<'_, Lane> {
+    app.world_mut().resource_mut::<Lanes>().map_unchanged(|lanes| lanes.get_mut(id).unwrap())
+}
+
+fn ltctl_command(port: u16, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ltctl"));
+    command
+        .args(["--server", &format!("127.0.0.1:{}", port), "--timeout", "10"])
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// Run ltctl to completion while the server keeps updating
+fn ltctl(app: &mut App, port: u16, args: &[&str]) -> Output {
+    let mut child = ltctl_command(port, args).spawn().expect("Should run ltctl");
+    while child.try_wait().unwrap().is_none() {
+        app.update();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    child.wait_with_output().unwrap()
+}
+
+fn stdout_json(output: &Output) -> serde_json::Value {
+    assert!(output.status.success(), "ltctl failed: {}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("Output should be JSON")
+}
+
+#[test]
+fn test_rejects_unknown_command() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ltctl")).arg("launch").output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown command"));
+}
+
+#[test]
+fn test_start_and_stop_round() {
+    let port = TEST_PORT_BASE;
+    let mut app = create_server(port);
+
+    let json = stdout_json(&ltctl(&mut app, port, &["start"]));
+    assert_eq!(json["phase"], "Running");
+    assert_eq!(lane(&app, DEFAULT_LANE).match_state.phase, MatchPhase::Running);
+    assert_eq!(lane(&app, 2).match_state.phase, MatchPhase::Idle, "Other lanes keep their own match");
+
+    let json = stdout_json(&ltctl(&mut app, port, &["stop"]));
+    assert_eq!(json["phase"], "Stopped");
+
+    let output = ltctl(&mut app, port, &["pause"]);
+    assert_eq!(output.status.code(), Some(1), "A stopped match cannot be paused");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not running"));
+}
+
+#[test]
+fn test_load_layout_and_show_status() {
+    let port = TEST_PORT_BASE + 1;
+    let mut app = create_server(port);
+    lane_mut(&mut app, 2).targets.place(3.0, 3.0, 1.0);
+
+    let layout = std::env::temp_dir().join(format!("ltctl-layout-{}.json", std::process::id()));
+    std::fs::write(&layout, r#"[{"x": -1.0, "y": 0.5}, {"x": 1.0, "y": 0.5, "radius": 0.25}]"#).unwrap();
+
+    let json = stdout_json(&ltctl(&mut app, port, &["--lane", "2", "load", layout.to_str().unwrap()]));
+    std::fs::remove_file(&layout).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 2);
+    assert_eq!(json[0]["radius"], 0.5, "Radius should default when omitted");
+
+    let targets = lane(&app, 2).targets.list().to_vec();
+    assert_eq!(targets.len(), 2, "Layout should replace existing targets");
+    assert_eq!(targets[1].radius, 0.25);
+
+    let json = stdout_json(&ltctl(&mut app, port, &["status"]));
+    assert_eq!(json["lanes"][0]["targets"], 0);
+    assert_eq!(json["lanes"][1]["lane"], 2);
+    assert_eq!(json["lanes"][1]["targets"], 2);
+    assert_eq!(json["lanes"][1]["match"]["phase"], "Idle");
+    assert!(json["clients"].as_u64().unwrap() >= 1, "The operator itself is connected");
+}
+
+#[test]
+fn test_toggle_recording() {
+    let port = TEST_PORT_BASE + 4;
+    let mut app = create_server(port);
+
+    let json = stdout_json(&ltctl(&mut app, port, &["--lane", "2", "record", "start"]));
+    assert_eq!(json["recording"], true);
+    assert!(lane(&app, 2).recording);
+    assert!(!lane(&app, DEFAULT_LANE).recording);
+
+    let json = stdout_json(&ltctl(&mut app, port, &["--lane", "2", "record", "stop"]));
+    assert_eq!(json["recording"], false);
+    assert!(!lane(&app, 2).recording);
+}
+
+#[test]
+fn test_change_scene() {
+    let port = TEST_PORT_BASE + 2;
+    let mut app = create_server(port);
+
+    let json = stdout_json(&ltctl(&mut app, port, &["scene", "--width", "6"]));
+    assert_eq!(json["scene_width"], 6.0);
+
+    let scene = lane(&app, DEFAULT_LANE).scene;
+    assert_eq!(scene.scene_width, 6.0);
+    assert_eq!(scene.target_projection_distance, SceneConfiguration::default().target_projection_distance);
+
+    let output = ltctl(&mut app, port, &["scene", "--distance", "-1"]);
+    assert_eq!(output.status.code(), Some(1), "Negative distance should be rejected");
+    assert_eq!(lane(&app, DEFAULT_LANE).scene.scene_width, 6.0);
+    assert_eq!(lane(&app, 2).scene, SceneConfiguration::default(), "Other lanes keep their own scene");
+}
+
+#[test]
+fn test_tail_prints_game_events() {
+    let port = TEST_PORT_BASE + 3;
+    let mut app = create_server(port);
+    lane_mut(&mut app, DEFAULT_LANE).targets.place(0.0, 0.0, 0.5);
+
+    let mut child = ltctl_command(port, &["tail"]).spawn().expect("Should run ltctl");
+    let stdout = child.stdout.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut events = Vec::new();
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let done = line.contains("ShotRegistered");
+            events.push(line);
+            if done {
+                break;
+            }
+        }
+        events
+    });
+
+    let mut connected_at = None;
+    for frame in 0..1000 {
+        if connected_at.is_none() && app.world().resource::<ConnectedClients>().len() == 1 {
+            connected_at = Some(frame);
+            lane_mut(&mut app, DEFAULT_LANE).match_state.start();
+        }
+        if connected_at.is_some_and(|connected| frame == connected + 10) {
+            app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::new(0.1, 0.0)));
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(10));
+        if reader.is_finished() {
+            break;
+        }
+    }
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let events = reader.join().unwrap();
+    assert!(events.iter().any(|event| event.contains("TargetPlaced")), "Should print the current state first");
+    assert!(events.iter().any(|event| event.contains("\"Running\"")), "Should print match phase changes");
+
+    let shot: serde_json::Value = serde_json::from_str(events.last().unwrap()).unwrap();
+    assert_eq!(shot["ShotRegistered"]["target_id"], 1);
+}
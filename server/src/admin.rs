@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use common::network::{LaneCommand, OperatorCommand, OperatorError, OperatorReply, ADMIN_PORT, DEFAULT_LANE};
+use serde::de::DeserializeOwned;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use crate::game::GameSystemSet;
+use crate::http::{self, HttpRequest, HttpResponse};
+use crate::operator::OperatorContext;
+
+/// How long the HTTP thread waits for the app to answer a request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct AdminSystemSet;
+
+/// Map an HTTP request to an operator command, or to the error response to send back
+///
+/// Lane routes live under `/lanes/{id}`, the same routes without the prefix act on the default lane.
+pub fn parse_command(request: &HttpRequest) -> Result<OperatorCommand, HttpResponse> {
+    let segments = request.segments();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["clients"]) => Ok(OperatorCommand::ListClients),
+        ("GET", ["status"]) => Ok(OperatorCommand::GetStatus),
+        (_, ["lanes", lane, route @ ..]) => {
+            let lane = lane.parse().map_err(|_| HttpResponse::bad_request("invalid lane id"))?;
+            parse_lane_command(request, route).map(|command| OperatorCommand::Lane { lane, command })
+        }
+        (_, route) => parse_lane_command(request, route).map(|command| OperatorCommand::Lane { lane: DEFAULT_LANE, command }),
+    }
+}
+
+fn parse_lane_command(request: &HttpRequest, route: &[&str]) -> Result<LaneCommand, HttpResponse> {
+    match (request.method.as_str(), route) {
+        ("GET", ["scene"]) => Ok(LaneCommand::GetScene),
+        ("PUT" | "POST", ["scene"]) => parse_body(request).map(LaneCommand::UpdateScene),
+        ("GET", ["match"]) => Ok(LaneCommand::GetMatch),
+        ("POST", ["match", "start"]) => Ok(LaneCommand::StartMatch),
+        ("POST", ["match", "pause"]) => Ok(LaneCommand::PauseMatch),
+        ("POST", ["match", "stop"]) => Ok(LaneCommand::StopMatch),
+        ("GET", ["targets"]) => Ok(LaneCommand::ListTargets),
+        ("POST", ["targets"]) => parse_body(request).map(LaneCommand::PlaceTarget),
+        ("PUT", ["targets"]) => parse_body(request).map(LaneCommand::LoadTargets),
+        ("DELETE", ["targets", id]) => id
+            .parse()
+            .map(|id| LaneCommand::RemoveTarget { id })
+            .map_err(|_| HttpResponse::bad_request("invalid target id")),
+        ("GET", ["scores"]) => Ok(LaneCommand::GetScores),
+        ("GET", ["recording"]) => Ok(LaneCommand::GetRecording),
+        ("POST", ["recording", "start"]) => Ok(LaneCommand::StartRecording),
+        ("POST", ["recording", "stop"]) => Ok(LaneCommand::StopRecording),
+        _ => Err(HttpResponse::not_found()),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(request: &HttpRequest) -> Result<T, HttpResponse> {
+    serde_json::from_str(&request.body).map_err(|e| HttpResponse::bad_request(&e.to_string()))
+}
+
+/// Map the reply of an operator command to the JSON response of the admin API
+pub fn reply_response(reply: &OperatorReply) -> HttpResponse {
+    match reply {
+        OperatorReply::Clients(clients) => HttpResponse::json(clients),
+        OperatorReply::Status(status) => HttpResponse::json(status),
+        OperatorReply::Scene(scene) => HttpResponse::json(scene),
+        OperatorReply::Match(status) => HttpResponse::json(status),
+        OperatorReply::Targets(targets) => HttpResponse::json(targets),
+        OperatorReply::Target(target) => HttpResponse::json(target),
+        OperatorReply::Scores(scores) => HttpResponse::json(scores),
+        OperatorReply::Recording(status) => HttpResponse::json(status),
+        OperatorReply::Rejected(OperatorError::InvalidArgument(message)) => HttpResponse::bad_request(message),
+        OperatorReply::Rejected(OperatorError::NotFound(message)) => HttpResponse::error(404, message),
+        OperatorReply::Rejected(OperatorError::Conflict(message)) => HttpResponse::error(409, message),
+        OperatorReply::Rejected(OperatorError::Unauthorized(message)) => HttpResponse::error(403, message),
+    }
+}
+
+/// A command forwarded from the HTTP thread, with the channel to answer on
+struct AdminRequest {
+    command: OperatorCommand,
+    reply: Sender<HttpResponse>,
+}
+
+#[derive(Resource)]
+struct AdminApi {
+    port: u16,
+    requests: Option<Mutex<Receiver<AdminRequest>>>,
+}
+
+/// Plugin that serves the local HTTP/JSON admin API on its own port
+pub struct AdminApiPlugin {
+    pub port: u16,
+}
+
+impl Default for AdminApiPlugin {
+    fn default() -> Self {
+        Self { port: ADMIN_PORT }
+    }
+}
+
+impl Plugin for AdminApiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AdminApi { port: self.port, requests: None })
+            .add_systems(Startup, start_admin_api)
+            .add_systems(Update, process_admin_requests.in_set(AdminSystemSet).before(GameSystemSet));
+    }
+}
+
+/// Bind the admin port and serve requests from a background thread
+fn start_admin_api(mut api: ResMut<AdminApi>) {
+    let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, api.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start admin API on port {}: {}", api.port, e);
+            return;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    api.requests = Some(Mutex::new(receiver));
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                // Each connection on its own thread, so a slow client does not hold up the others
+                Ok(mut stream) => {
+                    let sender = sender.clone();
+                    std::thread::spawn(move || handle_connection(&mut stream, &sender));
+                }
+                Err(e) => error!("Admin API connection failed: {}", e),
+            }
+        }
+    });
+
+    info!("Admin API listening on http://{}:{}", Ipv4Addr::LOCALHOST, api.port);
+}
+
+fn handle_connection(stream: &mut TcpStream, sender: &Sender<AdminRequest>) {
+    if let Err(e) = http::set_timeouts(stream, http::IO_TIMEOUT) {
+        error!("Failed to set admin API connection timeouts: {}", e);
+        return;
+    }
+    let response = match http::read_request(stream) {
+        Ok(request) => match parse_command(&request) {
+            Ok(command) => {
+                let (reply, response) = mpsc::channel();
+                if sender.send(AdminRequest { command, reply }).is_err() {
+                    HttpResponse::error(503, "server is shutting down")
+                } else {
+                    response
+                        .recv_timeout(RESPONSE_TIMEOUT)
+                        .unwrap_or_else(|_| HttpResponse::error(503, "server did not respond"))
+                }
+            }
+            Err(response) => response,
+        },
+        Err(e) => HttpResponse::bad_request(&e.to_string()),
+    };
+
+    if let Err(e) = http::write_response(stream, &response) {
+        error!("Failed to write admin API response: {}", e);
+    }
+}
+
+fn process_admin_requests(api: Res<AdminApi>, mut context: OperatorContext) {
+    let Some(requests) = api.requests.as_ref() else {
+        return;
+    };
+    let Ok(requests) = requests.lock() else {
+        return;
+    };
+
+    while let Ok(request) = requests.try_recv() {
+        let response = reply_response(&context.execute(request.command));
+        // The HTTP thread may have given up waiting already
+        let _ = request.reply.send(response);
+    }
+}
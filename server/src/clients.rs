@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy_quinnet::server::messages::ServerMessageReceiveError;
+use bevy_quinnet::server::{ConnectionEvent, ConnectionLostEvent, QuinnetServer};
+use bevy_quinnet::shared::ClientId;
+use common::network::{ClientInfo, LaneId, NetworkMessage};
+use std::collections::BTreeMap;
+
+use crate::metrics::ServerMetrics;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ClientsSystemSet;
+
+/// Clients currently connected to the server, with their last measured round trip time
+#[derive(Resource, Default)]
+pub struct ConnectedClients {
+    clients: BTreeMap<ClientId, ClientInfo>,
+}
+
+impl ConnectedClients {
+    pub fn list(&self) -> Vec<ClientInfo> {
+        self.clients.values().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Clients that should receive a message about the given lane, or all clients for messages without a lane
+    pub fn following(&self, lane: Option<LaneId>) -> Vec<ClientId> {
+        self.clients
+            .values()
+            .filter(|client| lane.is_none() || client.lane.is_none() || client.lane == lane)
+            .map(|client| client.id)
+            .collect()
+    }
+
+    fn insert(&mut self, id: ClientId) {
+        self.clients.entry(id).or_insert(ClientInfo { id, lane: None, rtt_ms: None });
+    }
+
+    fn join(&mut self, id: ClientId, lane: LaneId) {
+        self.insert(id);
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.lane = Some(lane);
+        }
+    }
+
+    /// Forget the lane a client joined, such as one the server does not run
+    pub fn leave(&mut self, id: ClientId) {
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.lane = None;
+        }
+    }
+
+    fn remove(&mut self, id: ClientId) {
+        self.clients.remove(&id);
+    }
+
+    fn record_rtt(&mut self, id: ClientId, rtt_ms: f32) {
+        self.insert(id);
+        if let Some(client) = self.clients.get_mut(&id) {
+            client.rtt_ms = Some(rtt_ms);
+        }
+    }
+}
+
+/// A message received from a client that is not handled by the connection bookkeeping
+#[derive(Message, Debug, Clone)]
+pub struct ClientMessage {
+    pub client_id: ClientId,
+    pub message: NetworkMessage,
+}
+
+/// Plugin that tracks connected clients, pings them and handles their messages
+pub struct ClientsPlugin;
+
+impl Plugin for ClientsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConnectedClients::default())
+            .init_resource::<ServerMetrics>()
+            .add_message::<ClientMessage>()
+            .add_systems(Update, (
+                track_connections,
+                handle_server_events,
+                send_ping_periodically,
+            ).chain().in_set(ClientsSystemSet));
+    }
+}
+
+/// Milliseconds since the unix epoch, used as ping timestamp
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn track_connections(
+    mut connected: MessageReader<ConnectionEvent>,
+    mut lost: MessageReader<ConnectionLostEvent>,
+    mut clients: ResMut<ConnectedClients>,
+) {
+    for event in connected.read() {
+        info!("Client {} connected", event.id);
+        clients.insert(event.id);
+    }
+    for event in lost.read() {
+        info!("Client {} disconnected", event.id);
+        clients.remove(event.id);
+    }
+}
+
+/// Handle incoming client connections and messages
+fn handle_server_events(
+    mut server: ResMut<QuinnetServer>,
+    mut clients: ResMut<ConnectedClients>,
+    mut metrics: ResMut<ServerMetrics>,
+    mut client_messages: MessageWriter<ClientMessage>,
+) {
+    let Some(endpoint) = server.get_endpoint_mut() else {
+        return;
+    };
+
+    // Handle incoming messages from all clients on the default channel
+    for client_id in endpoint.clients() {
+        loop {
+            let message = match endpoint.receive_message::<NetworkMessage>(client_id) {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(ServerMessageReceiveError::Deserialization) => {
+                    // The undecodable payload is consumed, keep reading the next one
+                    error!("Failed to decode message from client {}", client_id);
+                    metrics.decode_errors += 1;
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to receive message from client {}: {}", client_id, e);
+                    break;
+                }
+            };
+            info!("Received message from client {}: {:?}", client_id, message);
+            metrics.record_received(message.kind());
+
+            match message {
+                NetworkMessage::Pong { timestamp } => {
+                    let rtt_ms = now_millis().saturating_sub(timestamp) as f32;
+                    info!("Received pong from client {}, rtt {} ms", client_id, rtt_ms);
+                    clients.record_rtt(client_id, rtt_ms);
+                }
+                NetworkMessage::JoinLane { lane } => {
+                    info!("Client {} joined lane {}", client_id, lane);
+                    clients.join(client_id, lane);
+                    client_messages.write(ClientMessage { client_id, message });
+                }
+                message => {
+                    client_messages.write(ClientMessage { client_id, message });
+                }
+            }
+        }
+    }
+}
+
+/// Send periodic ping messages to all connected clients
+fn send_ping_periodically(
+    mut server: ResMut<QuinnetServer>,
+    mut metrics: ResMut<ServerMetrics>,
+    time: Res<Time>,
+    mut timer: Local<Option<Timer>>,
+) {
+    // Initialize timer on first run
+    if timer.is_none() {
+        *timer = Some(Timer::from_seconds(2.0, TimerMode::Repeating));
+    }
+
+    let timer = timer.as_mut().unwrap();
+    timer.tick(time.delta());
+
+    if !timer.just_finished() {
+        return;
+    }
+
+    let Some(endpoint) = server.get_endpoint_mut() else {
+        return;
+    };
+
+    let timestamp = now_millis();
+    let message = NetworkMessage::Ping { timestamp };
+
+    // Broadcast ping to all connected clients
+    for client_id in endpoint.clients() {
+        match endpoint.send_message(client_id, message.clone()) {
+            Ok(_) => {
+                info!("Sent ping to client {} at timestamp {}", client_id, timestamp);
+                metrics.record_sent(message.kind(), 1);
+            }
+            Err(e) => {
+                error!("Failed to send ping to client {}: {}", client_id, e);
+                metrics.record_failed(message.kind());
+            }
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_quinnet::server::{ConnectionEvent, QuinnetServer};
+use bevy_quinnet::shared::ClientId;
+use common::config::SceneConfiguration;
+use common::network::{LaneId, MatchPhase, MatchStatus, NetworkMessage, ScoreSummary, TargetInfo};
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::clients::{now_millis, ClientMessage, ClientsSystemSet, ConnectedClients};
+use crate::metrics::ServerMetrics;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct GameSystemSet;
+
+/// State of the match run on a lane
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MatchState {
+    pub phase: MatchPhase,
+    /// Time spent in the running phase since the match was started, in seconds.
+    pub elapsed_secs: f32,
+}
+
+impl MatchState {
+    /// Start a new match, or resume a paused one. Returns true when a new match was started.
+    pub fn start(&mut self) -> bool {
+        let new_match = self.phase != MatchPhase::Paused;
+        if new_match {
+            self.elapsed_secs = 0.0;
+        }
+        self.phase = MatchPhase::Running;
+        new_match
+    }
+
+    pub fn pause(&mut self) -> bool {
+        if self.phase != MatchPhase::Running {
+            return false;
+        }
+        self.phase = MatchPhase::Paused;
+        true
+    }
+
+    pub fn stop(&mut self) {
+        self.phase = MatchPhase::Stopped;
+    }
+}
+
+/// Targets placed on the scene of a lane
+#[derive(Default, Debug, Clone)]
+pub struct Targets {
+    targets: Vec<TargetInfo>,
+    next_id: u32,
+}
+
+impl Targets {
+    pub fn list(&self) -> &[TargetInfo] {
+        &self.targets
+    }
+
+    /// Place a target at the given scene-local position and return its id
+    pub fn place(&mut self, x: f32, y: f32, radius: f32) -> TargetInfo {
+        self.next_id += 1;
+        let target = TargetInfo { id: self.next_id, x, y, radius };
+        self.targets.push(target);
+        target
+    }
+
+    pub fn clear(&mut self) {
+        self.targets.clear();
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<TargetInfo> {
+        let index = self.targets.iter().position(|target| target.id == id)?;
+        Some(self.targets.remove(index))
+    }
+
+    /// First target containing the given scene-local position
+    pub fn hit_test(&self, position: Vec2) -> Option<&TargetInfo> {
+        self.targets
+            .iter()
+            .find(|target| position.distance(Vec2::new(target.x, target.y)) <= target.radius)
+    }
+}
+
+/// State of one shooting lane, with its own scene, match, targets and score
+#[derive(Default, Debug, Clone)]
+pub struct Lane {
+    pub scene: SceneConfiguration,
+    pub match_state: MatchState,
+    pub targets: Targets,
+    /// Score of the current match
+    pub scores: ScoreSummary,
+    /// Whether the terminals of the lane should record their thermal frames
+    pub recording: bool,
+}
+
+impl Lane {
+    pub fn match_status(&self) -> MatchStatus {
+        MatchStatus {
+            phase: self.match_state.phase,
+            elapsed_secs: self.match_state.elapsed_secs,
+        }
+    }
+
+    /// Messages that reproduce the state of this lane on a fresh client
+    pub fn messages(&self, lane: LaneId) -> Vec<NetworkMessage> {
+        let mut messages = vec![
+            NetworkMessage::SceneConfiguration { lane, configuration: self.scene.clone() },
+            NetworkMessage::MatchStateChanged {
+                lane,
+                phase: self.match_state.phase,
+                elapsed_secs: self.match_state.elapsed_secs,
+            },
+            NetworkMessage::ScoreUpdated { lane, score: self.scores },
+            NetworkMessage::RecordingChanged { lane, recording: self.recording },
+        ];
+        messages.extend(self.targets.list().iter().map(|target| NetworkMessage::TargetPlaced { lane, target: *target }));
+        messages
+    }
+
+    /// Messages announcing what changed since the previous state of this lane
+    fn changes_since(&self, lane: LaneId, previous: &Lane) -> Vec<NetworkMessage> {
+        let mut messages = Vec::new();
+
+        if self.scene != previous.scene {
+            messages.push(NetworkMessage::SceneConfiguration { lane, configuration: self.scene.clone() });
+        }
+
+        // Only announce phase changes, the elapsed time ticks every frame while running
+        if self.match_state.phase != previous.match_state.phase {
+            messages.push(NetworkMessage::MatchStateChanged {
+                lane,
+                phase: self.match_state.phase,
+                elapsed_secs: self.match_state.elapsed_secs,
+            });
+        }
+
+        for known in previous.targets.list() {
+            if !self.targets.list().iter().any(|target| target.id == known.id) {
+                messages.push(NetworkMessage::TargetRemoved { lane, id: known.id });
+            }
+        }
+        for target in self.targets.list() {
+            if !previous.targets.list().contains(target) {
+                messages.push(NetworkMessage::TargetPlaced { lane, target: *target });
+            }
+        }
+
+        if self.scores != previous.scores {
+            messages.push(NetworkMessage::ScoreUpdated { lane, score: self.scores });
+        }
+
+        if self.recording != previous.recording {
+            messages.push(NetworkMessage::RecordingChanged { lane, recording: self.recording });
+        }
+
+        messages
+    }
+}
+
+/// Shooting lanes run by the server, each with its own terminals and shooter
+#[derive(Resource, Debug, Clone)]
+pub struct Lanes {
+    lanes: BTreeMap<LaneId, Lane>,
+}
+
+impl Default for Lanes {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Lanes {
+    /// Lanes numbered from 1 to `count`
+    pub fn new(count: u32) -> Self {
+        Self { lanes: (1..=count).map(|id| (id, Lane::default())).collect() }
+    }
+
+    pub fn get(&self, lane: LaneId) -> Option<&Lane> {
+        self.lanes.get(&lane)
+    }
+
+    pub fn get_mut(&mut self, lane: LaneId) -> Option<&mut Lane> {
+        self.lanes.get_mut(&lane)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LaneId, &Lane)> {
+        self.lanes.iter().map(|(id, lane)| (*id, lane))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (LaneId, &mut Lane)> {
+        self.lanes.iter_mut().map(|(id, lane)| (*id, lane))
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+}
+
+/// A shot registered on the scene plane of a lane, in scene-local meters
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ShotDetected {
+    pub lane: LaneId,
+    pub position: Vec2,
+    /// Milliseconds since the unix epoch at which the shot was detected, used to measure hit latency
+    pub detected_at_ms: u64,
+}
+
+impl ShotDetected {
+    /// A shot detected now
+    pub fn new(lane: LaneId, position: Vec2) -> Self {
+        Self::reported(lane, position, now_millis())
+    }
+
+    /// A shot detected by a terminal at the given time, measured with its own clock
+    pub fn reported(lane: LaneId, position: Vec2, detected_at_ms: u64) -> Self {
+        Self { lane, position, detected_at_ms }
+    }
+}
+
+/// Game state change broadcast to every client, and to any other listener such as the scoreboard
+#[derive(Message, Debug, Clone)]
+pub struct GameBroadcast(pub NetworkMessage);
+
+/// Read access to the whole game state, used to bring new listeners up to date
+#[derive(SystemParam)]
+pub struct GameStateSnapshot<'w> {
+    lanes: Res<'w, Lanes>,
+}
+
+impl GameStateSnapshot<'_> {
+    /// Messages that reproduce the state of every lane on a fresh client
+    pub fn messages(&self) -> Vec<NetworkMessage> {
+        self.lanes.iter().flat_map(|(id, lane)| lane.messages(id)).collect()
+    }
+}
+
+/// Plugin that owns the lanes with their match state, targets and scoring
+///
+/// Insert [`Lanes`] before adding the plugin to start with more than one lane.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lanes>()
+            .init_resource::<ServerMetrics>()
+            .add_message::<ShotDetected>()
+            .add_message::<GameBroadcast>()
+            .add_systems(Update, (
+                tick_match,
+                receive_reported_shots,
+                score_shots,
+                send_game_state_to_new_clients,
+                send_lane_state_to_joined_clients,
+                collect_game_state_changes,
+                send_game_broadcasts,
+            ).chain().in_set(GameSystemSet).after(ClientsSystemSet));
+    }
+}
+
+fn tick_match(mut lanes: ResMut<Lanes>, time: Res<Time>) {
+    // Avoid flagging the lanes as changed while no match is running
+    if !lanes.iter().any(|(_, lane)| lane.match_state.phase == MatchPhase::Running) {
+        return;
+    }
+    for (_, lane) in lanes.iter_mut() {
+        if lane.match_state.phase == MatchPhase::Running {
+            lane.match_state.elapsed_secs += time.delta_secs();
+        }
+    }
+}
+
+/// Turn shots detected by the terminals into shots to score
+fn receive_reported_shots(mut client_messages: MessageReader<ClientMessage>, mut shots: MessageWriter<ShotDetected>) {
+    for ClientMessage { client_id, message } in client_messages.read() {
+        if let NetworkMessage::ShotReported { lane, x, y, detected_at_ms } = *message {
+            info!("Client {} reported a shot at x:{:.2} y:{:.2} on lane {}", client_id, x, y, lane);
+            shots.write(ShotDetected::reported(lane, Vec2::new(x, y), detected_at_ms));
+        }
+    }
+}
+
+fn score_shots(
+    mut shots: MessageReader<ShotDetected>,
+    mut lanes: ResMut<Lanes>,
+    mut broadcasts: MessageWriter<GameBroadcast>,
+    mut metrics: ResMut<ServerMetrics>,
+) {
+    for shot in shots.read() {
+        let Some(lane) = lanes.get_mut(shot.lane) else {
+            warn!("Ignoring shot on unknown lane {}", shot.lane);
+            continue;
+        };
+        if lane.match_state.phase != MatchPhase::Running {
+            continue;
+        }
+        metrics.shots_processed += 1;
+        // The terminal clock may run ahead of the server one, such a shot counts as scored at once
+        let latency_ms = now_millis().saturating_sub(shot.detected_at_ms);
+        metrics.hit_latency.observe(Duration::from_millis(latency_ms));
+        lane.scores.shots += 1;
+        let target_id = lane.targets.hit_test(shot.position).map(|target| target.id);
+        if let Some(id) = target_id {
+            info!("Shot at {:?} hit target {} on lane {}", shot.position, id, shot.lane);
+            lane.scores.hits += 1;
+        }
+        broadcasts.write(GameBroadcast(NetworkMessage::ShotRegistered {
+            lane: shot.lane,
+            x: shot.position.x,
+            y: shot.position.y,
+            target_id,
+        }));
+    }
+}
+
+fn send_state(
+    server: &mut QuinnetServer,
+    client_id: ClientId,
+    messages: Vec<NetworkMessage>,
+    metrics: &mut ServerMetrics,
+) {
+    let Some(endpoint) = server.get_endpoint_mut() else {
+        return;
+    };
+
+    for message in messages {
+        let kind = message.kind();
+        match endpoint.send_message(client_id, message) {
+            Ok(_) => metrics.record_sent(kind, 1),
+            Err(e) => {
+                error!("Failed to send game state to client {}: {}", client_id, e);
+                metrics.record_failed(kind);
+            }
+        }
+    }
+}
+
+/// Send a snapshot of all lanes to clients that just connected
+fn send_game_state_to_new_clients(
+    mut server: ResMut<QuinnetServer>,
+    mut connections: MessageReader<ConnectionEvent>,
+    snapshot: GameStateSnapshot,
+    mut metrics: ResMut<ServerMetrics>,
+) {
+    for event in connections.read() {
+        send_state(&mut server, event.id, snapshot.messages(), &mut metrics);
+    }
+}
+
+/// Send the state of the lane a terminal joined, refusing lanes beyond the configured count
+fn send_lane_state_to_joined_clients(
+    mut server: ResMut<QuinnetServer>,
+    mut client_messages: MessageReader<ClientMessage>,
+    lanes: Res<Lanes>,
+    mut clients: ResMut<ConnectedClients>,
+    mut metrics: ResMut<ServerMetrics>,
+) {
+    for ClientMessage { client_id, message } in client_messages.read() {
+        let NetworkMessage::JoinLane { lane } = *message else {
+            continue;
+        };
+
+        match lanes.get(lane) {
+            Some(state) => send_state(&mut server, *client_id, state.messages(lane), &mut metrics),
+            None => {
+                // Only the lanes the server was started with exist, clients cannot add any
+                warn!("Client {} joined lane {} which does not exist, {} lanes are configured", client_id, lane, lanes.len());
+                clients.leave(*client_id);
+            }
+        }
+    }
+}
+
+/// Turn changes of the lanes into broadcasts
+fn collect_game_state_changes(
+    mut broadcasts: MessageWriter<GameBroadcast>,
+    lanes: Res<Lanes>,
+    mut known_lanes: Local<BTreeMap<LaneId, Lane>>,
+) {
+    if !lanes.is_changed() {
+        return;
+    }
+
+    let mut messages = Vec::new();
+    for (id, lane) in lanes.iter() {
+        match known_lanes.get(&id) {
+            Some(previous) => messages.extend(lane.changes_since(id, previous)),
+            None => messages.extend(lane.messages(id)),
+        }
+    }
+    *known_lanes = lanes.lanes.clone();
+
+    broadcasts.write_batch(messages.into_iter().map(GameBroadcast));
+}
+
+/// Send game broadcasts to the clients following the lane they are about
+fn send_game_broadcasts(
+    mut server: ResMut<QuinnetServer>,
+    mut broadcasts: MessageReader<GameBroadcast>,
+    clients: Res<ConnectedClients>,
+    mut metrics: ResMut<ServerMetrics>,
+) {
+    let Some(endpoint) = server.get_endpoint_mut() else {
+        broadcasts.clear();
+        return;
+    };
+
+    for GameBroadcast(message) in broadcasts.read() {
+        let recipients = clients.following(message.lane());
+        if recipients.is_empty() {
+            continue;
+        }
+        match endpoint.send_group_message(recipients.iter(), message.clone()) {
+            Ok(_) => metrics.record_sent(message.kind(), recipients.len() as u64),
+            Err(e) => {
+                error!("Failed to broadcast game state: {}", e);
+                metrics.record_failed(message.kind());
+            }
+        }
+    }
+}
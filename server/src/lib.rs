@@ -0,0 +1,7 @@
+pub mod admin;
+pub mod clients;
+pub mod game;
+pub mod http;
+pub mod scoreboard;
+pub mod metrics;
+pub mod operator;
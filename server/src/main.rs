@@ -8,12 +8,98 @@ use bevy_quinnet::{
         EndpointAddrConfiguration, ServerEndpointConfiguration,
     },
 };
-use common::network::{NetworkMessage, SERVER_HOST, SERVER_PORT};
+use common::network::{ADMIN_PORT, METRICS_PORT, SCOREBOARD_PORT, SERVER_HOST, SERVER_PORT};
+use server::admin::AdminApiPlugin;
+use server::clients::ClientsPlugin;
+use server::game::{GamePlugin, Lanes};
+use server::metrics::MetricsPlugin;
+use server::operator::{OperatorPlugin, OperatorToken};
+use server::scoreboard::ScoreboardPlugin;
 use std::time::Duration;
 use std::net::Ipv6Addr;
 
+/// Command line options of the server binary
+struct ServerArgs {
+    /// Number of shooting lanes created at startup
+    lanes: u32,
+    /// Port of the HTTP admin API, disabled when not set
+    admin_port: Option<u16>,
+    /// Port of the spectator web scoreboard, disabled when not set
+    scoreboard_port: Option<u16>,
+    /// Port of the metrics endpoint, disabled when not set
+    metrics_port: Option<u16>,
+    /// Token operator clients must give, operator commands over QUIC are refused when not set
+    operator_token: Option<String>,
+}
+
+impl Default for ServerArgs {
+    fn default() -> Self {
+        Self {
+            lanes: 1,
+            admin_port: None,
+            scoreboard_port: None,
+            metrics_port: None,
+            operator_token: None,
+        }
+    }
+}
+
+const USAGE: &str = "Usage: server [--lanes <count>] [--admin] [--admin-port <port>] [--scoreboard] [--scoreboard-port <port>] [--metrics] [--metrics-port <port>] [--operator-token <token>]";
+
+fn parse_args() -> ServerArgs {
+    let mut parsed = ServerArgs::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lanes" => {
+                parsed.lanes = match args.next().and_then(|count| count.parse().ok()) {
+                    Some(count) if count > 0 => count,
+                    _ => {
+                        eprintln!("--lanes expects a positive number of lanes");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            "--admin" => parsed.admin_port = Some(ADMIN_PORT),
+            "--admin-port" => parsed.admin_port = Some(parse_port(&arg, args.next())),
+            "--scoreboard" => parsed.scoreboard_port = Some(SCOREBOARD_PORT),
+            "--scoreboard-port" => parsed.scoreboard_port = Some(parse_port(&arg, args.next())),
+            "--metrics" => parsed.metrics_port = Some(METRICS_PORT),
+            "--metrics-port" => parsed.metrics_port = Some(parse_port(&arg, args.next())),
+            "--operator-token" => {
+                parsed.operator_token = match args.next() {
+                    Some(token) if !token.is_empty() => Some(token),
+                    _ => {
+                        eprintln!("--operator-token expects a token");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            _ => {
+                eprintln!("Unknown argument: {}", arg);
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    parsed
+}
+
+fn parse_port(flag: &str, value: Option<String>) -> u16 {
+    match value.and_then(|port| port.parse().ok()) {
+        Some(port) => port,
+        None => {
+            eprintln!("{} expects a port number", flag);
+            std::process::exit(2);
+        }
+    }
+}
+
 fn main() {
-    App::new()
+    let args = parse_args();
+
+    let mut app = App::new();
+    app
         // Use MinimalPlugins for headless server (no rendering, no input, no windowing)
         .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
             Duration::from_secs_f64(1.0 / 60.0), // 60 FPS
@@ -22,9 +108,26 @@ fn main() {
         // Add Quinnet server plugin for networking
         .add_plugins(QuinnetServerPlugin::default())
         // Add our server systems
-        .add_systems(Startup, start_server)
-        .add_systems(Update, (handle_server_events, send_ping_periodically))
-        .run();
+        .insert_resource(Lanes::new(args.lanes))
+        .insert_resource(OperatorToken(args.operator_token))
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(OperatorPlugin)
+        .add_systems(Startup, start_server);
+
+    if let Some(port) = args.admin_port {
+        app.add_plugins(AdminApiPlugin { port });
+    }
+
+    if let Some(port) = args.scoreboard_port {
+        app.add_plugins(ScoreboardPlugin { port });
+    }
+
+    if let Some(port) = args.metrics_port {
+        app.add_plugins(MetricsPlugin { port });
+    }
+
+    app.run();
 }
 
 /// Start the Quinnet server on startup
@@ -50,67 +153,3 @@ fn start_server(mut server: ResMut<QuinnetServer>) {
         }
     }
 }
-
-/// Handle incoming client connections and messages
-fn handle_server_events(mut server: ResMut<QuinnetServer>) {
-    let Some(endpoint) = server.get_endpoint_mut() else {
-        return;
-    };
-
-    // Handle incoming messages from all clients on the default channel
-    for client_id in endpoint.clients() {
-        while let Some(message) = endpoint.try_receive_message::<NetworkMessage>(client_id) {
-            info!("Received message from client {}: {:?}", client_id, message);
-            
-            match message {
-                NetworkMessage::Pong { timestamp } => {
-                    info!("Received pong from client {} at timestamp {}", client_id, timestamp);
-                }
-                _ => {}
-            }
-        }
-    }
-}
-
-/// Send periodic ping messages to all connected clients
-fn send_ping_periodically(
-    mut server: ResMut<QuinnetServer>,
-    time: Res<Time>,
-    mut timer: Local<Option<Timer>>,
-) {
-    // Initialize timer on first run
-    if timer.is_none() {
-        *timer = Some(Timer::from_seconds(2.0, TimerMode::Repeating));
-    }
-    
-    let timer = timer.as_mut().unwrap();
-    timer.tick(time.delta());
-
-    if !timer.just_finished() {
-        return;
-    }
-
-    let Some(endpoint) = server.get_endpoint_mut() else {
-        return;
-    };
-
-    // Get current timestamp
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
-
-    let message = NetworkMessage::Ping { timestamp };
-
-    // Broadcast ping to all connected clients
-    for client_id in endpoint.clients() {
-        match endpoint.send_message(client_id, message.clone()) {
-            Ok(_) => {
-                info!("Sent ping to client {} at timestamp {}", client_id, timestamp);
-            }
-            Err(e) => {
-                error!("Failed to send ping to client {}: {}", client_id, e);
-            }
-        }
-    }
-}
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use common::network::METRICS_PORT;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::clients::ConnectedClients;
+use crate::http::{self, HttpResponse};
+
+/// How long the HTTP thread waits for the app to render the metrics
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Running sum and count of observed durations, exposed as a summary
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DurationSummary {
+    pub last_secs: f64,
+    pub sum_secs: f64,
+    pub count: u64,
+}
+
+impl DurationSummary {
+    pub fn observe(&mut self, duration: Duration) {
+        self.last_secs = duration.as_secs_f64();
+        self.sum_secs += self.last_secs;
+        self.count += 1;
+    }
+}
+
+/// Counters and gauges collected by the server systems
+#[derive(Resource, Debug, Default)]
+pub struct ServerMetrics {
+    /// Time spent running the schedules of a single frame
+    pub tick: DurationSummary,
+    /// Time between a shot being detected and being scored
+    pub hit_latency: DurationSummary,
+    pub messages_sent: BTreeMap<&'static str, u64>,
+    pub messages_received: BTreeMap<&'static str, u64>,
+    pub messages_failed: BTreeMap<&'static str, u64>,
+    pub decode_errors: u64,
+    pub shots_processed: u64,
+    tick_started: Option<Instant>,
+}
+
+impl ServerMetrics {
+    pub fn record_sent(&mut self, kind: &'static str, count: u64) {
+        *self.messages_sent.entry(kind).or_default() += count;
+    }
+
+    pub fn record_received(&mut self, kind: &'static str) {
+        *self.messages_received.entry(kind).or_default() += 1;
+    }
+
+    pub fn record_failed(&mut self, kind: &'static str) {
+        *self.messages_failed.entry(kind).or_default() += 1;
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self, clients: &ConnectedClients) -> String {
+        let mut out = String::new();
+
+        write_summary(&mut out, "lasertargets_tick_duration_seconds", "Time spent running one server frame", &self.tick);
+
+        write_header(&mut out, "lasertargets_connected_clients", "Number of connected clients", "gauge");
+        let _ = writeln!(out, "lasertargets_connected_clients {}", clients.len());
+
+        write_header(&mut out, "lasertargets_client_rtt_seconds", "Round trip time of the last ping per client", "gauge");
+        for client in clients.list() {
+            if let Some(rtt_ms) = client.rtt_ms {
+                let _ = writeln!(out, "lasertargets_client_rtt_seconds{{client=\"{}\"}} {}", client.id, rtt_ms / 1000.0);
+            }
+        }
+
+        write_by_type(&mut out, "lasertargets_messages_sent_total", "Messages sent to clients", &self.messages_sent);
+        write_by_type(&mut out, "lasertargets_messages_received_total", "Messages received from clients", &self.messages_received);
+        write_by_type(&mut out, "lasertargets_messages_failed_total", "Messages that could not be sent", &self.messages_failed);
+
+        write_header(&mut out, "lasertargets_decode_errors_total", "Messages from clients that could not be decoded", "counter");
+        let _ = writeln!(out, "lasertargets_decode_errors_total {}", self.decode_errors);
+
+        write_header(&mut out, "lasertargets_shots_processed_total", "Shots scored by the server", "counter");
+        let _ = writeln!(out, "lasertargets_shots_processed_total {}", self.shots_processed);
+
+        write_summary(&mut out, "lasertargets_hit_latency_seconds", "Time from shot detection to scoring", &self.hit_latency);
+
+        out
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn write_summary(out: &mut String, name: &str, help: &str, summary: &DurationSummary) {
+    write_header(out, name, help, "summary");
+    let _ = writeln!(out, "{}_sum {}", name, summary.sum_secs);
+    let _ = writeln!(out, "{}_count {}", name, summary.count);
+    write_header(out, &format!("{}_last", name), help, "gauge");
+    let _ = writeln!(out, "{}_last {}", name, summary.last_secs);
+}
+
+fn write_by_type(out: &mut String, name: &str, help: &str, counts: &BTreeMap<&'static str, u64>) {
+    write_header(out, name, help, "counter");
+    for (kind, count) in counts {
+        let _ = writeln!(out, "{}{{type=\"{}\"}} {}", name, kind, count);
+    }
+}
+
+#[derive(Resource)]
+struct MetricsEndpoint {
+    port: u16,
+    requests: Option<Mutex<Receiver<Sender<String>>>>,
+}
+
+/// Plugin that measures the frame loop and serves the metrics over HTTP
+pub struct MetricsPlugin {
+    pub port: u16,
+}
+
+impl Default for MetricsPlugin {
+    fn default() -> Self {
+        Self { port: METRICS_PORT }
+    }
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServerMetrics>()
+            .insert_resource(MetricsEndpoint { port: self.port, requests: None })
+            .add_systems(Startup, start_metrics_endpoint)
+            .add_systems(First, begin_tick)
+            .add_systems(Last, (end_tick, serve_metrics).chain());
+    }
+}
+
+fn begin_tick(mut metrics: ResMut<ServerMetrics>) {
+    metrics.tick_started = Some(Instant::now());
+}
+
+fn end_tick(mut metrics: ResMut<ServerMetrics>) {
+    if let Some(started) = metrics.tick_started.take() {
+        metrics.tick.observe(started.elapsed());
+    }
+}
+
+/// Bind the metrics port and answer scrapes from a background thread
+fn start_metrics_endpoint(mut endpoint: ResMut<MetricsEndpoint>) {
+    let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, endpoint.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start metrics endpoint on port {}: {}", endpoint.port, e);
+            return;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    endpoint.requests = Some(Mutex::new(receiver));
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                // Each connection on its own thread, so a stalled client does not hold up the scrapes
+                Ok(mut stream) => {
+                    let sender = sender.clone();
+                    std::thread::spawn(move || handle_connection(&mut stream, &sender));
+                }
+                Err(e) => error!("Metrics connection failed: {}", e),
+            }
+        }
+    });
+
+    info!("Metrics served on http://{}:{}/metrics", Ipv4Addr::UNSPECIFIED, endpoint.port);
+}
+
+fn handle_connection(stream: &mut TcpStream, sender: &Sender<Sender<String>>) {
+    if let Err(e) = http::set_timeouts(stream, http::IO_TIMEOUT) {
+        error!("Failed to set metrics connection timeouts: {}", e);
+        return;
+    }
+    let response = match http::read_request(stream) {
+        Ok(request) => match (request.method.as_str(), request.segments().as_slice()) {
+            ("GET", ["metrics"]) => {
+                let (reply, rendered) = mpsc::channel();
+                match sender.send(reply).ok().and_then(|_| rendered.recv_timeout(RESPONSE_TIMEOUT).ok()) {
+                    Some(body) => HttpResponse::new(200, CONTENT_TYPE, body),
+                    None => HttpResponse::error(503, "server did not respond"),
+                }
+            }
+            _ => HttpResponse::not_found(),
+        },
+        Err(e) => HttpResponse::bad_request(&e.to_string()),
+    };
+
+    if let Err(e) = http::write_response(stream, &response) {
+        error!("Failed to write metrics response: {}", e);
+    }
+}
+
+fn serve_metrics(endpoint: Res<MetricsEndpoint>, metrics: Res<ServerMetrics>, clients: Res<ConnectedClients>) {
+    let Some(requests) = endpoint.requests.as_ref() else {
+        return;
+    };
+    let Ok(requests) = requests.lock() else {
+        return;
+    };
+
+    while let Ok(reply) = requests.try_recv() {
+        let _ = reply.send(metrics.render(&clients));
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_quinnet::server::QuinnetServer;
+use common::network::{
+    LaneCommand, LaneId, LaneStatus, NetworkMessage, OperatorCommand, OperatorError, OperatorReply, RecordingStatus,
+    SceneUpdate, ServerStatus, TargetSpec,
+};
+
+use crate::clients::{ClientMessage, ClientsSystemSet, ConnectedClients};
+use crate::game::{GameSystemSet, Lane, Lanes};
+use crate::metrics::ServerMetrics;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct OperatorSystemSet;
+
+/// Token QUIC clients must give with operator commands, commands are refused while none is set
+#[derive(Resource, Debug, Clone, Default)]
+pub struct OperatorToken(pub Option<String>);
+
+impl OperatorToken {
+    pub fn check(&self, token: &str) -> Result<(), OperatorError> {
+        match &self.0 {
+            Some(expected) if expected == token => Ok(()),
+            Some(_) => Err(OperatorError::Unauthorized("invalid operator token".to_string())),
+            None => Err(OperatorError::Unauthorized(
+                "operator commands are disabled, start the server with --operator-token".to_string(),
+            )),
+        }
+    }
+}
+
+/// Game state operator commands act on, shared by the QUIC operator clients and the admin API
+#[derive(SystemParam)]
+pub struct OperatorContext<'w> {
+    clients: Res<'w, ConnectedClients>,
+    lanes: ResMut<'w, Lanes>,
+}
+
+impl OperatorContext<'_> {
+    /// Apply a command to the game state and build the reply
+    pub fn execute(&mut self, command: OperatorCommand) -> OperatorReply {
+        info!("Operator command: {:?}", command);
+
+        let result = match command {
+            OperatorCommand::ListClients => Ok(OperatorReply::Clients(self.clients.list())),
+            OperatorCommand::GetStatus => Ok(OperatorReply::Status(self.status())),
+            OperatorCommand::Lane { lane, command } => match self.lanes.get_mut(lane) {
+                Some(state) => execute_lane_command(state, command),
+                None => Err(OperatorError::NotFound(format!("lane {} does not exist", lane))),
+            },
+        };
+
+        result.unwrap_or_else(OperatorReply::Rejected)
+    }
+
+    fn status(&self) -> ServerStatus {
+        ServerStatus {
+            clients: self.clients.len() as u32,
+            lanes: self.lanes.iter().map(|(id, lane)| lane_status(id, lane)).collect(),
+        }
+    }
+}
+
+fn lane_status(id: LaneId, lane: &Lane) -> LaneStatus {
+    LaneStatus {
+        lane: id,
+        match_status: lane.match_status(),
+        scene: lane.scene.clone(),
+        targets: lane.targets.list().len() as u32,
+        scores: lane.scores,
+        recording: lane.recording,
+    }
+}
+
+fn execute_lane_command(lane: &mut Lane, command: LaneCommand) -> Result<OperatorReply, OperatorError> {
+    match command {
+        LaneCommand::GetScene => Ok(OperatorReply::Scene(lane.scene.clone())),
+        LaneCommand::UpdateScene(update) => update_scene(lane, update),
+        LaneCommand::GetMatch => Ok(OperatorReply::Match(lane.match_status())),
+        LaneCommand::StartMatch => {
+            if lane.match_state.start() {
+                lane.scores = Default::default();
+            }
+            Ok(OperatorReply::Match(lane.match_status()))
+        }
+        LaneCommand::PauseMatch => {
+            if lane.match_state.pause() {
+                Ok(OperatorReply::Match(lane.match_status()))
+            } else {
+                Err(OperatorError::Conflict("match is not running".to_string()))
+            }
+        }
+        LaneCommand::StopMatch => {
+            lane.match_state.stop();
+            Ok(OperatorReply::Match(lane.match_status()))
+        }
+        LaneCommand::ListTargets => Ok(OperatorReply::Targets(lane.targets.list().to_vec())),
+        LaneCommand::PlaceTarget(spec) => {
+            validate_target(&spec).map(|_| OperatorReply::Target(lane.targets.place(spec.x, spec.y, spec.radius)))
+        }
+        LaneCommand::RemoveTarget { id } => lane
+            .targets
+            .remove(id)
+            .map(OperatorReply::Target)
+            .ok_or_else(|| OperatorError::NotFound(format!("target {} does not exist", id))),
+        LaneCommand::LoadTargets(layout) => load_targets(lane, &layout),
+        LaneCommand::GetScores => Ok(OperatorReply::Scores(lane.scores)),
+        LaneCommand::GetRecording => Ok(recording_reply(lane)),
+        LaneCommand::StartRecording => {
+            lane.recording = true;
+            Ok(recording_reply(lane))
+        }
+        LaneCommand::StopRecording => {
+            lane.recording = false;
+            Ok(recording_reply(lane))
+        }
+    }
+}
+
+fn recording_reply(lane: &Lane) -> OperatorReply {
+    OperatorReply::Recording(RecordingStatus { recording: lane.recording })
+}
+
+fn update_scene(lane: &mut Lane, update: SceneUpdate) -> Result<OperatorReply, OperatorError> {
+    let mut configuration = lane.scene.clone();
+    if let Some(distance) = update.target_projection_distance {
+        configuration.target_projection_distance = distance;
+    }
+    if let Some(width) = update.scene_width {
+        configuration.scene_width = width;
+    }
+
+    if configuration.target_projection_distance <= 0.0 || configuration.scene_width <= 0.0 {
+        return Err(OperatorError::InvalidArgument(
+            "scene distance and width must be positive".to_string(),
+        ));
+    }
+
+    lane.scene = configuration;
+    Ok(OperatorReply::Scene(lane.scene.clone()))
+}
+
+/// Replace all targets, leaving the current ones untouched if any target of the layout is invalid
+fn load_targets(lane: &mut Lane, layout: &[TargetSpec]) -> Result<OperatorReply, OperatorError> {
+    layout.iter().try_for_each(validate_target)?;
+
+    lane.targets.clear();
+    for spec in layout {
+        lane.targets.place(spec.x, spec.y, spec.radius);
+    }
+    Ok(OperatorReply::Targets(lane.targets.list().to_vec()))
+}
+
+fn validate_target(spec: &TargetSpec) -> Result<(), OperatorError> {
+    if spec.radius > 0.0 && spec.x.is_finite() && spec.y.is_finite() {
+        Ok(())
+    } else {
+        Err(OperatorError::InvalidArgument(
+            "target position must be finite and radius positive".to_string(),
+        ))
+    }
+}
+
+/// Plugin that lets operator clients such as `ltctl` control the game over the QUIC connection
+pub struct OperatorPlugin;
+
+impl Plugin for OperatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OperatorToken>().add_systems(
+            Update,
+            process_operator_commands
+                .in_set(OperatorSystemSet)
+                .after(ClientsSystemSet)
+                .before(GameSystemSet),
+        );
+    }
+}
+
+/// Run operator commands received from clients with the operator token and answer the client that sent each one
+fn process_operator_commands(
+    mut server: ResMut<QuinnetServer>,
+    mut client_messages: MessageReader<ClientMessage>,
+    mut context: OperatorContext,
+    operator_token: Res<OperatorToken>,
+    mut metrics: ResMut<ServerMetrics>,
+) {
+    for ClientMessage { client_id, message } in client_messages.read() {
+        let NetworkMessage::Operator { token, command } = message else {
+            continue;
+        };
+
+        let reply = match operator_token.check(token) {
+            Ok(()) => context.execute(command.clone()),
+            Err(e) => {
+                warn!("Refused operator command from client {}: {}", client_id, e);
+                OperatorReply::Rejected(e)
+            }
+        };
+        let reply = NetworkMessage::OperatorReply(reply);
+        let kind = reply.kind();
+        let Some(endpoint) = server.get_endpoint_mut() else {
+            continue;
+        };
+        match endpoint.send_message(*client_id, reply) {
+            Ok(_) => metrics.record_sent(kind, 1),
+            Err(e) => {
+                error!("Failed to answer operator client {}: {}", client_id, e);
+                metrics.record_failed(kind);
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::prelude::*;
+use common::network::{NetworkMessage, SCOREBOARD_PORT};
+use std::io::Write;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::game::{GameBroadcast, GameStateSnapshot, GameSystemSet};
+use crate::http::{self, HttpResponse};
+
+/// Static page shown on the spectator screen
+const SCOREBOARD_PAGE: &str = include_str!("scoreboard.html");
+/// Browsers that stall longer than this are dropped instead of holding up the other viewers
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+/// Comment frames keep idle connections alive and reveal closed ones
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ScoreboardSystemSet;
+
+/// Work for the thread writing to the event streams, so that slow browsers never stall the frame loop
+enum StreamEvent {
+    /// A browser that just connected, with the frames bringing it up to date
+    Subscribe(TcpStream, String),
+    /// Frames for every connected browser
+    Frames(String),
+}
+
+#[derive(Resource)]
+struct Scoreboard {
+    port: u16,
+    /// Event streams that just connected and still need the current game state
+    pending: Arc<Mutex<Vec<TcpStream>>>,
+    /// Channel to the thread writing to the event streams, once the scoreboard is served
+    events: Option<Sender<StreamEvent>>,
+}
+
+/// Plugin that serves the spectator web scoreboard and its live event stream
+pub struct ScoreboardPlugin {
+    pub port: u16,
+}
+
+impl Default for ScoreboardPlugin {
+    fn default() -> Self {
+        Self { port: SCOREBOARD_PORT }
+    }
+}
+
+impl Plugin for ScoreboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Scoreboard {
+                port: self.port,
+                pending: Arc::new(Mutex::new(Vec::new())),
+                events: None,
+            })
+            .add_systems(Startup, start_scoreboard)
+            .add_systems(Update, stream_scoreboard_events.in_set(ScoreboardSystemSet).after(GameSystemSet));
+    }
+}
+
+/// Format a broadcast as a Server-Sent Events frame
+pub fn sse_frame(message: &NetworkMessage) -> Option<String> {
+    match message {
+        NetworkMessage::Ping { .. } | NetworkMessage::Pong { .. } => None,
+        _ => serde_json::to_string(message).ok().map(|json| format!("data: {}\n\n", json)),
+    }
+}
+
+/// Bind the scoreboard port and serve the page and event streams from background threads
+fn start_scoreboard(mut scoreboard: ResMut<Scoreboard>) {
+    let listener = match TcpListener::bind((Ipv4Addr::UNSPECIFIED, scoreboard.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start scoreboard on port {}: {}", scoreboard.port, e);
+            return;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    scoreboard.events = Some(sender);
+    std::thread::spawn(move || write_event_streams(receiver));
+
+    let pending = scoreboard.pending.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                // Each connection on its own thread, so a browser preconnecting without a request does not hold up the others
+                Ok(mut stream) => {
+                    let pending = pending.clone();
+                    std::thread::spawn(move || handle_connection(&mut stream, &pending));
+                }
+                Err(e) => error!("Scoreboard connection failed: {}", e),
+            }
+        }
+    });
+
+    info!("Scoreboard served on http://{}:{}", Ipv4Addr::UNSPECIFIED, scoreboard.port);
+}
+
+fn handle_connection(stream: &mut TcpStream, pending: &Mutex<Vec<TcpStream>>) {
+    if let Err(e) = http::set_timeouts(stream, http::IO_TIMEOUT) {
+        error!("Failed to set scoreboard connection timeouts: {}", e);
+        return;
+    }
+    let response = match http::read_request(stream) {
+        Ok(request) => match (request.method.as_str(), request.segments().as_slice()) {
+            ("GET", []) | ("GET", ["index.html"]) => {
+                HttpResponse::new(200, "text/html; charset=utf-8", SCOREBOARD_PAGE)
+            }
+            ("GET", ["events"]) => {
+                let subscribed = http::write_event_stream_headers(stream)
+                    .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)))
+                    .and_then(|_| stream.try_clone());
+                match subscribed {
+                    Ok(subscriber) => {
+                        if let Ok(mut pending) = pending.lock() {
+                            pending.push(subscriber);
+                        }
+                    }
+                    Err(e) => error!("Failed to open scoreboard event stream: {}", e),
+                }
+                return;
+            }
+            _ => HttpResponse::not_found(),
+        },
+        Err(e) => HttpResponse::bad_request(&e.to_string()),
+    };
+
+    if let Err(e) = http::write_response(stream, &response) {
+        error!("Failed to write scoreboard response: {}", e);
+    }
+}
+
+/// Write the frames to every connected browser, dropping the ones that stall or closed the stream
+fn write_event_streams(events: Receiver<StreamEvent>) {
+    let mut subscribers: Vec<TcpStream> = Vec::new();
+    loop {
+        let frames = match events.recv_timeout(KEEP_ALIVE) {
+            Ok(StreamEvent::Subscribe(mut subscriber, state)) => {
+                if subscriber.write_all(state.as_bytes()).is_ok() {
+                    subscribers.push(subscriber);
+                }
+                info!("Scoreboard has {} viewers", subscribers.len());
+                continue;
+            }
+            Ok(StreamEvent::Frames(frames)) => frames,
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        subscribers.retain_mut(|subscriber| subscriber.write_all(frames.as_bytes()).is_ok());
+    }
+}
+
+/// Forward game broadcasts to the thread writing to the browsers
+fn stream_scoreboard_events(
+    scoreboard: Res<Scoreboard>,
+    mut broadcasts: MessageReader<GameBroadcast>,
+    snapshot: GameStateSnapshot,
+) {
+    let Some(events) = scoreboard.events.as_ref() else {
+        broadcasts.clear();
+        return;
+    };
+
+    let new_subscribers: Vec<TcpStream> = match scoreboard.pending.lock() {
+        Ok(mut pending) => pending.drain(..).collect(),
+        Err(_) => Vec::new(),
+    };
+    if !new_subscribers.is_empty() {
+        let state: String = snapshot.messages().iter().filter_map(sse_frame).collect();
+        for subscriber in new_subscribers {
+            let _ = events.send(StreamEvent::Subscribe(subscriber, state.clone()));
+        }
+    }
+
+    let frames: String = broadcasts.read().filter_map(|GameBroadcast(message)| sse_frame(message)).collect();
+    if !frames.is_empty() {
+        let _ = events.send(StreamEvent::Frames(frames));
+    }
+}
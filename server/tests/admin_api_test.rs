@@ -0,0 +1,257 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::server::QuinnetServerPlugin;
+use server::admin::{reply_response, AdminApiPlugin};
+use server::clients::ClientsPlugin;
+use common::network::{OperatorError, OperatorReply, DEFAULT_LANE};
+use server::game::{GamePlugin, Lanes, ShotDetected};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::Duration;
+
+const TEST_PORT_BASE: u16 = 6200;
+
+/// Helper to create a server app serving the admin API on the given port
+fn create_admin_server(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(AdminApiPlugin { port });
+    // Run startup systems so the admin port is bound
+    app.update();
+    app
+}
+
+/// Minimal HTTP client returning the status code and the body
+fn http_request(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Should connect to admin API");
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Should read response");
+
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .expect("Response should have a status code");
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+    (status, body)
+}
+
+/// Issue a request from a helper thread while the app keeps updating
+fn request(app: &mut App, port: u16, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+    let (method, path, body) = (method.to_string(), path.to_string(), body.to_string());
+    let handle = std::thread::spawn(move || http_request(port, &method, &path, &body));
+
+    while !handle.is_finished() {
+        app.update();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let (status, body) = handle.join().unwrap();
+    let json = serde_json::from_str(&body).expect("Response should be JSON");
+    (status, json)
+}
+
+#[test]
+fn test_list_clients_empty() {
+    let port = TEST_PORT_BASE;
+    let mut app = create_admin_server(port);
+
+    let (status, json) = request(&mut app, port, "GET", "/clients", "");
+    assert_eq!(status, 200);
+    assert_eq!(json, serde_json::json!([]));
+}
+
+#[test]
+fn test_get_and_set_scene() {
+    let port = TEST_PORT_BASE + 1;
+    let mut app = create_admin_server(port);
+
+    let (status, json) = request(&mut app, port, "GET", "/scene", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["target_projection_distance"], 25.0);
+    assert_eq!(json["scene_width"], 10.0);
+
+    let (status, _) = request(
+        &mut app,
+        port,
+        "PUT",
+        "/scene",
+        r#"{"target_projection_distance": 12.0, "scene_width": 6.0}"#,
+    );
+    assert_eq!(status, 200);
+
+    let (_, json) = request(&mut app, port, "GET", "/scene", "");
+    assert_eq!(json["target_projection_distance"], 12.0);
+    assert_eq!(json["scene_width"], 6.0);
+
+    let (status, _) = request(
+        &mut app,
+        port,
+        "PUT",
+        "/scene",
+        r#"{"target_projection_distance": -1.0, "scene_width": 6.0}"#,
+    );
+    assert_eq!(status, 400, "Negative distance should be rejected");
+}
+
+#[test]
+fn test_match_lifecycle() {
+    let port = TEST_PORT_BASE + 2;
+    let mut app = create_admin_server(port);
+
+    let (_, json) = request(&mut app, port, "GET", "/match", "");
+    assert_eq!(json["phase"], "Idle");
+
+    let (status, _) = request(&mut app, port, "POST", "/match/pause", "");
+    assert_eq!(status, 409, "Idle match cannot be paused");
+
+    let (_, json) = request(&mut app, port, "POST", "/match/start", "");
+    assert_eq!(json["phase"], "Running");
+
+    let (_, json) = request(&mut app, port, "POST", "/match/pause", "");
+    assert_eq!(json["phase"], "Paused");
+
+    let (_, json) = request(&mut app, port, "POST", "/match/start", "");
+    assert_eq!(json["phase"], "Running");
+
+    let (_, json) = request(&mut app, port, "POST", "/match/stop", "");
+    assert_eq!(json["phase"], "Stopped");
+}
+
+#[test]
+fn test_place_and_remove_targets() {
+    let port = TEST_PORT_BASE + 3;
+    let mut app = create_admin_server(port);
+
+    let (status, placed) = request(&mut app, port, "POST", "/targets", r#"{"x": 1.0, "y": -0.5, "radius": 0.3}"#);
+    assert_eq!(status, 200);
+    let id = placed["id"].as_u64().expect("Target should have an id");
+
+    let (_, targets) = request(&mut app, port, "GET", "/targets", "");
+    assert_eq!(targets.as_array().unwrap().len(), 1);
+    assert_eq!(targets[0]["x"], 1.0);
+    assert_eq!(targets[0]["radius"], 0.3);
+
+    let (status, _) = request(&mut app, port, "DELETE", &format!("/targets/{}", id), "");
+    assert_eq!(status, 200);
+
+    let (_, targets) = request(&mut app, port, "GET", "/targets", "");
+    assert!(targets.as_array().unwrap().is_empty());
+
+    let (status, _) = request(&mut app, port, "DELETE", &format!("/targets/{}", id), "");
+    assert_eq!(status, 404, "Removing an unknown target should fail");
+}
+
+#[test]
+fn test_fetch_scores() {
+    let port = TEST_PORT_BASE + 4;
+    let mut app = create_admin_server(port);
+
+    request(&mut app, port, "POST", "/targets", r#"{"x": 0.0, "y": 0.0, "radius": 0.5}"#);
+    request(&mut app, port, "POST", "/match/start", "");
+
+    app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::new(0.1, 0.1)));
+    app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::new(3.0, 3.0)));
+    app.update();
+
+    let (status, scores) = request(&mut app, port, "GET", "/scores", "");
+    assert_eq!(status, 200);
+    assert_eq!(scores["shots"], 2);
+    assert_eq!(scores["hits"], 1);
+}
+
+#[test]
+fn test_unknown_route() {
+    let port = TEST_PORT_BASE + 5;
+    let mut app = create_admin_server(port);
+
+    let (status, json) = request(&mut app, port, "GET", "/nothing", "");
+    assert_eq!(status, 404);
+    assert!(json["error"].is_string());
+}
+
+#[test]
+fn test_lane_routes() {
+    let port = TEST_PORT_BASE + 6;
+    let mut app = create_admin_server(port);
+    app.world_mut().insert_resource(Lanes::new(2));
+
+    let (status, json) = request(&mut app, port, "POST", "/lanes/2/match/start", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["phase"], "Running");
+
+    let (_, json) = request(&mut app, port, "GET", "/match", "");
+    assert_eq!(json["phase"], "Idle", "Routes without a lane act on the default lane only");
+
+    let (_, json) = request(&mut app, port, "GET", "/status", "");
+    assert_eq!(json["lanes"][1]["match"]["phase"], "Running");
+
+    let (status, _) = request(&mut app, port, "GET", "/lanes/3/scores", "");
+    assert_eq!(status, 404, "Unknown lanes should not be found");
+}
+
+#[test]
+fn test_recording_routes() {
+    let port = TEST_PORT_BASE + 7;
+    let mut app = create_admin_server(port);
+
+    let (status, json) = request(&mut app, port, "POST", "/lanes/1/recording/start", "");
+    assert_eq!(status, 200);
+    assert_eq!(json["recording"], true);
+    assert!(app.world().resource::<Lanes>().get(DEFAULT_LANE).unwrap().recording);
+
+    let (_, json) = request(&mut app, port, "GET", "/status", "");
+    assert_eq!(json["lanes"][0]["recording"], true);
+
+    let (_, json) = request(&mut app, port, "POST", "/recording/stop", "");
+    assert_eq!(json["recording"], false);
+    let (_, json) = request(&mut app, port, "GET", "/recording", "");
+    assert_eq!(json["recording"], false);
+}
+
+#[test]
+fn test_idle_connection_does_not_block_requests() {
+    let port = TEST_PORT_BASE + 8;
+    let mut app = create_admin_server(port);
+
+    // A client that connects and never sends its request, like `nc` left open
+    let _idle = TcpStream::connect(("127.0.0.1", port)).expect("Should connect to admin API");
+
+    let (status, json) = request(&mut app, port, "GET", "/clients", "");
+    assert_eq!(status, 200);
+    assert_eq!(json, serde_json::json!([]));
+}
+
+#[test]
+fn test_unauthorized_reply_is_forbidden() {
+    let port = TEST_PORT_BASE + 9;
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port)).expect("Should bind");
+    let mut client = TcpStream::connect(("127.0.0.1", port)).expect("Should connect");
+    let (mut stream, _) = listener.accept().unwrap();
+
+    let reply = OperatorReply::Rejected(OperatorError::Unauthorized("invalid operator token".to_string()));
+    server::http::write_response(&mut stream, &reply_response(&reply)).unwrap();
+    drop(stream);
+
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"), "Unexpected response: {}", response);
+    assert!(response.ends_with(r#"{"error":"invalid operator token"}"#), "Unexpected response: {}", response);
+}
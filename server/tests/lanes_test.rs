@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::{
+    server::{
+        QuinnetServerPlugin, QuinnetServer,
+        certificate::CertificateRetrievalMode,
+        EndpointAddrConfiguration, ServerEndpointConfiguration,
+    },
+    client::{
+        QuinnetClientPlugin, QuinnetClient,
+        certificate::CertificateVerificationMode,
+        connection::ClientAddrConfiguration,
+        ClientConnectionConfiguration,
+    },
+};
+use common::network::{LaneId, NetworkMessage};
+use server::clients::{ClientsPlugin, ConnectedClients};
+use server::game::{GamePlugin, Lanes, ShotDetected};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+const TEST_PORT_BASE: u16 = 6600;
+
+/// Helper to create a server app running the given number of lanes
+fn create_lane_server(port: u16, lanes: u32) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .insert_resource(Lanes::new(lanes))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin);
+    app.world_mut()
+        .resource_mut::<QuinnetServer>()
+        .start_endpoint(ServerEndpointConfiguration {
+            addr_config: EndpointAddrConfiguration::from_ip(Ipv4Addr::LOCALHOST, port),
+            cert_mode: CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: "localhost".to_string(),
+            },
+            defaultables: Default::default(),
+        })
+        .expect("Server should start");
+    app.update();
+    app
+}
+
+/// Helper to connect a terminal-like client to the server
+fn create_terminal(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetClientPlugin::default());
+    app.world_mut()
+        .resource_mut::<QuinnetClient>()
+        .open_connection(ClientConnectionConfiguration {
+            addr_config: ClientAddrConfiguration::from_strings(&format!("127.0.0.1:{}", port), "0.0.0.0:0").unwrap(),
+            cert_mode: CertificateVerificationMode::SkipVerification,
+            defaultables: Default::default(),
+        })
+        .expect("Client should connect");
+    app
+}
+
+fn join_lane(terminal: &mut App, lane: LaneId) {
+    terminal
+        .world_mut()
+        .resource_mut::<QuinnetClient>()
+        .connection_mut()
+        .send_message(NetworkMessage::JoinLane { lane })
+        .expect("Should send join");
+}
+
+/// Update all apps for a number of frames, collecting what each terminal receives
+fn run(server: &mut App, terminals: &mut [App], received: &mut [Vec<NetworkMessage>], frames: usize) {
+    for _ in 0..frames {
+        server.update();
+        for (terminal, messages) in terminals.iter_mut().zip(received.iter_mut()) {
+            terminal.update();
+            let mut client = terminal.world_mut().resource_mut::<QuinnetClient>();
+            if let Some(connection) = client.get_connection_mut() {
+                while let Some(message) = connection.try_receive_message::<NetworkMessage>() {
+                    messages.push(message);
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn wait_for_clients(server: &mut App, terminals: &mut [App], received: &mut [Vec<NetworkMessage>]) {
+    for _ in 0..100 {
+        run(server, terminals, received, 1);
+        if server.world().resource::<ConnectedClients>().len() == terminals.len() {
+            return;
+        }
+    }
+    panic!("Terminals did not connect");
+}
+
+#[test]
+fn test_shots_only_reach_their_lane() {
+    let port = TEST_PORT_BASE;
+    let mut server = create_lane_server(port, 2);
+    let mut terminals = [create_terminal(port), create_terminal(port)];
+    let mut received = [Vec::new(), Vec::new()];
+
+    wait_for_clients(&mut server, &mut terminals, &mut received);
+    join_lane(&mut terminals[0], 1);
+    join_lane(&mut terminals[1], 2);
+    run(&mut server, &mut terminals, &mut received, 20);
+
+    let lanes: Vec<_> = server.world().resource::<ConnectedClients>().list().iter().map(|client| client.lane).collect();
+    assert!(lanes.contains(&Some(1)) && lanes.contains(&Some(2)), "Terminals should be bound to their lane");
+
+    {
+        let mut lanes = server.world_mut().resource_mut::<Lanes>();
+        let lane = lanes.get_mut(2).unwrap();
+        lane.targets.place(0.0, 0.0, 0.5);
+        lane.match_state.start();
+    }
+    run(&mut server, &mut terminals, &mut received, 5);
+    server.world_mut().write_message(ShotDetected::new(2, Vec2::new(0.1, 0.0)));
+    server.world_mut().write_message(ShotDetected::new(1, Vec2::new(0.1, 0.0)));
+    run(&mut server, &mut terminals, &mut received, 20);
+
+    let shots = |messages: &[NetworkMessage]| -> Vec<(LaneId, Option<u32>)> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                NetworkMessage::ShotRegistered { lane, target_id, .. } => Some((*lane, *target_id)),
+                _ => None,
+            })
+            .collect()
+    };
+    assert!(shots(&received[0]).is_empty(), "Lane 1 has no running match and must not see lane 2 shots");
+    assert_eq!(shots(&received[1]), vec![(2, Some(1))]);
+
+    let lanes = server.world().resource::<Lanes>();
+    assert_eq!(lanes.get(1).unwrap().scores.shots, 0);
+    assert_eq!(lanes.get(2).unwrap().scores.hits, 1);
+}
+
+#[test]
+fn test_joining_unknown_lane_is_refused() {
+    let port = TEST_PORT_BASE + 1;
+    let mut server = create_lane_server(port, 1);
+    let mut terminals = [create_terminal(port)];
+    let mut received = [Vec::new()];
+
+    wait_for_clients(&mut server, &mut terminals, &mut received);
+    join_lane(&mut terminals[0], 5);
+    run(&mut server, &mut terminals, &mut received, 20);
+
+    assert!(server.world().resource::<Lanes>().get(5).is_none());
+    assert_eq!(server.world().resource::<Lanes>().len(), 1);
+    assert_eq!(server.world().resource::<ConnectedClients>().list()[0].lane, None);
+    assert!(
+        !received[0].iter().any(|message| message.lane() == Some(5)),
+        "The terminal should receive nothing about the refused lane"
+    );
+}
+
+#[test]
+fn test_reported_shots_are_scored_on_their_lane() {
+    let port = TEST_PORT_BASE + 2;
+    let mut server = create_lane_server(port, 2);
+    let mut terminals = [create_terminal(port)];
+    let mut received = [Vec::new()];
+
+    wait_for_clients(&mut server, &mut terminals, &mut received);
+    join_lane(&mut terminals[0], 2);
+    {
+        let mut lanes = server.world_mut().resource_mut::<Lanes>();
+        let lane = lanes.get_mut(2).unwrap();
+        lane.targets.place(1.0, 1.0, 0.5);
+        lane.match_state.start();
+    }
+    run(&mut server, &mut terminals, &mut received, 10);
+
+    terminals[0]
+        .world_mut()
+        .resource_mut::<QuinnetClient>()
+        .connection_mut()
+        .send_message(NetworkMessage::ShotReported { lane: 2, x: 1.2, y: 0.9, detected_at_ms: 0 })
+        .expect("Should report shot");
+    run(&mut server, &mut terminals, &mut received, 20);
+
+    let lanes = server.world().resource::<Lanes>();
+    assert_eq!(lanes.get(2).unwrap().scores.hits, 1);
+    assert_eq!(lanes.get(1).unwrap().scores.shots, 0);
+    assert!(
+        received[0]
+            .iter()
+            .any(|message| matches!(message, NetworkMessage::ShotRegistered { lane: 2, target_id: Some(1), .. })),
+        "The terminal should see its shot registered"
+    );
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::{
+    server::{
+        QuinnetServerPlugin, QuinnetServer,
+        certificate::CertificateRetrievalMode,
+        EndpointAddrConfiguration, ServerEndpointConfiguration,
+    },
+    client::{
+        QuinnetClientPlugin, QuinnetClient,
+        certificate::CertificateVerificationMode,
+        connection::ClientAddrConfiguration,
+        ClientConnectionConfiguration,
+    },
+};
+use server::clients::{now_millis, ClientsPlugin, ConnectedClients};
+use common::network::DEFAULT_LANE;
+use server::game::{GamePlugin, Lanes, ShotDetected};
+use server::http::IO_TIMEOUT;
+use server::metrics::MetricsPlugin;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpStream};
+use std::time::{Duration, Instant};
+
+const TEST_PORT_BASE: u16 = 6400;
+
+/// Helper to create a server app serving metrics on the given port
+fn create_metrics_server(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(MetricsPlugin { port });
+    app.update();
+    app
+}
+
+/// Scrape the metrics endpoint while the app keeps updating
+fn scrape(app: &mut App, port: u16) -> String {
+    let handle = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Should connect to metrics");
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("Should read metrics");
+        response
+    });
+
+    while !handle.is_finished() {
+        app.update();
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let response = handle.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "Unexpected response: {}", response);
+    response.split_once("\r\n\r\n").unwrap().1.to_string()
+}
+
+/// Value of the sample with the given name and labels
+fn sample(metrics: &str, name: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.parse().ok())
+}
+
+#[test]
+fn test_exposes_frame_and_client_metrics() {
+    let port = TEST_PORT_BASE;
+    let mut app = create_metrics_server(port);
+
+    let metrics = scrape(&mut app, port);
+
+    assert!(metrics.contains("# TYPE lasertargets_tick_duration_seconds summary"));
+    assert!(sample(&metrics, "lasertargets_tick_duration_seconds_count").unwrap() >= 1.0);
+    assert_eq!(sample(&metrics, "lasertargets_connected_clients"), Some(0.0));
+    assert_eq!(sample(&metrics, "lasertargets_decode_errors_total"), Some(0.0));
+}
+
+#[test]
+fn test_counts_processed_shots() {
+    let port = TEST_PORT_BASE + 1;
+    let mut app = create_metrics_server(port);
+
+    app.world_mut().resource_mut::<Lanes>().get_mut(DEFAULT_LANE).unwrap().match_state.start();
+    app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::ZERO));
+    app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::ONE));
+    app.update();
+
+    let metrics = scrape(&mut app, port);
+
+    assert_eq!(sample(&metrics, "lasertargets_shots_processed_total"), Some(2.0));
+    assert_eq!(sample(&metrics, "lasertargets_hit_latency_seconds_count"), Some(2.0));
+}
+
+#[test]
+fn test_hit_latency_measured_from_detection() {
+    let port = TEST_PORT_BASE + 4;
+    let mut app = create_metrics_server(port);
+
+    app.world_mut().resource_mut::<Lanes>().get_mut(DEFAULT_LANE).unwrap().match_state.start();
+    let detected_at_ms = now_millis() - 250;
+    app.world_mut().write_message(ShotDetected::reported(DEFAULT_LANE, Vec2::ZERO, detected_at_ms));
+    app.update();
+
+    let metrics = scrape(&mut app, port);
+
+    let latency = sample(&metrics, "lasertargets_hit_latency_seconds_last").unwrap();
+    assert!((0.25..1.0).contains(&latency), "Latency should start at detection, got {}", latency);
+}
+
+#[test]
+fn test_counts_client_messages_and_decode_errors() {
+    let port = TEST_PORT_BASE + 2;
+    let metrics_port = TEST_PORT_BASE + 3;
+    let mut server_app = create_metrics_server(metrics_port);
+
+    server_app
+        .world_mut()
+        .resource_mut::<QuinnetServer>()
+        .start_endpoint(ServerEndpointConfiguration {
+            addr_config: EndpointAddrConfiguration::from_ip(Ipv4Addr::LOCALHOST, port),
+            cert_mode: CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: "localhost".to_string(),
+            },
+            defaultables: Default::default(),
+        })
+        .expect("Server should start");
+
+    let mut client_app = App::new();
+    client_app
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetClientPlugin::default());
+    client_app
+        .world_mut()
+        .resource_mut::<QuinnetClient>()
+        .open_connection(ClientConnectionConfiguration {
+            addr_config: ClientAddrConfiguration::from_strings(&format!("127.0.0.1:{}", port), "0.0.0.0:0").unwrap(),
+            cert_mode: CertificateVerificationMode::SkipVerification,
+            defaultables: Default::default(),
+        })
+        .expect("Client should connect");
+
+    for _ in 0..100 {
+        server_app.update();
+        client_app.update();
+        if server_app.world().resource::<ConnectedClients>().len() == 1 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    {
+        let mut client = client_app.world_mut().resource_mut::<QuinnetClient>();
+        let connection = client.get_connection_mut().expect("Client should be connected");
+        connection
+            .send_message(common::network::NetworkMessage::Pong { timestamp: 0 })
+            .expect("Should send message");
+        connection.send_payload(vec![0xFF, 0xFF, 0xFF]).expect("Should send payload");
+    }
+
+    for _ in 0..20 {
+        client_app.update();
+        server_app.update();
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let metrics = scrape(&mut server_app, metrics_port);
+
+    assert_eq!(sample(&metrics, "lasertargets_connected_clients"), Some(1.0));
+    assert_eq!(sample(&metrics, "lasertargets_messages_received_total{type=\"Pong\"}"), Some(1.0));
+    assert_eq!(sample(&metrics, "lasertargets_decode_errors_total"), Some(1.0));
+    assert!(metrics.contains("lasertargets_client_rtt_seconds{client="));
+}
+
+#[test]
+fn test_idle_connection_does_not_block_scrapes() {
+    let port = TEST_PORT_BASE + 5;
+    let mut app = create_metrics_server(port);
+
+    // A client that connects and never sends its request
+    let _idle = TcpStream::connect(("127.0.0.1", port)).expect("Should connect to metrics");
+
+    let started = Instant::now();
+    let metrics = scrape(&mut app, port);
+    assert!(sample(&metrics, "lasertargets_tick_duration_seconds_count").is_some());
+    // Answered right away rather than once the idle connection timed out
+    assert!(started.elapsed() < IO_TIMEOUT, "Scrape took {:?}", started.elapsed());
+}
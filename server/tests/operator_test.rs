@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::{
+    server::{
+        QuinnetServerPlugin, QuinnetServer,
+        certificate::CertificateRetrievalMode,
+        EndpointAddrConfiguration, ServerEndpointConfiguration,
+    },
+    client::{
+        QuinnetClientPlugin, QuinnetClient,
+        certificate::CertificateVerificationMode,
+        connection::ClientAddrConfiguration,
+        ClientConnectionConfiguration,
+    },
+};
+use common::network::{
+    LaneCommand, MatchPhase, NetworkMessage, OperatorCommand, OperatorError, OperatorReply, DEFAULT_LANE,
+};
+use server::clients::{ClientsPlugin, ConnectedClients};
+use server::game::{GamePlugin, Lanes};
+use server::operator::{OperatorPlugin, OperatorToken};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+const TEST_PORT_BASE: u16 = 6700;
+
+/// Helper to create a server app accepting operator commands with the given token
+fn create_operator_server(port: u16, token: Option<&str>) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .insert_resource(OperatorToken(token.map(str::to_string)))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(OperatorPlugin);
+    app.world_mut()
+        .resource_mut::<QuinnetServer>()
+        .start_endpoint(ServerEndpointConfiguration {
+            addr_config: EndpointAddrConfiguration::from_ip(Ipv4Addr::LOCALHOST, port),
+            cert_mode: CertificateRetrievalMode::GenerateSelfSigned {
+                server_hostname: "localhost".to_string(),
+            },
+            defaultables: Default::default(),
+        })
+        .expect("Server should start");
+    app.update();
+    app
+}
+
+/// Helper to connect an operator client such as `ltctl` to the server
+fn create_operator(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetClientPlugin::default());
+    app.world_mut()
+        .resource_mut::<QuinnetClient>()
+        .open_connection(ClientConnectionConfiguration {
+            addr_config: ClientAddrConfiguration::from_strings(&format!("127.0.0.1:{}", port), "0.0.0.0:0").unwrap(),
+            cert_mode: CertificateVerificationMode::SkipVerification,
+            defaultables: Default::default(),
+        })
+        .expect("Client should connect");
+    app
+}
+
+/// Send a command once connected and wait for the reply
+fn run_command(server: &mut App, operator: &mut App, token: &str, command: OperatorCommand) -> OperatorReply {
+    let mut sent = false;
+    for _ in 0..200 {
+        server.update();
+        operator.update();
+        if !sent && server.world().resource::<ConnectedClients>().len() == 1 {
+            operator
+                .world_mut()
+                .resource_mut::<QuinnetClient>()
+                .connection_mut()
+                .send_message(NetworkMessage::Operator { token: token.to_string(), command: command.clone() })
+                .expect("Should send command");
+            sent = true;
+        }
+        let mut client = operator.world_mut().resource_mut::<QuinnetClient>();
+        if let Some(connection) = client.get_connection_mut() {
+            while let Some(message) = connection.try_receive_message::<NetworkMessage>() {
+                if let NetworkMessage::OperatorReply(reply) = message {
+                    return reply;
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("No reply to the operator command");
+}
+
+fn start_match() -> OperatorCommand {
+    OperatorCommand::Lane { lane: DEFAULT_LANE, command: LaneCommand::StartMatch }
+}
+
+#[test]
+fn test_commands_with_token_are_run() {
+    let port = TEST_PORT_BASE;
+    let mut server = create_operator_server(port, Some("secret"));
+    let mut operator = create_operator(port);
+
+    let reply = run_command(&mut server, &mut operator, "secret", start_match());
+
+    assert!(matches!(reply, OperatorReply::Match(_)), "Unexpected reply {:?}", reply);
+    let phase = server.world().resource::<Lanes>().get(DEFAULT_LANE).unwrap().match_state.phase;
+    assert_eq!(phase, MatchPhase::Running);
+}
+
+#[test]
+fn test_commands_with_wrong_token_are_rejected() {
+    let port = TEST_PORT_BASE + 1;
+    let mut server = create_operator_server(port, Some("secret"));
+    let mut operator = create_operator(port);
+
+    let reply = run_command(&mut server, &mut operator, "guess", start_match());
+
+    assert!(matches!(reply, OperatorReply::Rejected(OperatorError::Unauthorized(_))), "Unexpected reply {:?}", reply);
+    let phase = server.world().resource::<Lanes>().get(DEFAULT_LANE).unwrap().match_state.phase;
+    assert_eq!(phase, MatchPhase::Idle);
+}
+
+#[test]
+fn test_commands_are_rejected_without_server_token() {
+    let port = TEST_PORT_BASE + 2;
+    let mut server = create_operator_server(port, None);
+    let mut operator = create_operator(port);
+
+    let reply = run_command(&mut server, &mut operator, "", OperatorCommand::GetStatus);
+
+    assert!(matches!(reply, OperatorReply::Rejected(OperatorError::Unauthorized(_))), "Unexpected reply {:?}", reply);
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::server::QuinnetServerPlugin;
+use server::clients::ClientsPlugin;
+use common::network::DEFAULT_LANE;
+use server::game::{GamePlugin, Lanes, ShotDetected};
+use server::scoreboard::ScoreboardPlugin;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const TEST_PORT_BASE: u16 = 6300;
+
+/// Helper to create a server app serving the scoreboard on the given port
+fn create_scoreboard_server(port: u16) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .add_plugins(ScoreboardPlugin { port });
+    app.update();
+    app
+}
+
+fn open_request(port: u16, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Should connect to scoreboard");
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    stream
+}
+
+#[test]
+fn test_serves_scoreboard_page() {
+    let port = TEST_PORT_BASE;
+    let _app = create_scoreboard_server(port);
+
+    let mut stream = open_request(port, "/");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Should read page");
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("text/html"));
+    assert!(response.contains("EventSource"), "Page should subscribe to the event stream");
+}
+
+#[test]
+fn test_unknown_path_not_found() {
+    let port = TEST_PORT_BASE + 1;
+    let _app = create_scoreboard_server(port);
+
+    let mut stream = open_request(port, "/missing");
+    let mut response = String::new();
+    stream.read_to_string(&mut response).expect("Should read response");
+
+    assert!(response.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn test_event_stream_sends_state_and_live_shots() {
+    let port = TEST_PORT_BASE + 2;
+    let mut app = create_scoreboard_server(port);
+
+    let reader = std::thread::spawn(move || {
+        let stream = open_request(port, "/events");
+        let mut reader = BufReader::new(stream);
+        let mut events = Vec::new();
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if let Some(data) = line.strip_prefix("data: ") {
+                        events.push(data.trim().to_string());
+                        if data.contains("ShotRegistered") {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        events
+    });
+
+    for frame in 0..500 {
+        if frame == 30 {
+            let mut lanes = app.world_mut().resource_mut::<Lanes>();
+            let lane = lanes.get_mut(DEFAULT_LANE).unwrap();
+            lane.targets.place(0.0, 0.0, 0.5);
+            lane.match_state.start();
+        }
+        if frame == 40 {
+            app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, Vec2::new(0.2, 0.0)));
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(10));
+        if reader.is_finished() {
+            break;
+        }
+    }
+
+    let events = reader.join().unwrap();
+    assert!(events.iter().any(|event| event.contains("SceneConfiguration")), "Should receive the current state first");
+    assert!(events.iter().any(|event| event.contains("\"Running\"")), "Should receive match phase changes");
+
+    let shot: serde_json::Value = serde_json::from_str(events.last().unwrap()).unwrap();
+    assert_eq!(shot["ShotRegistered"]["target_id"], 1);
+}
@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy::app::ScheduleRunnerPlugin;
+use bevy_quinnet::server::QuinnetServerPlugin;
+use common::config::{CameraConfiguration, SceneConfiguration};
+use common::detection::{DetectionConfig, SpotDetector};
+use common::frame::FrameSource;
+use common::network::{NetworkMessage, DEFAULT_LANE};
+use common::simulator::{HitProfile, ThermalSimulator, WarmBody};
+use common::tracking::{SpotTracker, TrackingConfig};
+use server::clients::ClientsPlugin;
+use server::game::{GameBroadcast, GamePlugin, Lanes, ShotDetected};
+use std::time::Duration;
+
+const FPS: f32 = 25.0;
+const FRAME: Duration = Duration::from_millis(40);
+
+/// Broadcasts sent by the game, recorded for assertions
+#[derive(Resource, Default)]
+struct Broadcasts(Vec<NetworkMessage>);
+
+fn record_broadcasts(mut broadcasts: MessageReader<GameBroadcast>, mut recorded: ResMut<Broadcasts>) {
+    recorded.0.extend(broadcasts.read().map(|broadcast| broadcast.0.clone()));
+}
+
+/// A server app with a running match and targets at the given scene-local positions
+fn create_game(targets: &[Vec2]) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_once()))
+        .add_plugins(QuinnetServerPlugin::default())
+        .add_plugins(ClientsPlugin)
+        .add_plugins(GamePlugin)
+        .init_resource::<Broadcasts>()
+        .add_systems(PostUpdate, record_broadcasts);
+
+    let mut lanes = app.world_mut().resource_mut::<Lanes>();
+    let lane = lanes.get_mut(DEFAULT_LANE).unwrap();
+    for target in targets {
+        lane.targets.place(target.x, target.y, 0.5);
+    }
+    lane.match_state.start();
+    app.update();
+    app
+}
+
+/// A simulator whose hits cool down within one frame
+fn create_simulator() -> ThermalSimulator {
+    let mut simulator = ThermalSimulator::new(&CameraConfiguration::default(), &SceneConfiguration::default(), FPS).unwrap();
+    simulator.hit_profile = HitProfile {
+        decay: Duration::from_millis(15),
+        ..HitProfile::default()
+    };
+    simulator
+}
+
+/// Feed the simulated frames through detection and tracking into the game, returning the detected positions
+fn run(app: &mut App, simulator: &mut ThermalSimulator, frames: usize) -> Vec<Vec2> {
+    let mut detector = SpotDetector::new(DetectionConfig::default());
+    let mut tracker = SpotTracker::new(TrackingConfig::default());
+    let mapping = simulator.mapping();
+    let mut detected = Vec::new();
+    for _ in 0..frames {
+        let frame = simulator.next_frame().unwrap().unwrap();
+        let spots = detector.process(&frame);
+        for blob in tracker.update(&spots, frame.timestamp) {
+            let position = mapping.to_scene(blob.centroid);
+            detected.push(position);
+            app.world_mut().write_message(ShotDetected::new(DEFAULT_LANE, position));
+        }
+        app.update();
+    }
+    detected
+}
+
+fn registered_shots(app: &App) -> Vec<Option<u32>> {
+    app.world()
+        .resource::<Broadcasts>()
+        .0
+        .iter()
+        .filter_map(|message| match message {
+            NetworkMessage::ShotRegistered { target_id, .. } => Some(*target_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_scripted_shots_are_scored() {
+    let mut app = create_game(&[Vec2::new(-2.0, 1.0), Vec2::new(2.0, -1.0)]);
+    let mut simulator = create_simulator();
+    let shots = [Vec2::new(-2.1, 1.2), Vec2::new(2.0, -1.0), Vec2::new(0.0, 2.5)];
+    for (index, shot) in shots.iter().enumerate() {
+        simulator.shoot(FRAME * (5 + 10 * index as u32), *shot);
+    }
+
+    let detected = run(&mut app, &mut simulator, 40);
+
+    assert_eq!(detected.len(), shots.len());
+    for (detected, shot) in detected.iter().zip(shots) {
+        assert!(detected.distance(shot) < 0.05, "detected {:?} for a shot at {:?}", detected, shot);
+    }
+    assert_eq!(registered_shots(&app), vec![Some(1), Some(2), None]);
+    let scores = app.world().resource::<Lanes>().get(DEFAULT_LANE).unwrap().scores;
+    assert_eq!((scores.shots, scores.hits), (3, 2));
+}
+
+#[test]
+fn test_simultaneous_shots_are_scored() {
+    let mut app = create_game(&[Vec2::new(-3.0, 0.0), Vec2::new(3.0, 0.0)]);
+    let mut simulator = create_simulator();
+    simulator.shoot(FRAME * 5, Vec2::new(-3.0, 0.0));
+    simulator.shoot(FRAME * 5, Vec2::new(3.0, 0.0));
+
+    run(&mut app, &mut simulator, 10);
+
+    let mut registered = registered_shots(&app);
+    registered.sort();
+    assert_eq!(registered, vec![Some(1), Some(2)]);
+}
+
+#[test]
+fn test_noise_bodies_and_dead_pixels_are_not_shots() {
+    let mut app = create_game(&[Vec2::ZERO]);
+    let mut simulator = create_simulator();
+    simulator.noise = 0.8;
+    simulator.seed(11);
+    simulator.dead_pixels = vec![UVec2::new(10, 10), UVec2::new(128, 96), UVec2::new(200, 150)];
+    simulator.bodies.push(WarmBody {
+        start: Vec2::new(-5.0, -1.0),
+        velocity: Vec2::new(1.5, 0.0),
+        size: Vec2::new(0.5, 1.7),
+        temperature: 34.0,
+        appears_at: FRAME * 2,
+        leaves_at: FRAME * 60,
+    });
+    simulator.shoot(FRAME * 30, Vec2::new(0.1, 1.5));
+
+    let detected = run(&mut app, &mut simulator, 60);
+
+    assert_eq!(detected.len(), 1, "only the laser hit should be detected, got {:?}", detected);
+    assert_eq!(registered_shots(&app), vec![None]);
+}
+
+#[test]
+fn test_lingering_heat_is_scored_once() {
+    let mut app = create_game(&[Vec2::new(1.0, 1.0)]);
+    let mut simulator = create_simulator();
+    simulator.hit_profile = HitProfile::default();
+    simulator.shoot(FRAME * 5, Vec2::new(1.0, 1.0));
+    simulator.shoot(FRAME * 30, Vec2::new(1.1, 1.0));
+
+    run(&mut app, &mut simulator, 80);
+
+    assert_eq!(registered_shots(&app), vec![Some(1), Some(1)]);
+    let scores = app.world().resource::<Lanes>().get(DEFAULT_LANE).unwrap().scores;
+    assert_eq!((scores.shots, scores.hits), (2, 2));
+}
@@ -2,6 +2,7 @@ use std::env;
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use log::info;
+use common::network::{LaneId, DEFAULT_LANE};
 
 mod plugins;
 mod util;
@@ -16,10 +17,44 @@ use crate::plugins::settings::SettingsPlugin;
 use crate::plugins::target::TargetPlugin;
 use crate::plugins::basictarget::BasicTargetPlugin;
 use crate::plugins::networking::NetworkingPlugin;
+use crate::plugins::detection::DetectionPlugin;
+use crate::plugins::thermalview::ThermalViewPlugin;
+use crate::plugins::recording::RecordingPlugin;
+use crate::plugins::masks::MasksPlugin;
+use crate::plugins::lens::LensPlugin;
+use crate::plugins::layout::LayoutPlugin;
+use crate::plugins::validation::ValidationPlugin;
+use crate::plugins::zeroing::ZeroingPlugin;
 
 const FIXED_TIMESTEP: f64 = 1.0 / 50.0; 
+const USAGE: &str = "Usage: terminal [--lane <id>]";
+
+/// Shooting lane given with `--lane`, the default lane otherwise
+fn parse_lane() -> LaneId {
+    let mut lane = DEFAULT_LANE;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Lanes are counted from the default lane
+            "--lane" => match args.next().and_then(|id| id.parse().ok()).filter(|&id| id >= DEFAULT_LANE) {
+                Some(id) => lane = id,
+                None => {
+                    eprintln!("--lane expects a lane id from {}", DEFAULT_LANE);
+                    std::process::exit(2);
+                }
+            },
+            _ => {
+                eprintln!("Unknown argument: {}", arg);
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    lane
+}
 
 fn main() {
+    let lane = parse_lane();
 
     util::setup_logging();
     unsafe {
@@ -51,6 +86,14 @@ fn main() {
     .add_plugins(SettingsPlugin)
     .add_plugins(BasicTargetPlugin)
     .add_plugins(TargetPlugin)
-    .add_plugins(NetworkingPlugin);
+    .add_plugins(DetectionPlugin)
+    .add_plugins(ThermalViewPlugin)
+    .add_plugins(RecordingPlugin)
+    .add_plugins(MasksPlugin)
+    .add_plugins(LensPlugin)
+    .add_plugins(LayoutPlugin)
+    .add_plugins(ValidationPlugin)
+    .add_plugins(ZeroingPlugin)
+    .add_plugins(NetworkingPlugin { lane });
     app.run();
 }
@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_prototype_lyon::prelude::*;
 use log::info;
 use common::path::{UniversalPath, PathProvider, PathRenderable};
-use crate::plugins::scene::{SceneData, SceneTag};
+use crate::plugins::scene::PlaneCursor;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct BasicTargetSystemSet;
@@ -52,7 +52,7 @@ fn draw_basic_targets(
 fn handle_basic_target_click(
     mut commands: Commands,
     mouse_button: Res<ButtonInput<MouseButton>>,
-    scene_query: Query<&SceneData, With<SceneTag>>,
+    plane_cursor: Res<PlaneCursor>,
     target_query: Query<(Entity, &GlobalTransform, &BasicTarget)>,
 ) {
     // Only check on mouse click
@@ -60,12 +60,8 @@ fn handle_basic_target_click(
         return;
     }
 
-    let Ok(scene_data) = scene_query.single() else {
-        return;
-    };
-
-    // Use prepared mouse world position from scene data
-    let Some(mouse_world_pos) = scene_data.mouse_world_pos else {
+    // Use prepared mouse world position on the plane under the cursor
+    let Some(mouse_world_pos) = plane_cursor.0.as_ref().map(|hit| hit.world) else {
         return;
     };
 
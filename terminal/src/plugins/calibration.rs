@@ -1,9 +1,16 @@
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
 use crate::plugins::camera::CameraSystemSet;
 use crate::plugins::camera::DisplayMode;
+use crate::plugins::detection::LatestFrame;
+use crate::plugins::scene::SceneClicks;
 use crate::plugins::scene::SceneData;
 use crate::plugins::scene::SceneTag;
-use common::config::{SceneConfiguration, ProjectorConfiguration};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+use common::config::{CameraConfiguration, SceneConfiguration, ProjectorConfiguration};
+use common::homography::{estimate_homography, HomographyFit};
+use common::lens::find_hot_spots;
+use common::venue::VenueConfiguration;
 use std::f32::consts::PI;
 use bevy::color::palettes::css::DARK_GREY;
 use bevy::color::palettes::css::SILVER;
@@ -13,6 +20,11 @@ use bevy::color::palettes::css::BLUE;
 use bevy::color::palettes::css::YELLOW;
 use bevy::color::palettes::css::ORANGE;
 
+const BTN_NAME: &str = "homography";
+
+/// Clicks this close to a heated marker, in camera pixels, are moved onto its centroid
+const SNAP_RADIUS: f32 = 6.0;
+
 pub const DARK_GREY_THIRD: Srgba = Srgba::new(0.663, 0.663, 0.663, 0.3);
 
 pub const GRID_SPACING: f32 = 0.25;
@@ -22,10 +34,49 @@ pub struct CalibrationSystemSet;
 
 pub struct CalibrationPlugin;
 
+/// A point of the thermal image whose position on the scene is known
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ReferencePoint {
+    /// Camera input pixel, as seen through the lens
+    pixel: Vec2,
+    /// Scene-local position in meters, as measured at the venue
+    position: Vec2,
+}
+
+/// Calibration of the main camera homography from reference points marked on the thermal image
+#[derive(Resource)]
+struct HomographyCalibration {
+    active: bool,
+    points: Vec<ReferencePoint>,
+    /// Move clicks onto the centroid of the nearest heated marker
+    snap: bool,
+    /// Kelvin the markers are warmer than the wall
+    min_rise: f32,
+    /// Fit of the current points, once there are enough of them
+    fit: Option<Result<HomographyFit, String>>,
+}
+
+impl Default for HomographyCalibration {
+    fn default() -> Self {
+        Self {
+            active: false,
+            points: Vec::new(),
+            snap: true,
+            min_rise: 5.0,
+            fit: None,
+        }
+    }
+}
+
 
 impl Plugin for CalibrationPlugin {
     fn build(&self, app: &mut App) {
         app
+        .init_resource::<HomographyCalibration>()
+        .add_systems(Startup, register_homography_button)
+        .add_systems(Update, handle_homography_button.in_set(CalibrationSystemSet))
+        .add_systems(Update, draw_reference_points.in_set(CalibrationSystemSet).after(CameraSystemSet))
+        .add_systems(EguiPrimaryContextPass, homography_ui)
         .add_systems(Update, update_grid.in_set(CalibrationSystemSet).after(CameraSystemSet))
         //.add_systems(Update, draw_axes.in_set(CalibrationSystemSet).after(CameraSystemSet))
         .add_systems(Update, draw_billboard_gizmos.in_set(CalibrationSystemSet).after(CameraSystemSet))
@@ -129,38 +180,20 @@ fn draw_crosshair(
 
 fn draw_projector_billboard(
     mut gizmos: Gizmos,
-    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
-    scene_query: Query<(&GlobalTransform, &SceneData), With<SceneTag>>,
+    scene_query: Query<&GlobalTransform, With<SceneTag>>,
     projector_config: Res<ProjectorConfiguration>,
-    scene_config: Res<SceneConfiguration>,
 ) {
-    for (camera, camera_transform) in camera_query.iter() {
-        for (scene_transform, scene_data) in scene_query.iter() {
-            let billboard_position = scene_transform.translation();
-            
-            // Calculate projected size based on angle and distance
-            let angle_rad = projector_config.angle.to_radians();
-            let distance = scene_config.target_projection_distance;
-            let half_angle = angle_rad / 2.0;
-            let projected_size = 2.0 * distance * half_angle.tan();
-            
-            // Square dimensions for projector
-            let width = projected_size;
-            let height = projected_size;
-            
-            let orange_alpha = Srgba::new(1.0, 0.647, 0.0, 0.3);
-            
-            draw_billboard_grid(
-                &mut gizmos,
-                camera_transform,
-                billboard_position,
-                width,
-                height,
-                ORANGE,
-                orange_alpha,
-                GRID_SPACING * 2.0,
-            );
-        }
+    for scene_transform in scene_query.iter() {
+        let scene = scene_transform.compute_transform();
+        let size = projector_config.output_resolution.as_vec2();
+        let corners = [Vec2::ZERO, Vec2::new(size.x, 0.0), size, Vec2::new(0.0, size.y)];
+
+        // The output covers the quad its mapping puts on the scene, once calibrated, or else the one thrown from its pose
+        let Some(mapping) = projector_config.scene_mapping(&scene) else {
+            continue;
+        };
+        let footprint = corners.map(|corner| scene_transform.transform_point(mapping.apply(corner).extend(0.0)));
+        gizmos.linestrip(footprint.into_iter().chain([footprint[0]]), ORANGE);
     }
 }
 
@@ -220,3 +253,198 @@ fn draw_billboard_grid(
         gizmos.line(start, end, grid_color);
     }
 }
+
+fn register_homography_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Camera calibration".to_string(),
+        icon: Some("\u{f05b}".to_string()), // Crosshairs icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_homography_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut calibration: ResMut<HomographyCalibration>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            calibration.active = !calibration.active;
+            toolbar_registry.update_button_state(BTN_NAME, calibration.active);
+        }
+    }
+}
+
+/// Pixel of the thermal feed shown at a scene-local position, the feed being stretched over the camera footprint
+fn feed_pixel(scene_data: &SceneData, position: Vec2) -> Vec2 {
+    let footprint = scene_data.camera_mapping().footprint();
+    let resolution = scene_data.camera_resolution.as_vec2();
+    Vec2::new(
+        (position.x - footprint.min.x) / footprint.width() * resolution.x,
+        (footprint.max.y - position.y) / footprint.height() * resolution.y,
+    )
+}
+
+/// Scene-local position at which a pixel of the thermal feed is shown
+fn feed_position(scene_data: &SceneData, pixel: Vec2) -> Vec2 {
+    let footprint = scene_data.camera_mapping().footprint();
+    let resolution = scene_data.camera_resolution.as_vec2();
+    Vec2::new(
+        footprint.min.x + pixel.x / resolution.x * footprint.width(),
+        footprint.max.y - pixel.y / resolution.y * footprint.height(),
+    )
+}
+
+/// Fit the homography to the reference points, their pixels undistorted through the lens
+fn fit_homography(points: &[ReferencePoint], config: &CameraConfiguration) -> Result<HomographyFit, String> {
+    let pairs: Vec<(Vec2, Vec2)> = points
+        .iter()
+        .map(|point| (config.lens.map_or(point.pixel, |lens| lens.undistort(point.pixel)), point.position))
+        .collect();
+    estimate_homography(&pairs).map_err(|e| e.to_string())
+}
+
+/// Mark the reference points where they were clicked, with the error of the fit towards their known position
+fn draw_reference_points(
+    mut gizmos: Gizmos,
+    calibration: Res<HomographyCalibration>,
+    config: Res<CameraConfiguration>,
+    scene_query: Query<(&GlobalTransform, &SceneData), With<SceneTag>>,
+) {
+    if !calibration.active {
+        return;
+    }
+    let Ok((scene_transform, scene_data)) = scene_query.single() else {
+        return;
+    };
+
+    let rotation = scene_transform.rotation();
+    let to_world = |position: Vec2| scene_transform.transform_point(position.extend(0.0));
+    let fit = calibration.fit.as_ref().and_then(|fit| fit.as_ref().ok());
+    for point in &calibration.points {
+        let clicked = to_world(feed_position(scene_data, point.pixel));
+        gizmos.circle(Isometry3d::new(clicked, rotation), GRID_SPACING * 0.25, YELLOW);
+        let known = to_world(point.position);
+        gizmos.cross(Isometry3d::new(known, rotation), GRID_SPACING * 0.5, GREEN);
+        if let Some(fit) = fit {
+            let pixel = config.lens.map_or(point.pixel, |lens| lens.undistort(point.pixel));
+            gizmos.line(known, to_world(fit.homography.apply(pixel)), RED);
+        }
+    }
+}
+
+fn homography_ui(
+    mut egui_context: EguiContexts,
+    mut calibration: ResMut<HomographyCalibration>,
+    mut venue: ResMut<VenueConfiguration>,
+    config: Res<CameraConfiguration>,
+    latest: Res<LatestFrame>,
+    scene_query: Query<&SceneData, With<SceneTag>>,
+    clicks: SceneClicks,
+) {
+    if !calibration.active {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    // Clicks outside of the panels add a reference point where the thermal feed shows it
+    let mut edited = calibration.points.clone();
+    if !ctx.is_pointer_over_area()
+        && let (Some(position), Ok(scene_data)) = (clicks.scene_position(MouseButton::Left), scene_query.single())
+    {
+        let mut pixel = feed_pixel(scene_data, position);
+        if calibration.snap
+            && let Some(frame) = latest.frame.as_ref()
+            && let Some(marker) = find_hot_spots(frame, &config.radiometry, calibration.min_rise)
+                .into_iter()
+                .filter(|marker| marker.distance(pixel) <= SNAP_RADIUS)
+                .min_by(|a, b| a.distance(pixel).total_cmp(&b.distance(pixel)))
+        {
+            pixel = marker;
+        }
+        edited.push(ReferencePoint { pixel, position });
+    }
+
+    let mut removed = None;
+    let mut applied = None;
+    let mut reset = false;
+    egui::Window::new("Camera Calibration")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 320.0])
+        .show(ctx, |ui| {
+            ui.label("Click four or more heated markers on the thermal feed, then enter their measured positions.");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut calibration.snap, "Snap to markers warmer by");
+                ui.add(egui::DragValue::new(&mut calibration.min_rise).speed(0.1).range(0.5..=100.0).suffix(" K"));
+            });
+            ui.separator();
+
+            let errors = calibration.fit.as_ref().and_then(|fit| fit.as_ref().ok()).map(|fit| fit.errors.clone());
+            for (index, point) in edited.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add_sized([110.0, 0.0], egui::Label::new(format!("{:.1}, {:.1} px", point.pixel.x, point.pixel.y)));
+                    ui.add(egui::DragValue::new(&mut point.position.x).speed(0.01).prefix("x ").suffix(" m"));
+                    ui.add(egui::DragValue::new(&mut point.position.y).speed(0.01).prefix("y ").suffix(" m"));
+                    if let Some(error) = errors.as_ref().and_then(|errors| errors.get(index)) {
+                        ui.label(format!("{:.0} mm", error * 1000.0));
+                    }
+                    if ui.button("Delete").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if ui.add_enabled(!edited.is_empty(), egui::Button::new("Clear points")).clicked() {
+                edited.clear();
+            }
+
+            match &calibration.fit {
+                Some(Ok(fit)) => {
+                    ui.label(format!("Reprojection error {:.0} mm rms", fit.rms_error * 1000.0));
+                    if ui.button("Apply").clicked() {
+                        applied = Some(fit.homography);
+                    }
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e);
+                }
+                None => {}
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                match (venue.homography, venue.main_homography()) {
+                    (Some(_), Some(_)) => ui.label("Camera homography active"),
+                    (Some(_), None) => ui.colored_label(egui::Color32::LIGHT_RED, "Homography fitted through another lens, calibrate again"),
+                    (None, _) => ui.label("Camera facing the scene center"),
+                };
+                if ui.add_enabled(venue.homography.is_some(), egui::Button::new("Reset")).clicked() {
+                    reset = true;
+                }
+            });
+        });
+
+    if let Some(index) = removed {
+        edited.remove(index);
+    }
+    // Fit again through a new lens as well, the fit holds only through the lens it was made with
+    if edited != calibration.points || config.is_changed() {
+        calibration.fit = (edited.len() >= 4).then(|| fit_homography(&edited, &config));
+        calibration.points = edited;
+    }
+
+    if let Some(homography) = applied {
+        info!("Applied camera homography {:?}", homography);
+        venue.homography = Some(homography);
+        venue.homography_lens = config.lens;
+    }
+    if reset {
+        venue.homography = None;
+        venue.homography_lens = None;
+    }
+}
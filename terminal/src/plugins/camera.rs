@@ -4,8 +4,12 @@ use bevy::camera::Viewport;
 use bevy::window::WindowResized;
 use crate::plugins::instructions::{DebugInfoState, InstructionState};
 use crate::plugins::scene::{SceneData, SceneSystemSet, SceneTag};
-use bevy_camera::ScalingMode;
-use common::config::CameraConfiguration;
+use bevy_camera::primitives::Frustum;
+use bevy_camera::{CameraProjection, ScalingMode, SubCameraView};
+use bevy::math::Vec3A;
+use common::config::{AdditionalCameras, CameraConfiguration, ProjectorConfiguration};
+use common::lens::CameraOptics;
+use common::projector::Keystone;
 
 pub struct CameraPlugin;
 
@@ -17,6 +21,10 @@ pub enum DisplayMode {
     Mode3D,
 }
 
+/// Whether the 3D mode overlooks the devices and the scene instead of seeing through the thermal camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub struct SetupView(pub bool);
+
 /// Defines how the viewport scales to fit the window
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
 pub enum ViewportMode {
@@ -29,6 +37,7 @@ pub enum ViewportMode {
 
 const INSTRUCTION_F2: &str = "Press [F2] to toggle between 2d and 3d display mode";
 const INSTRUCTION_F3: &str = "Press [F3] to toggle viewport scaling mode";
+const INSTRUCTION_F4: &str = "Press [F4] to toggle the 3d setup view";
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct CameraSystemSet;
@@ -36,12 +45,48 @@ pub struct CameraSystemSet;
 #[derive(Component)]
 pub struct CameraTag;
 
+/// Projection drawing the picture of another projection into the keystone quad of the output
+#[derive(Debug, Clone)]
+struct KeystoneProjection {
+    projection: Projection,
+    warp: Mat4,
+}
+
+impl CameraProjection for KeystoneProjection {
+    fn get_clip_from_view(&self) -> Mat4 {
+        self.warp * self.projection.get_clip_from_view()
+    }
+
+    fn get_clip_from_view_for_sub(&self, sub_view: &SubCameraView) -> Mat4 {
+        self.warp * self.projection.get_clip_from_view_for_sub(sub_view)
+    }
+
+    fn update(&mut self, width: f32, height: f32) {
+        self.projection.update(width, height);
+    }
+
+    fn far(&self) -> f32 {
+        self.projection.far()
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        self.projection.get_frustum_corners(z_near, z_far)
+    }
+
+    // The picture is only moved within the output, so what it shows is culled as without the warp
+    fn compute_frustum(&self, camera_transform: &GlobalTransform) -> Frustum {
+        self.projection.compute_frustum(camera_transform)
+    }
+}
+
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
         .insert_resource(DisplayMode::default())
         .insert_resource(ViewportMode::default())
+        .insert_resource(SetupView::default())
         .insert_resource(CameraConfiguration::default())
+        .insert_resource(AdditionalCameras::default())
         .add_systems(Startup, (setup_camera).chain().in_set(CameraSystemSet).after(SceneSystemSet))
         .add_systems(Update, update_camera.in_set(CameraSystemSet).after(SceneSystemSet));
     }
@@ -53,10 +98,12 @@ fn setup_camera(
     mut instruction_state: ResMut<InstructionState>,
     scene_query: Query<(&SceneData), With<SceneTag>>,
     display_mode: Res<DisplayMode>,
-    config: Res<CameraConfiguration>) {
+    config: Res<CameraConfiguration>,
+    projector_config: Res<ProjectorConfiguration>) {
     
      instruction_state.instructions.push(INSTRUCTION_F2.to_string());
      instruction_state.instructions.push(INSTRUCTION_F3.to_string());
+     instruction_state.instructions.push(INSTRUCTION_F4.to_string());
      
      for (scene_data) in scene_query.iter() {
 
@@ -77,7 +124,7 @@ fn setup_camera(
             }),
             ..default()
         },
-        get_projection(*display_mode, scene_data.dimensions.x, scene_data.dimensions.y),
+        get_projection(*display_mode, scene_data.dimensions.x, scene_data.dimensions.y, &projector_config.keystone, config.optics),
         Transform::from_translation(config.transform.translation)
             .looking_at(config.transform.translation, Vec3::Y),
         ));
@@ -90,21 +137,32 @@ fn update_camera(
     mut camera_query: Query<(&mut Camera, &mut Projection, &mut Transform), With<CameraTag>>,
     scene_query: Query<(&GlobalTransform, &SceneData), With<SceneTag>>,
     mut config: ResMut<CameraConfiguration>,
+    projector_config: Res<ProjectorConfiguration>,
     keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
     mut debug_info: ResMut<DebugInfoState>,
     mut display_mode: ResMut<DisplayMode>,
     mut viewport_mode: ResMut<ViewportMode>,
+    mut setup_view: ResMut<SetupView>,
 ) {
     if let Ok((mut camera, mut projection, mut transform)) = camera_query.single_mut() {
       for (scene_transform,scene_data) in scene_query.iter() {
 
-        configure_camera(&mut display_mode, &mut viewport_mode, &keyboard);
+        configure_camera(&mut display_mode, &mut viewport_mode, &mut setup_view, &keyboard);
         if *display_mode == DisplayMode::Mode2D {
             // For 2D mode, ensure the camera is looking straight down the Z-axis by aligning it with the scene center.
             // This resets the camera's orientation and X/Y position for a clean top-down view, while preserving Z-distance.
             let scene_center = scene_transform.translation();
             *transform = Transform::from_xyz(scene_center.x, scene_center.y, transform.translation.z)
                 .looking_at(scene_center, Vec3::Y);
+        } else if setup_view.0 {
+            // Above and to the side of the space between the devices and the scene, held still while a device is dragged
+            if setup_view.is_changed() || !mouse_button.pressed(MouseButton::Left) {
+                let looking_at = scene_transform.translation() + Vec3::Z * scene_data.distance / 2.0;
+                let reach = scene_data.distance;
+                *transform = Transform::from_translation(looking_at + Vec3::new(reach * 0.6, reach * 0.5, reach * 0.6))
+                    .looking_at(looking_at, Vec3::Y);
+            }
         } else {
             *transform = Transform::from_translation(config.transform.translation)
                 .looking_at(scene_transform.translation(), Vec3::Y);
@@ -134,8 +192,9 @@ fn update_camera(
             }
         }
 
-        if config.is_changed() {
-            *projection = get_projection(*display_mode, scene_data.dimensions.x, scene_data.dimensions.y);
+        if config.is_changed() || projector_config.is_changed() || display_mode.is_changed() || setup_view.is_changed() {
+            let optics = config.optics.filter(|_| !setup_view.0);
+            *projection = get_projection(*display_mode, scene_data.dimensions.x, scene_data.dimensions.y, &projector_config.keystone, optics);
         }
          
       }
@@ -144,21 +203,34 @@ fn update_camera(
 }
 
 
-fn get_projection(display_mode: DisplayMode,w:f32, h:f32) -> Projection {
+/// Scene-local meters the 2D picture spans across and down a viewport of the given size
+pub fn picture_extent(w: f32, h: f32, viewport: UVec2) -> Vec2 {
+    // As the 2D projection fits the largest dimension to the viewport height
+    let height = w.max(h);
+    Vec2::new(height * viewport.x as f32 / viewport.y.max(1) as f32, height)
+}
+
+fn get_projection(display_mode: DisplayMode,w:f32, h:f32, keystone: &Keystone, optics: Option<CameraOptics>) -> Projection {
     match display_mode {
         
         DisplayMode::Mode2D => {
             // Scale to fit the largest dimension proportionally
             // This ensures the entire scene fits in the viewport
             let scale = w.max(h);
-            Projection::from(OrthographicProjection {
+            let projection = Projection::from(OrthographicProjection {
                 scaling_mode: ScalingMode::FixedVertical { viewport_height: scale },
                 ..OrthographicProjection::default_2d()
-            })
+            });
+            // The 2D view is what the projector throws, so only it is keystoned
+            match keystone.clip_warp() {
+                Some(warp) if !keystone.is_identity() => Projection::custom(KeystoneProjection { projection, warp }),
+                _ => projection,
+            }
         },
         
+        // With its optics known, the preview sees the scene with the field of view of the thermal camera
         DisplayMode::Mode3D => Projection::from(PerspectiveProjection {
-            fov: std::f32::consts::PI / 4.0,
+            fov: optics.map_or(std::f32::consts::PI / 4.0, |optics| optics.fov().y.to_radians()),
             near: 0.1,
             far: 1000.0,
             ..default()
@@ -169,6 +241,7 @@ fn get_projection(display_mode: DisplayMode,w:f32, h:f32) -> Projection {
 fn configure_camera(
     display_mode: &mut ResMut<DisplayMode>, 
     viewport_mode: &mut ResMut<ViewportMode>,
+    setup_view: &mut ResMut<SetupView>,
     keyboard: &Res<ButtonInput<KeyCode>>
 ) {
     if keyboard.just_pressed(KeyCode::F2) {
@@ -185,5 +258,9 @@ fn configure_camera(
             ViewportMode::FillWidth => ViewportMode::AspectFit,
         };
     }
+
+    if keyboard.just_pressed(KeyCode::F4) {
+        setup_view.0 = !setup_view.0;
+    }
 }
 
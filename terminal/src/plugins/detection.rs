@@ -0,0 +1,304 @@
+use bevy::prelude::*;
+use common::config::{AdditionalCameras, CameraConfiguration};
+use common::detection::{Blob, CameraMapping, SpotDetector};
+use common::masks::PixelMask;
+use common::stitching::HitMerger;
+use common::tracking::SpotTracker;
+use common::shooter::ShooterProfiles;
+use common::venue::VenueConfiguration;
+use common::frame::{FrameSource, ThermalFrame};
+use common::radiometry::RadiometricCalibration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::plugins::networking::TerminalLane;
+use crate::plugins::scene::{SceneCameras, SceneSystemSet};
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct DetectionSystemSet;
+
+/// A laser hit found on the thermal image, positioned in scene-local meters
+#[derive(Message, Debug, Clone, Copy)]
+pub struct ShotDetected {
+    /// Where the shooter on the lane aimed, the hit corrected by the zero of their laser
+    pub position: Vec2,
+    /// Where the laser hit
+    pub raw: Vec2,
+    /// Milliseconds since the unix epoch at which the shot was detected
+    pub detected_at_ms: u64,
+}
+
+/// A frame read from a thermal camera, sent for every frame run through the detector
+#[derive(Message, Debug, Clone)]
+pub struct FrameCaptured {
+    /// Index of the camera, 0 for the main camera followed by the additional ones
+    pub camera: usize,
+    pub frame: ThermalFrame,
+    /// Calibration the frame is read with, the one it was recorded with when played back
+    pub radiometry: RadiometricCalibration,
+}
+
+/// Control of a frame source playing a recording back
+#[derive(Message, Debug, Clone, Copy)]
+pub enum PlaybackCommand {
+    Pause(bool),
+    /// Continue from the given time of the recording
+    Seek(Duration),
+    Speed(f32),
+}
+
+/// State of the recording played back, when the frame source is one
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlaybackStatus {
+    pub available: bool,
+    pub paused: bool,
+    pub speed: f32,
+    pub position: Duration,
+    pub duration: Duration,
+}
+
+/// The last frame of the main camera run through the detector, with the spots found on it
+#[derive(Resource, Default)]
+pub struct LatestFrame {
+    pub frame: Option<ThermalFrame>,
+    pub spots: Vec<Blob>,
+    /// Calibration the frame is read with, the one it was recorded with when played back
+    pub radiometry: RadiometricCalibration,
+}
+
+/// Frames coming from the frame source of one camera, and the detector keeping the ones that are new shots
+struct CameraFeed {
+    source: Option<Box<dyn FrameSource>>,
+    /// App time at which the first frame was shown, frames are paced from there
+    started: Option<Duration>,
+    /// Frame read ahead of time, waiting for its timestamp
+    pending: Option<ThermalFrame>,
+    /// Calibration of the camera that recorded the frames played back, used instead of the configured one
+    recorded_radiometry: Option<RadiometricCalibration>,
+    spots: SpotDetector,
+    tracker: SpotTracker,
+    /// Camera mapping the venue masks were rasterized with
+    mask_mapping: Option<CameraMapping>,
+}
+
+impl CameraFeed {
+    fn open(config: &CameraConfiguration) -> Self {
+        let mut source = match config.frame_source.open(config.input_resolution) {
+            Ok(source) => {
+                info!("Opened thermal frame source {:?}", config.frame_source);
+                Some(source)
+            }
+            Err(e) => {
+                error!("Failed to open thermal frame source {:?}: {}", config.frame_source, e);
+                None
+            }
+        };
+        let recorded_radiometry = source
+            .as_mut()
+            .and_then(|source| source.playback())
+            .map(|playback| playback.recording().header().radiometry);
+        let mut spots = SpotDetector::new(config.detection);
+        spots.calibration = recorded_radiometry.unwrap_or(config.radiometry);
+        Self {
+            source,
+            started: None,
+            pending: None,
+            recorded_radiometry,
+            spots,
+            tracker: SpotTracker::new(config.detection.tracking),
+            mask_mapping: None,
+        }
+    }
+
+    fn configure(&mut self, config: &CameraConfiguration) {
+        self.spots.config = config.detection;
+        self.spots.calibration = self.recorded_radiometry.unwrap_or(config.radiometry);
+        self.tracker.config = config.detection.tracking;
+    }
+
+    /// The next frame that is due at the given app time, if any
+    fn next_due_frame(&mut self, now: Duration) -> Option<ThermalFrame> {
+        let started = *self.started.get_or_insert(now);
+        let frame = match self.pending.take() {
+            Some(frame) => frame,
+            None => match self.source.as_mut()?.next_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    info!("Thermal frame source has no more frames");
+                    self.source = None;
+                    return None;
+                }
+                Err(e) => {
+                    error!("Failed to read thermal frame: {}", e);
+                    self.source = None;
+                    return None;
+                }
+            },
+        };
+
+        if frame.timestamp > now - started {
+            self.pending = Some(frame);
+            return None;
+        }
+        Some(frame)
+    }
+}
+
+/// Feeds of the main camera, first, and of the additional cameras
+#[derive(Resource, Default)]
+struct ThermalFeeds {
+    feeds: Vec<CameraFeed>,
+    /// Counts a hit seen by two overlapping cameras once
+    merger: HitMerger,
+}
+
+/// Plugin that turns laser heat spots on the thermal images into shots
+pub struct DetectionPlugin;
+
+impl Plugin for DetectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ThermalFeeds>()
+            .init_resource::<LatestFrame>()
+            .init_resource::<VenueConfiguration>()
+            .init_resource::<ShooterProfiles>()
+            .init_resource::<PlaybackStatus>()
+            .add_message::<ShotDetected>()
+            .add_message::<FrameCaptured>()
+            .add_message::<PlaybackCommand>()
+            .add_systems(Startup, open_frame_source.in_set(DetectionSystemSet))
+            .add_systems(Update, (update_additional_cameras, control_playback, detect_shots)
+                .chain()
+                .in_set(DetectionSystemSet)
+                .after(SceneSystemSet));
+    }
+}
+
+fn open_frame_source(mut feeds: ResMut<ThermalFeeds>, config: Res<CameraConfiguration>) {
+    feeds.feeds = vec![CameraFeed::open(&config)];
+}
+
+/// Open the frame sources of the cameras set up in the venue next to the main camera, and apply the
+/// lens and homography calibrated for the main camera
+fn update_additional_cameras(
+    venue: Res<VenueConfiguration>,
+    mut config: ResMut<CameraConfiguration>,
+    mut additional: ResMut<AdditionalCameras>,
+    mut feeds: ResMut<ThermalFeeds>,
+) {
+    if venue.is_changed() {
+        if config.lens != venue.lens {
+            config.lens = venue.lens;
+        }
+        let homography = venue.main_homography();
+        if homography.is_none() && venue.homography.is_some() {
+            warn!("Ignoring the camera homography, it was fitted through another lens");
+        }
+        if config.homography != homography {
+            config.homography = homography;
+        }
+        additional.0 = venue.cameras.iter().map(|camera| camera.configuration(&config)).collect();
+    }
+    if !additional.is_changed() {
+        return;
+    }
+    feeds.feeds.truncate(1);
+    feeds.feeds.extend(additional.0.iter().map(CameraFeed::open));
+    feeds.merger.reset();
+    if !additional.0.is_empty() {
+        info!("Watching the scene with {} thermal cameras", feeds.feeds.len());
+    }
+}
+
+/// Apply playback commands when the main frame source is a recording, and publish its state
+fn control_playback(
+    mut feeds: ResMut<ThermalFeeds>,
+    mut commands: MessageReader<PlaybackCommand>,
+    mut status: ResMut<PlaybackStatus>,
+) {
+    let Some(feed) = feeds.feeds.first_mut() else {
+        commands.clear();
+        return;
+    };
+    let Some(playback) = feed.source.as_mut().and_then(|source| source.playback()) else {
+        commands.clear();
+        status.set_if_neq(PlaybackStatus::default());
+        return;
+    };
+
+    let mut seeked = false;
+    for command in commands.read() {
+        match *command {
+            PlaybackCommand::Pause(paused) => playback.set_paused(paused),
+            PlaybackCommand::Seek(time) => {
+                playback.seek(time);
+                seeked = true;
+            }
+            PlaybackCommand::Speed(speed) => {
+                if let Err(e) = playback.set_speed(speed) {
+                    error!("Failed to change playback speed: {}", e);
+                }
+            }
+        }
+    }
+
+    status.set_if_neq(PlaybackStatus {
+        available: true,
+        paused: playback.is_paused(),
+        speed: playback.speed(),
+        position: playback.position(),
+        duration: playback.recording().duration(),
+    });
+
+    if seeked {
+        // Frames before and after the jump have nothing in common
+        feed.pending = None;
+        feed.spots.reset();
+        feed.tracker.reset();
+    }
+}
+
+/// Run the detectors on every frame that is due and report the shots found
+fn detect_shots(
+    time: Res<Time>,
+    mut feeds: ResMut<ThermalFeeds>,
+    mut latest: ResMut<LatestFrame>,
+    cameras: SceneCameras,
+    venue: Res<VenueConfiguration>,
+    shooters: Res<ShooterProfiles>,
+    lane: Res<TerminalLane>,
+    mut shots: MessageWriter<ShotDetected>,
+    mut captured: MessageWriter<FrameCaptured>,
+) {
+    let ThermalFeeds { feeds, merger } = &mut *feeds;
+    let configurations = cameras.configurations();
+
+    for (camera, ((feed, camera_config), mapping)) in feeds.iter_mut().zip(configurations).zip(cameras.mappings()).enumerate() {
+        if cameras.config.is_changed() || cameras.additional.is_changed() {
+            feed.configure(camera_config);
+        }
+        if venue.is_changed() || feed.mask_mapping != Some(mapping) {
+            feed.spots.mask = Some(PixelMask::new(&venue.masks, &mapping)).filter(|mask| mask.count() > 0);
+            feed.mask_mapping = Some(mapping);
+        }
+
+        while let Some(frame) = feed.next_due_frame(time.elapsed()) {
+            let spots = feed.spots.process(&frame);
+            for blob in feed.tracker.update(&spots, frame.timestamp) {
+                let position = mapping.to_scene(blob.centroid);
+                if !merger.accept(camera, position, time.elapsed()) {
+                    info!("Shot at x:{:.2} y:{:.2} from camera {} was already seen by another camera", position.x, position.y, camera);
+                    continue;
+                }
+                info!("Detected shot at x:{:.2} y:{:.2} from {:?}", position.x, position.y, blob);
+                let distance = cameras.scene_configuration.target_projection_distance;
+                let detected_at_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+                shots.write(ShotDetected { position: shooters.correct(lane.0, position, distance), raw: position, detected_at_ms });
+            }
+            captured.write(FrameCaptured { camera, frame: frame.clone(), radiometry: feed.spots.calibration });
+            if camera == 0 {
+                latest.frame = Some(frame);
+                latest.spots = spots;
+                latest.radiometry = feed.spots.calibration;
+            }
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use bevy::color::palettes::css::{LIME, ORANGE, SKY_BLUE};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::config::{CameraConfiguration, ProjectorConfiguration, SceneConfiguration};
+use common::layout::{convex_intersection, hatch, plan_layout, Frustum, LayoutInput, LayoutPlan};
+use common::lens::CameraOptics;
+use common::venue::VenueConfiguration;
+
+use crate::plugins::camera::{CameraSystemSet, CameraTag, DisplayMode, SetupView};
+use crate::plugins::scene::{SceneCameras, SceneClicks, SceneData, SceneTag};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "layout_planner";
+
+/// Spacing of the lines shading where coverage areas overlap, in meters
+const HATCH_SPACING: f32 = 0.2;
+/// Distance from a device on screen, in logical pixels, within which a press grabs it
+const GRAB_RADIUS: f32 = 20.0;
+/// Size of the boxes standing for the devices, in meters
+const DEVICE_SIZE: Vec3 = Vec3::new(0.3, 0.2, 0.4);
+
+/// A device placed in the venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Device {
+    /// Thermal camera by index, 0 for the main camera followed by the additional ones
+    Camera(usize),
+    Projector,
+}
+
+/// Device dragged across the setup view, with the world position it is dragged to
+#[derive(Resource, Default)]
+struct DeviceDrag {
+    device: Option<Device>,
+    position: Vec3,
+}
+
+/// The venue and devices entered in the layout planner
+#[derive(Resource, Default)]
+struct LayoutPlanner {
+    active: bool,
+    input: LayoutInput,
+}
+
+/// Plugin drawing the thermal cameras and the projector in the 3D view, letting them be dragged
+/// around in the setup view, and planning where to mount them in a venue
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DeviceDrag>()
+            .init_resource::<LayoutPlanner>()
+            .add_systems(Startup, register_planner_button)
+            .add_systems(Update, handle_planner_button)
+            .add_systems(Update, (drag_devices, draw_devices).chain().after(CameraSystemSet))
+            .add_systems(EguiPrimaryContextPass, planner_ui);
+    }
+}
+
+fn register_planner_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Layout planner".to_string(),
+        icon: Some("\u{f546}".to_string()), // Ruler icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_planner_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut planner: ResMut<LayoutPlanner>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            planner.active = !planner.active;
+            toolbar_registry.update_button_state(BTN_NAME, planner.active);
+        }
+    }
+}
+
+/// Frustum of every device, with where it covers the scene in scene-local meters
+fn device_frusta(
+    cameras: &SceneCameras,
+    projector_config: &ProjectorConfiguration,
+    scene: &Transform,
+    drag: &DeviceDrag,
+) -> Vec<(Device, Frustum, Option<[Vec2; 4]>)> {
+    let mut frusta: Vec<(Device, Frustum, Option<[Vec2; 4]>)> = cameras
+        .configurations()
+        .zip(cameras.mappings())
+        .enumerate()
+        .map(|(index, (config, mapping))| {
+            let frustum = match drag.device {
+                Some(Device::Camera(dragged)) if dragged == index => CameraConfiguration {
+                    transform: Transform::from_translation(drag.position),
+                    ..config.clone()
+                }
+                .frustum(&mapping, scene),
+                _ => config.frustum(&mapping, scene),
+            };
+            (Device::Camera(index), frustum, frustum.footprint(scene))
+        })
+        .collect();
+
+    // A calibrated projector covers what its mapping says, whatever its pose
+    let size = projector_config.output_resolution.as_vec2();
+    let footprint = projector_config.scene_mapping(scene).map(|mapping| {
+        [Vec2::ZERO, Vec2::new(size.x, 0.0), size, Vec2::new(0.0, size.y)].map(|corner| mapping.apply(corner))
+    });
+    frusta.push((Device::Projector, projector_config.frustum(), footprint));
+    frusta
+}
+
+fn draw_devices(
+    mut gizmos: Gizmos,
+    scene_query: Query<&GlobalTransform, With<SceneTag>>,
+    cameras: SceneCameras,
+    projector_config: Res<ProjectorConfiguration>,
+    display_mode: Res<DisplayMode>,
+    setup_view: Res<SetupView>,
+    drag: Res<DeviceDrag>,
+) {
+    if *display_mode != DisplayMode::Mode3D {
+        return;
+    }
+    let Ok(scene_transform) = scene_query.single() else {
+        return;
+    };
+    let scene = scene_transform.compute_transform();
+    let frusta = device_frusta(&cameras, &projector_config, &scene, &drag);
+
+    for (device, frustum, footprint) in &frusta {
+        let color = match device {
+            Device::Camera(_) => SKY_BLUE,
+            Device::Projector => ORANGE,
+        };
+        let faint = color.with_alpha(0.3);
+        // Seen through the main camera, its own frustum would surround the view
+        let seen_from = !setup_view.0 && *device == Device::Camera(0);
+        if !seen_from {
+            gizmos.cuboid(frustum.transform.with_scale(DEVICE_SIZE), color);
+        }
+        let Some(footprint) = footprint else {
+            continue;
+        };
+        let corners = footprint.map(|corner| scene.transform_point(corner.extend(0.0)));
+        if !seen_from {
+            for corner in corners {
+                gizmos.line(frustum.transform.translation, corner, faint);
+            }
+        }
+        if let Device::Camera(_) = device {
+            gizmos.linestrip(corners.into_iter().chain([corners[0]]), color);
+        }
+    }
+
+    // Shade where two cameras, or a camera and the projector, cover the same part of the scene
+    for (index, (device, _, footprint)) in frusta.iter().enumerate() {
+        for (other, _, other_footprint) in &frusta[index + 1..] {
+            let (Some(footprint), Some(other_footprint)) = (footprint, other_footprint) else {
+                continue;
+            };
+            let color = if *device == Device::Projector || *other == Device::Projector { LIME } else { SKY_BLUE };
+            let overlap = convex_intersection(footprint, other_footprint);
+            for (start, end) in hatch(&overlap, HATCH_SPACING) {
+                gizmos.line(
+                    scene.transform_point(start.extend(0.0)),
+                    scene.transform_point(end.extend(0.0)),
+                    color.with_alpha(0.4),
+                );
+            }
+        }
+    }
+}
+
+/// World position of a device
+fn device_position(device: Device, config: &CameraConfiguration, projector_config: &ProjectorConfiguration, venue: &VenueConfiguration) -> Vec3 {
+    match device {
+        Device::Camera(0) => config.transform.translation,
+        Device::Camera(index) => venue
+            .cameras
+            .get(index - 1)
+            .and_then(|camera| camera.position)
+            .map_or(config.transform.translation, Vec3::from),
+        Device::Projector => projector_config.transform.translation,
+    }
+}
+
+/// Grab a device in the setup view and move it across the horizontal plane at its height
+fn drag_devices(
+    clicks: SceneClicks,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<CameraTag>>,
+    scene_query: Query<&SceneData, With<SceneTag>>,
+    display_mode: Res<DisplayMode>,
+    setup_view: Res<SetupView>,
+    mut drag: ResMut<DeviceDrag>,
+    mut config: ResMut<CameraConfiguration>,
+    mut projector_config: ResMut<ProjectorConfiguration>,
+    mut venue: ResMut<VenueConfiguration>,
+    mut scene_configuration: ResMut<SceneConfiguration>,
+) {
+    if *display_mode != DisplayMode::Mode3D || !setup_view.0 || scene_query.is_empty() {
+        drag.device = None;
+        return;
+    }
+    let (Ok((camera, camera_transform)), Some(cursor)) = (camera_query.single(), window.cursor_position()) else {
+        return;
+    };
+
+    if clicks.just_pressed(MouseButton::Left) {
+        let devices = (0..=venue.cameras.len()).map(Device::Camera).chain([Device::Projector]);
+        drag.device = devices
+            .filter_map(|device| {
+                let position = device_position(device, &config, &projector_config, &venue);
+                let on_screen = camera.world_to_viewport(camera_transform, position).ok()?;
+                Some((device, on_screen.distance(cursor)))
+            })
+            .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(device, _)| device);
+        if let Some(device) = drag.device {
+            drag.position = device_position(device, &config, &projector_config, &venue);
+        }
+    }
+    let Some(device) = drag.device else {
+        return;
+    };
+
+    let height = drag.position.y;
+    if let Ok(ray) = camera.viewport_to_world(camera_transform, cursor)
+        && let Some(distance) = ray.intersect_plane(Vec3::Y * height, InfinitePlane3d::new(Vec3::Y))
+    {
+        drag.position = ray.get_point(distance).with_y(height);
+    }
+
+    match device {
+        Device::Camera(0) => {
+            if config.transform.translation != drag.position {
+                // The target distance is measured from the main camera, so the scene stays where it is
+                scene_configuration.target_projection_distance += drag.position.z - config.transform.translation.z;
+                config.transform.translation = drag.position;
+            }
+        }
+        Device::Projector => {
+            if projector_config.transform.translation != drag.position {
+                projector_config.transform.translation = drag.position;
+            }
+        }
+        // Moving an additional camera reopens its feed, so it is only placed once dropped
+        Device::Camera(index) => {
+            if mouse_button.just_released(MouseButton::Left)
+                && let Some(placement) = venue.cameras.get_mut(index - 1)
+            {
+                info!("Moved camera {} to {:?}", placement.name, drag.position);
+                placement.position = Some(drag.position.to_array());
+            }
+        }
+    }
+    if !mouse_button.pressed(MouseButton::Left) {
+        drag.device = None;
+    }
+}
+
+/// Planner input describing the devices and the scene as they are set up now
+fn current_setup(
+    input: LayoutInput,
+    config: &CameraConfiguration,
+    projector_config: &ProjectorConfiguration,
+    scene_data: &SceneData,
+) -> LayoutInput {
+    let camera_fov = match config.optics {
+        Some(optics) => optics.fov().x,
+        None => 2.0 * (scene_data.dimensions.x / 2.0 / scene_data.distance).atan().to_degrees(),
+    };
+    LayoutInput {
+        target_size: scene_data.dimensions,
+        camera_resolution: config.input_resolution,
+        camera_fov,
+        projector_resolution: projector_config.output_resolution,
+        projector_angle: projector_config.angle,
+        ..input
+    }
+}
+
+/// Mount the devices where the plan puts them, the target area being the scene
+fn apply_plan(
+    plan: &LayoutPlan,
+    input: &LayoutInput,
+    scene_center: Vec3,
+    config: &mut CameraConfiguration,
+    projector_config: &mut ProjectorConfiguration,
+    scene_configuration: &mut SceneConfiguration,
+) {
+    let to_world = |position: Vec3| scene_center + position - Vec3::Y * input.target_height;
+    config.transform.translation = to_world(plan.camera);
+    if let Some(optics) = config.optics {
+        config.optics = Some(CameraOptics::from_fov(input.camera_fov, optics.sensor_size));
+    }
+    // The camera is aimed at the middle of the scene, which stays where it is
+    scene_configuration.target_projection_distance = plan.camera.z;
+    scene_configuration.scene_width = plan.camera_coverage.x;
+    projector_config.transform = Transform::from_translation(to_world(plan.projector)).looking_at(scene_center, Vec3::Y);
+    projector_config.angle = input.projector_angle;
+    projector_config.output_resolution = input.projector_resolution;
+}
+
+fn drag_meters(ui: &mut egui::Ui, label: &str, values: &mut [&mut f32]) {
+    ui.horizontal(|ui| {
+        ui.add_sized([120.0, 0.0], egui::Label::new(label));
+        for value in values.iter_mut() {
+            ui.add(egui::DragValue::new(&mut **value).speed(0.05).range(0.0..=100.0).suffix(" m"));
+        }
+    });
+}
+
+fn planner_ui(
+    mut egui_context: EguiContexts,
+    mut planner: ResMut<LayoutPlanner>,
+    mut config: ResMut<CameraConfiguration>,
+    mut projector_config: ResMut<ProjectorConfiguration>,
+    mut scene_configuration: ResMut<SceneConfiguration>,
+    scene_query: Query<(&SceneData, &GlobalTransform), With<SceneTag>>,
+) {
+    if !planner.active {
+        return;
+    }
+    let (Ok(ctx), Ok((scene_data, scene_transform))) = (egui_context.ctx_mut(), scene_query.single()) else {
+        return;
+    };
+
+    let mut from_current = false;
+    let mut apply = false;
+    let plan = plan_layout(&planner.input);
+    egui::Window::new("Venue Layout Planner")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 120.0])
+        .show(ctx, |ui| {
+            let input = &mut planner.input;
+            ui.label("Distances from the middle of the target wall, heights from the floor.");
+            let room = &mut input.room;
+            drag_meters(ui, "Room w x h x d:", &mut [&mut room.x, &mut room.y, &mut room.z]);
+            drag_meters(ui, "Shooter across, out:", &mut [&mut input.shooter.x, &mut input.shooter.y]);
+            drag_meters(ui, "Shooter height:", &mut [&mut input.shooter_height]);
+            drag_meters(ui, "Target area w x h:", &mut [&mut input.target_size.x, &mut input.target_size.y]);
+            drag_meters(ui, "Target middle height:", &mut [&mut input.target_height]);
+            ui.horizontal(|ui| {
+                ui.add_sized([120.0, 0.0], egui::Label::new("Smallest target:"));
+                let mut millimeters = input.smallest_target * 1000.0;
+                if ui.add(egui::DragValue::new(&mut millimeters).speed(1.0).range(1.0..=2000.0).suffix(" mm")).changed() {
+                    input.smallest_target = millimeters / 1000.0;
+                }
+                ui.add(egui::DragValue::new(&mut input.min_pixels_per_target).speed(0.1).range(1.0..=20.0).suffix(" px needed"));
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add_sized([120.0, 0.0], egui::Label::new("Camera:"));
+                ui.add(egui::DragValue::new(&mut input.camera_resolution.x).range(16..=4096));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut input.camera_resolution.y).range(16..=4096));
+                ui.add(egui::DragValue::new(&mut input.camera_fov).speed(0.1).range(1.0..=170.0).suffix("°"));
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([120.0, 0.0], egui::Label::new("Projector:"));
+                ui.add(egui::DragValue::new(&mut input.projector_resolution.x).range(16..=8192));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut input.projector_resolution.y).range(16..=8192));
+                ui.add(egui::DragValue::new(&mut input.projector_angle).speed(0.1).range(1.0..=170.0).suffix("°"));
+            });
+            from_current = ui.button("From current setup").clicked();
+            ui.separator();
+
+            let place = |position: Vec3| format!("{:.2} m across, {:.2} m high, {:.2} m out", position.x, position.y, position.z);
+            ui.label(format!("Camera at {}", place(plan.camera)));
+            ui.label(format!(
+                "  sees {:.2} x {:.2} m, {:.1} mm per pixel, {:.1} px across the smallest target",
+                plan.camera_coverage.x,
+                plan.camera_coverage.y,
+                plan.camera_units_per_pixel * 1000.0,
+                input.smallest_target / plan.camera_units_per_pixel,
+            ));
+            ui.label(format!("Projector at {}", place(plan.projector)));
+            ui.label(format!(
+                "  covers {:.2} x {:.2} m, {:.1} mm per pixel",
+                plan.projector_coverage.x,
+                plan.projector_coverage.y,
+                plan.projector_units_per_pixel * 1000.0,
+            ));
+            ui.label(format!(
+                "Current setup: {:.1} mm per camera pixel",
+                scene_data.get_world_units_per_camera_input_pixel() * 1000.0,
+            ));
+            for warning in &plan.warnings {
+                ui.colored_label(egui::Color32::YELLOW, warning.to_string());
+            }
+            if plan.warnings.is_empty() {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, "The layout fits the room and resolves the smallest target");
+            }
+            apply = ui.button("Apply").clicked();
+        });
+
+    if from_current {
+        planner.input = current_setup(planner.input, &config, &projector_config, scene_data);
+    }
+    if apply {
+        info!("Mounted the devices as planned: camera at {}, projector at {}", plan.camera, plan.projector);
+        apply_plan(
+            &plan,
+            &planner.input,
+            scene_transform.translation(),
+            &mut config,
+            &mut projector_config,
+            &mut scene_configuration,
+        );
+    }
+}
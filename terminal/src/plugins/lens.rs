@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::config::CameraConfiguration;
+use common::lens::{estimate_lens, find_grid_points, CameraOptics, LensFit};
+use common::venue::VenueConfiguration;
+
+use crate::plugins::detection::LatestFrame;
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "lens";
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct LensSystemSet;
+
+/// Estimation of the camera lens distortion from a capture of a heated grid
+#[derive(Resource)]
+struct LensCalibration {
+    active: bool,
+    /// Number of grid points per row and per column
+    grid: UVec2,
+    /// Kelvin the grid points are warmer than the wall
+    min_rise: f32,
+    /// Focal length in pixels, the grid cannot tell it apart from the distortion
+    focal_length: Option<f32>,
+    /// Outcome of the last capture
+    fit: Option<Result<LensFit, String>>,
+}
+
+impl Default for LensCalibration {
+    fn default() -> Self {
+        Self {
+            active: false,
+            grid: UVec2::new(7, 5),
+            min_rise: 5.0,
+            focal_length: None,
+            fit: None,
+        }
+    }
+}
+
+pub struct LensPlugin;
+
+impl Plugin for LensPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LensCalibration>()
+            .add_systems(Startup, register_lens_button)
+            .add_systems(Update, handle_lens_button.in_set(LensSystemSet))
+            .add_systems(EguiPrimaryContextPass, lens_ui);
+    }
+}
+
+fn register_lens_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Lens calibration".to_string(),
+        icon: Some("\u{f030}".to_string()), // Camera icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_lens_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut calibration: ResMut<LensCalibration>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            calibration.active = !calibration.active;
+            toolbar_registry.update_button_state(BTN_NAME, calibration.active);
+        }
+    }
+}
+
+/// Fit the lens distortion to the grid points of the latest frame
+fn capture_grid(
+    calibration: &LensCalibration,
+    latest: &LatestFrame,
+    config: &CameraConfiguration,
+) -> Result<LensFit, String> {
+    let frame = latest.frame.as_ref().ok_or("no thermal frame received yet")?;
+    let points = find_grid_points(frame, &config.radiometry, calibration.min_rise, calibration.grid)
+        .map_err(|e| e.to_string())?;
+    let focal_length = calibration.focal_length.unwrap_or(frame.resolution.x as f32);
+    estimate_lens(&points, calibration.grid, Vec2::splat(focal_length), frame.resolution.as_vec2() / 2.0)
+        .map_err(|e| e.to_string())
+}
+
+fn lens_ui(
+    mut egui_context: EguiContexts,
+    mut calibration: ResMut<LensCalibration>,
+    mut config: ResMut<CameraConfiguration>,
+    mut venue: ResMut<VenueConfiguration>,
+    latest: Res<LatestFrame>,
+) {
+    if !calibration.active {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Lens Calibration")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 440.0])
+        .show(ctx, |ui| {
+            ui.label("Hang a heated grid flat on the wall, filling the camera image.");
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Grid points:"));
+                ui.add(egui::DragValue::new(&mut calibration.grid.x).range(3..=32));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut calibration.grid.y).range(3..=32));
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Grid warmer by:"));
+                ui.add(egui::DragValue::new(&mut calibration.min_rise).speed(0.1).range(0.5..=100.0).suffix(" K"));
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Focal length:"));
+                let mut focal_length = calibration.focal_length.unwrap_or(config.input_resolution.x as f32);
+                let drag = egui::DragValue::new(&mut focal_length).speed(1.0).range(10.0..=10000.0).suffix(" px");
+                if ui.add(drag).changed() {
+                    calibration.focal_length = Some(focal_length);
+                }
+            });
+
+            if ui.button("Capture grid").clicked() {
+                let fit = capture_grid(&calibration, &latest, &config);
+                match &fit {
+                    Ok(fit) => info!("Estimated lens distortion {:?}, rms error {:.3} px", fit.lens, fit.rms_error),
+                    Err(e) => error!("Failed to estimate lens distortion: {}", e),
+                }
+                calibration.fit = Some(fit);
+            }
+
+            match &calibration.fit {
+                Some(Ok(fit)) => {
+                    let [k1, k2, _] = fit.lens.radial;
+                    let [p1, p2] = fit.lens.tangential;
+                    ui.label(format!("k1 {:.4}  k2 {:.4}  p1 {:.5}  p2 {:.5}", k1, k2, p1, p2));
+                    ui.label(format!("Residual error {:.3} px", fit.rms_error));
+                    // Saved with the venue, which hands it to the camera configuration
+                    if ui.button("Apply").clicked() {
+                        venue.lens = Some(fit.lens);
+                    }
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e);
+                }
+                None => {}
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label(if venue.lens.is_some() { "Lens correction active" } else { "No lens correction" });
+                if ui.add_enabled(venue.lens.is_some(), egui::Button::new("Reset")).clicked() {
+                    venue.lens = None;
+                }
+            });
+            if let (Some(lens), Some(optics)) = (venue.lens, config.optics)
+                && ui.button("Use for field of view").clicked()
+            {
+                config.optics = Some(CameraOptics::from_intrinsics(&lens, config.input_resolution, optics.sensor_size));
+            }
+        });
+}
@@ -0,0 +1,249 @@
+use bevy::color::palettes::css::{ORANGE, YELLOW};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::masks::{ExclusionMask, HotSpotLearner, MaskOrigin};
+use common::radiometry::RadiometricCalibration;
+use common::venue::VenueConfiguration;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::plugins::detection::{DetectionSystemSet, FrameCaptured};
+use crate::plugins::scene::{SceneCameras, SceneClicks, SceneSystemSet, SceneTag};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "masks";
+
+/// Venue configuration file, relative to the working directory
+const VENUE_FILE: &str = "venue.json";
+
+/// Time the empty scene is watched to find its permanent hot spots
+const LEARN_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct MasksSystemSet;
+
+/// Polygon tool drawing exclusion masks on the scene
+#[derive(Resource, Default)]
+struct MaskEditor {
+    active: bool,
+    /// Scene-local vertices of the polygon being drawn
+    vertices: Vec<Vec2>,
+}
+
+/// Background learning in progress, one learner per camera, if any
+#[derive(Resource, Default)]
+struct BackgroundLearning {
+    learners: Vec<HotSpotLearner>,
+    /// Calibration of the frames each learner was fed, the recorded one when played back
+    radiometry: Vec<RadiometricCalibration>,
+    until: Duration,
+}
+
+pub struct MasksPlugin;
+
+impl Plugin for MasksPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MaskEditor>()
+            .init_resource::<BackgroundLearning>()
+            .add_systems(Startup, (register_masks_button, load_venue))
+            .add_systems(Update, (
+                handle_masks_button,
+                learn_background,
+                save_venue,
+                draw_masks,
+            ).chain().in_set(MasksSystemSet).after(SceneSystemSet).after(DetectionSystemSet))
+            .add_systems(EguiPrimaryContextPass, masks_ui);
+    }
+}
+
+fn register_masks_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Detection masks".to_string(),
+        icon: Some("\u{f05e}".to_string()), // Ban icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn load_venue(mut venue: ResMut<VenueConfiguration>) {
+    if !Path::new(VENUE_FILE).exists() {
+        return;
+    }
+    match VenueConfiguration::load(VENUE_FILE) {
+        Ok(loaded) => {
+            info!("Loaded venue configuration with {} masks from {}", loaded.masks.len(), VENUE_FILE);
+            *venue = loaded;
+        }
+        Err(e) => error!("Failed to load venue configuration from {}: {}", VENUE_FILE, e),
+    }
+}
+
+fn save_venue(venue: Res<VenueConfiguration>) {
+    if !venue.is_changed() || venue.is_added() {
+        return;
+    }
+    if let Err(e) = venue.save(VENUE_FILE) {
+        error!("Failed to save venue configuration to {}: {}", VENUE_FILE, e);
+    }
+}
+
+fn handle_masks_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut editor: ResMut<MaskEditor>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            editor.active = !editor.active;
+            editor.vertices.clear();
+            toolbar_registry.update_button_state(BTN_NAME, editor.active);
+        }
+    }
+}
+
+/// Feed captured frames to the learners and replace the learned masks once they are done
+fn learn_background(
+    time: Res<Time>,
+    mut learning: ResMut<BackgroundLearning>,
+    mut frames: MessageReader<FrameCaptured>,
+    mut venue: ResMut<VenueConfiguration>,
+    cameras: SceneCameras,
+) {
+    if learning.learners.is_empty() {
+        frames.clear();
+        return;
+    }
+    for FrameCaptured { camera, frame, radiometry } in frames.read() {
+        if let (Some(learner), Some(calibration)) = (learning.learners.get_mut(*camera), learning.radiometry.get_mut(*camera)) {
+            learner.add(frame);
+            *calibration = *radiometry;
+        }
+    }
+    if time.elapsed() < learning.until {
+        return;
+    }
+
+    let learners = std::mem::take(&mut learning.learners);
+    let mut masks = Vec::new();
+    let mappings = cameras.mappings();
+    let learned = learners.iter().zip(&learning.radiometry).zip(cameras.configurations()).zip(&mappings);
+    for (((learner, radiometry), camera), mapping) in learned {
+        masks.extend(learner.masks(camera.detection.threshold, radiometry, mapping));
+    }
+    // Number the hot spots of all cameras in one sequence
+    for (number, mask) in masks.iter_mut().enumerate() {
+        mask.name = format!("Hot spot {}", number + 1);
+    }
+    let frames: u32 = learners.iter().map(HotSpotLearner::frames).sum();
+    info!("Learned {} hot spots from {} frames", masks.len(), frames);
+    venue.replace_learned_masks(masks);
+}
+
+fn draw_masks(
+    mut gizmos: Gizmos,
+    editor: Res<MaskEditor>,
+    venue: Res<VenueConfiguration>,
+    scene_query: Query<&GlobalTransform, With<SceneTag>>,
+) {
+    if !editor.active {
+        return;
+    }
+    let Ok(scene_transform) = scene_query.single() else {
+        return;
+    };
+
+    let to_world = |vertex: Vec2| scene_transform.transform_point(vertex.extend(0.0));
+    for mask in &venue.masks {
+        let first = mask.vertices().next();
+        gizmos.linestrip(mask.vertices().chain(first).map(to_world), ORANGE);
+    }
+    gizmos.linestrip(editor.vertices.iter().copied().map(to_world), YELLOW);
+}
+
+/// Close the polygon being drawn into a new mask
+fn close_polygon(editor: &mut MaskEditor, venue: &mut VenueConfiguration) {
+    if editor.vertices.len() < 3 {
+        return;
+    }
+    let number = venue.masks.iter().filter(|mask| mask.origin == MaskOrigin::Drawn).count() + 1;
+    venue.masks.push(ExclusionMask::new(format!("Mask {}", number), &editor.vertices, MaskOrigin::Drawn));
+    editor.vertices.clear();
+}
+
+fn masks_ui(
+    mut egui_context: EguiContexts,
+    mut editor: ResMut<MaskEditor>,
+    mut venue: ResMut<VenueConfiguration>,
+    mut learning: ResMut<BackgroundLearning>,
+    time: Res<Time>,
+    cameras: SceneCameras,
+    clicks: SceneClicks,
+) {
+    if !editor.active {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    // Clicks outside of the panels edit the polygon
+    if !ctx.is_pointer_over_area() {
+        if let Some(position) = clicks.scene_position(MouseButton::Left) {
+            editor.vertices.push(position);
+        }
+        if clicks.just_pressed(MouseButton::Right) {
+            close_polygon(&mut editor, &mut venue);
+        }
+    }
+
+    let mut removed = None;
+    egui::Window::new("Detection Masks")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 200.0])
+        .show(ctx, |ui| {
+            ui.label("Click the scene to add vertices, right click to close the polygon.");
+            ui.horizontal(|ui| {
+                ui.label(format!("{} vertices", editor.vertices.len()));
+                if ui.add_enabled(editor.vertices.len() >= 3, egui::Button::new("Close polygon")).clicked() {
+                    close_polygon(&mut editor, &mut venue);
+                }
+                if ui.button("Discard").clicked() {
+                    editor.vertices.clear();
+                }
+            });
+            ui.separator();
+
+            for (index, mask) in venue.masks.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add_sized([140.0, 0.0], egui::Label::new(&mask.name));
+                    if mask.origin == MaskOrigin::Learned {
+                        ui.label("learned");
+                    }
+                    if ui.button("Delete").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            ui.separator();
+
+            if !learning.learners.is_empty() {
+                let left = learning.until.saturating_sub(time.elapsed());
+                ui.label(format!("Learning background, {:.0} s left...", left.as_secs_f32().ceil()));
+            } else if ui
+                .button("Learn background")
+                .on_hover_text("Watch the empty scene to mask its permanent hot spots")
+                .clicked()
+            {
+                learning.learners = cameras.configurations().map(|camera| HotSpotLearner::new(camera.input_resolution)).collect();
+                learning.radiometry = cameras.configurations().map(|camera| camera.radiometry).collect();
+                learning.until = time.elapsed() + LEARN_DURATION;
+            }
+        });
+
+    if let Some(index) = removed {
+        venue.masks.remove(index);
+    }
+}
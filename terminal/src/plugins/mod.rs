@@ -8,4 +8,12 @@ pub mod projector;
 pub mod settings;
 pub mod target;
 pub mod basictarget;
-pub mod networking;
\ No newline at end of file
+pub mod networking;
+pub mod detection;
+pub mod thermalview;
+pub mod recording;
+pub mod masks;
+pub mod lens;
+pub mod layout;
+pub mod validation;
+pub mod zeroing;
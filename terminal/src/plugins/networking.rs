@@ -2,22 +2,43 @@ use bevy::prelude::*;
 use bevy_quinnet::client::{
     QuinnetClientPlugin, QuinnetClient,
     certificate::CertificateVerificationMode,
-    connection::ClientAddrConfiguration,
+    connection::{ClientAddrConfiguration, ConnectionEvent},
     ClientConnectionConfiguration,
 };
-use common::network::{NetworkMessage, SERVER_PORT};
+use common::config::SceneConfiguration;
+use common::network::{LaneId, NetworkMessage, DEFAULT_LANE, SERVER_PORT};
+
+use crate::plugins::detection::{DetectionSystemSet, ShotDetected};
+use crate::plugins::recording::RecordingCommand;
+use crate::plugins::validation::Validation;
+use crate::plugins::zeroing::Zeroing;
 
 /// Plugin that handles networking with the server
-pub struct NetworkingPlugin;
+pub struct NetworkingPlugin {
+    /// Shooting lane this terminal serves
+    pub lane: LaneId,
+}
+
+impl Default for NetworkingPlugin {
+    fn default() -> Self {
+        Self { lane: DEFAULT_LANE }
+    }
+}
 
 impl Plugin for NetworkingPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(QuinnetClientPlugin::default())
+            .insert_resource(TerminalLane(self.lane))
             .add_systems(Startup, connect_to_server)
-            .add_systems(Update, handle_server_messages);
+            .add_systems(Update, (join_lane, handle_server_messages).chain())
+            .add_systems(Update, report_shots.after(DetectionSystemSet));
     }
 }
 
+/// Shooting lane this terminal is bound to
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TerminalLane(pub LaneId);
+
 /// Resource to store server connection info
 #[derive(Resource)]
 struct ServerAddress(String);
@@ -51,12 +72,70 @@ fn connect_to_server(mut client: ResMut<QuinnetClient>) {
     }
 }
 
+/// Bind the terminal to its lane whenever the connection is (re)established
+fn join_lane(
+    mut client: ResMut<QuinnetClient>,
+    mut connected: MessageReader<ConnectionEvent>,
+    lane: Res<TerminalLane>,
+) {
+    for _ in connected.read() {
+        let Some(connection) = client.get_connection_mut() else {
+            continue;
+        };
+        match connection.send_message(NetworkMessage::JoinLane { lane: lane.0 }) {
+            Ok(_) => info!("Joining lane {}", lane.0),
+            Err(e) => error!("Failed to join lane {}: {}", lane.0, e),
+        }
+    }
+}
+
+/// Send the shots detected on the thermal image to the server for scoring
+fn report_shots(
+    mut client: ResMut<QuinnetClient>,
+    mut shots: MessageReader<ShotDetected>,
+    lane: Res<TerminalLane>,
+    validation: Option<Res<Validation>>,
+    zeroing: Option<Res<Zeroing>>,
+) {
+    // Shots fired at the validation crosses or the zero target check the setup, they are not scored
+    if validation.is_some_and(|validation| validation.is_running())
+        || zeroing.is_some_and(|zeroing| zeroing.is_collecting())
+    {
+        shots.clear();
+        return;
+    }
+    let Some(connection) = client.get_connection_mut() else {
+        shots.clear();
+        return;
+    };
+    for shot in shots.read() {
+        let message = NetworkMessage::ShotReported {
+            lane: lane.0,
+            x: shot.position.x,
+            y: shot.position.y,
+            detected_at_ms: shot.detected_at_ms,
+        };
+        if let Err(e) = connection.send_message(message) {
+            error!("Failed to report shot: {}", e);
+        }
+    }
+}
+
 /// Handle incoming messages from the server
-fn handle_server_messages(mut client: ResMut<QuinnetClient>) {
+fn handle_server_messages(
+    mut client: ResMut<QuinnetClient>,
+    mut scene_configuration: ResMut<SceneConfiguration>,
+    lane: Res<TerminalLane>,
+    mut recording_commands: MessageWriter<RecordingCommand>,
+) {
     // Check connection status
     if let Some(connection) = client.get_connection_mut() {
         // Handle incoming messages
         while let Some(message) = connection.try_receive_message::<NetworkMessage>() {
+            // The server also sends the state of other lanes until the join is processed
+            if message.lane().is_some_and(|message_lane| message_lane != lane.0) {
+                continue;
+            }
             match message {
                 NetworkMessage::Ping { timestamp } => {
                     info!("Received ping from server at timestamp {}", timestamp);
@@ -72,6 +151,39 @@ fn handle_server_messages(mut client: ResMut<QuinnetClient>) {
                 NetworkMessage::Pong { timestamp } => {
                     info!("Received pong at timestamp {}", timestamp);
                 }
+                NetworkMessage::SceneConfiguration { configuration, .. } => {
+                    info!("Received scene configuration {:?}", configuration);
+                    if *scene_configuration != configuration {
+                        *scene_configuration = configuration;
+                    }
+                }
+                NetworkMessage::MatchStateChanged { phase, elapsed_secs, .. } => {
+                    info!("Match is {:?} at {:.1}s", phase, elapsed_secs);
+                }
+                NetworkMessage::TargetPlaced { target, .. } => {
+                    info!("Server placed target {:?}", target);
+                }
+                NetworkMessage::TargetRemoved { id, .. } => {
+                    info!("Server removed target {}", id);
+                }
+                NetworkMessage::ScoreUpdated { score, .. } => {
+                    info!("Score: {} hits of {} shots", score.hits, score.shots);
+                }
+                NetworkMessage::ShotRegistered { x, y, target_id, .. } => {
+                    info!("Shot at x:{:.2} y:{:.2} hit target {:?}", x, y, target_id);
+                }
+                NetworkMessage::RecordingChanged { recording: true, .. } => {
+                    recording_commands.write(RecordingCommand::Start);
+                }
+                NetworkMessage::RecordingChanged { recording: false, .. } => {
+                    recording_commands.write(RecordingCommand::Stop);
+                }
+                NetworkMessage::JoinLane { .. }
+                | NetworkMessage::ShotReported { .. }
+                | NetworkMessage::Operator { .. }
+                | NetworkMessage::OperatorReply(_) => {
+                    warn!("Ignoring client message sent to a terminal");
+                }
             }
         }
     }
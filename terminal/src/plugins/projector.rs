@@ -1,12 +1,23 @@
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
 use log::info;
 use crate::plugins::calibration::CalibrationSystemSet;
+use crate::plugins::camera::picture_extent;
+use crate::plugins::detection::{DetectionSystemSet, FrameCaptured};
 use crate::plugins::scene::{SceneData, SceneTag};
 use crate::plugins::toolbar::{ToolbarRegistry, ToolbarItem, Docking, ToolabarButton};
 use crate::plugins::instructions::InstructionState;
-use common::config::ProjectorConfiguration;
+use common::config::{CameraConfiguration, ProjectorConfiguration};
+use common::homography::{estimate_homography, Homography};
+use common::projector::{Keystone, PatternConfig, ProjectorCalibrator, ProjectorFit};
 
 const BTN_NAME: &str = "projector";
+const CALIBRATION_BTN_NAME: &str = "projector_calibration";
+
+/// Radius of the projected calibration dots, in output pixels
+const DOT_RADIUS: f32 = 12.0;
+/// Radius of the keystone corner handles, in logical pixels
+const KEYSTONE_HANDLE: f32 = 10.0;
 
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct ProjectorSystemSet;
@@ -14,6 +25,19 @@ pub struct ProjectorSystemSet;
 #[derive(Resource, Default)]
 pub struct ProjectorLockToScene(pub bool);
 
+/// Calibration of the projector output against the scene, automatic with a manual fallback
+#[derive(Resource, Default)]
+struct ProjectorCalibration {
+    active: bool,
+    pattern: PatternConfig,
+    /// Pattern being projected, if any
+    run: Option<ProjectorCalibrator>,
+    /// Outcome of the last run
+    fit: Option<Result<ProjectorFit, String>>,
+    /// Scene-local positions of the output corners clockwise from the top left, when adjusted by hand
+    corners: Option<[Vec2; 4]>,
+}
+
 
 pub struct ProjectorPlugin;
 
@@ -22,11 +46,15 @@ impl Plugin for ProjectorPlugin {
         app
             .insert_resource(ProjectorConfiguration::default())
             .insert_resource(ProjectorLockToScene(true))
+            .init_resource::<ProjectorCalibration>()
             .add_systems(Startup, (register_projector, register_projector_instructions).in_set(ProjectorSystemSet).after(CalibrationSystemSet))
             .add_systems(Update, (
                 handle_projector_button,
                 update_projector_system,
-            ).chain().in_set(ProjectorSystemSet).after(CalibrationSystemSet));
+                seed_keystone_from_mapping,
+                run_projector_calibration.after(DetectionSystemSet),
+            ).chain().in_set(ProjectorSystemSet).after(CalibrationSystemSet))
+            .add_systems(EguiPrimaryContextPass, projector_calibration_ui);
     }
 }
 
@@ -39,6 +67,14 @@ fn register_projector(mut toolbar: ResMut<ToolbarRegistry>) {
         docking: Docking::Left,
         button_size: 36.0,
     });
+    toolbar.register_button(ToolbarItem {
+        name: CALIBRATION_BTN_NAME.to_string(),
+        label: "Projector calibration".to_string(),
+        icon: Some("\u{f1de}".to_string()), // Sliders icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
 }
 
 fn register_projector_instructions(mut instructions: ResMut<InstructionState>) {
@@ -49,6 +85,7 @@ fn register_projector_instructions(mut instructions: ResMut<InstructionState>) {
 fn handle_projector_button(
     button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
     mut projector_config: ResMut<ProjectorConfiguration>,
+    mut calibration: ResMut<ProjectorCalibration>,
     mut toolbar_registry: ResMut<ToolbarRegistry>,
 ) {
     for (interaction, button) in &button_query {
@@ -56,6 +93,11 @@ fn handle_projector_button(
             projector_config.enabled = !projector_config.enabled;
             toolbar_registry.update_button_state(BTN_NAME, projector_config.enabled);
         }
+        if button.name == CALIBRATION_BTN_NAME && *interaction == Interaction::Pressed {
+            calibration.active = !calibration.active;
+            calibration.run = None;
+            toolbar_registry.update_button_state(CALIBRATION_BTN_NAME, calibration.active);
+        }
     }
 }
 
@@ -63,7 +105,7 @@ fn update_projector_system(
     mut projector_config: ResMut<ProjectorConfiguration>,
     mut lock_to_scene: ResMut<ProjectorLockToScene>,
     mut toolbar_registry: ResMut<ToolbarRegistry>,
-    scene_query: Query<&SceneData, With<SceneTag>>,
+    scene_query: Query<(&SceneData, &GlobalTransform), With<SceneTag>>,
     keyboard: Res<ButtonInput<KeyCode>>,
 ) {
     let prev_enabled = projector_config.enabled;
@@ -74,12 +116,42 @@ fn update_projector_system(
     }
     
     if lock_to_scene.0 {
-        if let Ok(scene_data) = scene_query.single() {
-            projector_config.angle = scene_data.calculate_projector_angle_for_scene_width();
+        // Widen the throw from the projector pose until it covers the whole scene
+        if let Ok((scene_data, scene_transform)) = scene_query.single()
+            && let Some(angle) = projector_config.frustum().angle_to_cover(&scene_transform.compute_transform(), scene_data.dimensions)
+            && angle != projector_config.angle
+        {
+            projector_config.angle = angle;
         }
     }
 }
 
+/// Seed the keystone whenever the mapping of the output onto the scene changes, calibrated or else
+/// given by the pose, so that the 2D picture throws every scene point onto its projector pixel
+fn seed_keystone_from_mapping(
+    mut projector_config: ResMut<ProjectorConfiguration>,
+    scene_query: Query<(&SceneData, &GlobalTransform), With<SceneTag>>,
+    mut seeded: Local<Option<(Homography, Vec2)>>,
+) {
+    let Ok((scene_data, scene_transform)) = scene_query.single() else {
+        return;
+    };
+    let Some(mapping) = projector_config.scene_mapping(&scene_transform.compute_transform()) else {
+        return;
+    };
+    let extent = picture_extent(scene_data.dimensions.x, scene_data.dimensions.y, scene_data.get_viewport_size());
+    // Corners dragged by hand are kept until the mapping changes again
+    if *seeded == Some((mapping, extent)) {
+        return;
+    }
+    *seeded = Some((mapping, extent));
+    if let Some(keystone) = Keystone::through(&mapping, projector_config.output_resolution, extent)
+        && keystone != projector_config.keystone
+    {
+        projector_config.keystone = keystone;
+    }
+}
+
 fn configure_projector(
     projector_config: &mut ResMut<ProjectorConfiguration>,
     lock_to_scene: &mut ResMut<ProjectorLockToScene>,
@@ -96,3 +168,244 @@ fn configure_projector(
     }
     
 }
+
+/// Feed the main camera frames to the calibration running, and solve the mapping once every dot was shown
+fn run_projector_calibration(
+    mut calibration: ResMut<ProjectorCalibration>,
+    mut captured: MessageReader<FrameCaptured>,
+) {
+    let Some(run) = calibration.run.as_mut() else {
+        captured.clear();
+        return;
+    };
+    for message in captured.read().filter(|message| message.camera == 0) {
+        run.update(&message.frame);
+    }
+    if !run.is_finished() {
+        return;
+    }
+
+    let fit = run.fit().map_err(|e| e.to_string());
+    match &fit {
+        Ok(fit) => info!(
+            "Projector calibration found {} of {} dots, rms error {:.3} m",
+            fit.detected, fit.projected, fit.rms_error
+        ),
+        Err(e) => error!("Projector calibration failed: {}", e),
+    }
+    calibration.fit = Some(fit);
+    calibration.run = None;
+}
+
+/// Output pixels of the corners of the projector output, clockwise from the top left
+fn output_corners(resolution: UVec2) -> [Vec2; 4] {
+    let size = resolution.as_vec2();
+    [Vec2::ZERO, Vec2::new(size.x, 0.0), size, Vec2::new(0.0, size.y)]
+}
+
+/// Corners of the projector output on the scene, from the last fit, the active mapping or the projector pose
+fn initial_corners(calibration: &ProjectorCalibration, config: &ProjectorConfiguration, scene: &Transform) -> [Vec2; 4] {
+    let homography = match &calibration.fit {
+        Some(Ok(fit)) => Some(fit.homography),
+        _ => config.scene_mapping(scene),
+    };
+    match homography {
+        Some(homography) => output_corners(config.output_resolution).map(|corner| homography.apply(corner)),
+        None => [Vec2::new(-1.0, 1.0), Vec2::ONE, Vec2::new(1.0, -1.0), Vec2::NEG_ONE],
+    }
+}
+
+/// Black out the viewport and draw the dot being calibrated, the terminal window being the projector output
+fn draw_calibration_dot(ctx: &egui::Context, scene_data: &SceneData, resolution: UVec2, dot: Vec2) {
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("projector_calibration_pattern")));
+    let origin = scene_data.get_viewport_scaled_position();
+    let size = scene_data.get_viewport_scaled_size();
+    painter.rect_filled(egui::Rect::from_min_size(egui::pos2(origin.x, origin.y), egui::vec2(size.x, size.y)), 0.0, egui::Color32::BLACK);
+
+    let scale = scene_data.get_viewport_size().as_vec2() / resolution.as_vec2();
+    let center = scene_data.translate_viewport_coordinates_to_window_coordinates(dot * scale);
+    let radius = DOT_RADIUS * scale.x / scene_data.scale_factor;
+    painter.circle_filled(egui::pos2(center.x, center.y), radius, egui::Color32::WHITE);
+}
+
+/// Let the corners of the keystone be dragged across the viewport, the terminal window being the projector output
+fn drag_keystone_corners(ctx: &egui::Context, scene_data: &SceneData, keystone: &mut Keystone) {
+    let size = scene_data.get_viewport_size().as_vec2();
+    for (index, corner) in keystone.corners.iter_mut().enumerate() {
+        let center = scene_data.translate_viewport_coordinates_to_window_coordinates(*corner * size);
+        egui::Area::new(egui::Id::new(("keystone_corner", index)))
+            .order(egui::Order::Foreground)
+            .fixed_pos(egui::pos2(center.x - KEYSTONE_HANDLE, center.y - KEYSTONE_HANDLE))
+            .show(ctx, |ui| {
+                let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(KEYSTONE_HANDLE * 2.0), egui::Sense::drag());
+                let color = if response.hovered() || response.dragged() { egui::Color32::YELLOW } else { egui::Color32::ORANGE };
+                ui.painter().circle_stroke(rect.center(), KEYSTONE_HANDLE - 1.0, egui::Stroke::new(2.0, color));
+                if response.dragged() {
+                    let delta = response.drag_delta();
+                    *corner = (*corner + Vec2::new(delta.x, delta.y) * scene_data.scale_factor / size).clamp(Vec2::ZERO, Vec2::ONE);
+                }
+            });
+    }
+}
+
+fn projector_calibration_ui(
+    mut egui_context: EguiContexts,
+    mut calibration: ResMut<ProjectorCalibration>,
+    mut projector_config: ResMut<ProjectorConfiguration>,
+    camera_config: Res<CameraConfiguration>,
+    scene_query: Query<(&SceneData, &GlobalTransform), With<SceneTag>>,
+) {
+    if !calibration.active {
+        return;
+    }
+    let (Ok(ctx), Ok((scene_data, scene_transform))) = (egui_context.ctx_mut(), scene_query.single()) else {
+        return;
+    };
+
+    if let Some(dot) = calibration.run.as_ref().and_then(|run| run.current_dot()) {
+        draw_calibration_dot(ctx, scene_data, projector_config.output_resolution, dot);
+    } else {
+        let mut keystone = projector_config.keystone;
+        drag_keystone_corners(ctx, scene_data, &mut keystone);
+        if keystone != projector_config.keystone {
+            projector_config.keystone = keystone;
+        }
+    }
+
+    let mut start = false;
+    let mut cancel = false;
+    let mut applied = None;
+    egui::Window::new("Projector Calibration")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 520.0])
+        .show(ctx, |ui| {
+            ui.label("Projects dots one at a time and finds where the camera sees them warm the wall.");
+            ui.add_enabled_ui(calibration.run.is_none(), |ui| {
+                let pattern = &mut calibration.pattern;
+                ui.horizontal(|ui| {
+                    ui.add_sized([100.0, 0.0], egui::Label::new("Dots:"));
+                    ui.add(egui::DragValue::new(&mut pattern.grid.x).range(2..=10));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut pattern.grid.y).range(2..=10));
+                });
+                ui.horizontal(|ui| {
+                    ui.add_sized([100.0, 0.0], egui::Label::new("Dwell:"));
+                    ui.add(egui::DragValue::new(&mut pattern.dwell_secs).speed(0.1).range(0.2..=10.0).suffix(" s"));
+                });
+                ui.horizontal(|ui| {
+                    ui.add_sized([100.0, 0.0], egui::Label::new("Dots warmer by:"));
+                    ui.add(egui::DragValue::new(&mut pattern.min_rise).speed(0.1).range(0.1..=20.0).suffix(" K"));
+                });
+            });
+
+            match &calibration.run {
+                Some(run) => {
+                    let progress = run.progress();
+                    ui.add(
+                        egui::ProgressBar::new(progress.measured as f32 / progress.projected as f32)
+                            .text(format!("Dot {} of {}, {} found", progress.measured + 1, progress.projected, progress.detected)),
+                    );
+                    cancel = ui.button("Cancel").clicked();
+                }
+                None => start = ui.button("Start").clicked(),
+            }
+            ui.separator();
+
+            match &calibration.fit {
+                Some(Ok(fit)) => {
+                    ui.label(format!("Found {} of {} dots", fit.detected, fit.projected));
+                    ui.label(format!("Error {:.0} mm rms, {:.0} mm max", fit.rms_error * 1000.0, fit.max_error * 1000.0));
+                    if fit.is_acceptable(&calibration.pattern) {
+                        ui.colored_label(egui::Color32::LIGHT_GREEN, "Good fit");
+                    } else {
+                        ui.colored_label(egui::Color32::YELLOW, "Poor fit, check the camera view or adjust the corners by hand");
+                    }
+                    if ui.button("Apply").clicked() {
+                        applied = Some(fit.homography);
+                    }
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e);
+                }
+                None => {}
+            }
+
+            ui.collapsing("Manual adjustment", |ui| {
+                let mut corners = calibration.corners.unwrap_or_else(|| initial_corners(&calibration, &projector_config, &scene_transform.compute_transform()));
+                for (corner, name) in corners.iter_mut().zip(["Top left", "Top right", "Bottom right", "Bottom left"]) {
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100.0, 0.0], egui::Label::new(name));
+                        ui.add(egui::DragValue::new(&mut corner.x).speed(0.01).prefix("x ").suffix(" m"));
+                        ui.add(egui::DragValue::new(&mut corner.y).speed(0.01).prefix("y ").suffix(" m"));
+                    });
+                }
+                calibration.corners = Some(corners);
+                ui.horizontal(|ui| {
+                    if ui.button("Apply corners").clicked() {
+                        let pairs: Vec<(Vec2, Vec2)> = output_corners(projector_config.output_resolution).into_iter().zip(corners).collect();
+                        match estimate_homography(&pairs) {
+                            Ok(fit) => applied = Some(fit.homography),
+                            Err(e) => error!("Failed to map the projector corners: {}", e),
+                        }
+                    }
+                    if ui.button("Revert").clicked() {
+                        calibration.corners = None;
+                    }
+                });
+            });
+
+            ui.collapsing("Keystone", |ui| {
+                ui.label("Follows the projector pose and calibration, drag the orange corners to fine-tune.");
+                let mut keystone = projector_config.keystone;
+                for (corner, name) in keystone.corners.iter_mut().zip(["Top left", "Top right", "Bottom right", "Bottom left"]) {
+                    ui.horizontal(|ui| {
+                        ui.add_sized([100.0, 0.0], egui::Label::new(name));
+                        ui.add(egui::DragValue::new(&mut corner.x).speed(0.001).range(0.0..=1.0).prefix("x "));
+                        ui.add(egui::DragValue::new(&mut corner.y).speed(0.001).range(0.0..=1.0).prefix("y "));
+                    });
+                }
+                ui.horizontal(|ui| {
+                    let squared = projector_config.mapping.and_then(|mapping| Keystone::square_up(&mapping, projector_config.output_resolution));
+                    if ui.add_enabled(squared.is_some(), egui::Button::new("Square up from calibration")).clicked() {
+                        keystone = squared.unwrap_or_default();
+                    }
+                    if ui.add_enabled(!keystone.is_identity(), egui::Button::new("Reset")).clicked() {
+                        keystone = Keystone::default();
+                    }
+                });
+                if keystone.homography().is_none() {
+                    ui.colored_label(egui::Color32::LIGHT_RED, "The corners do not form a quad");
+                }
+                if keystone != projector_config.keystone {
+                    projector_config.keystone = keystone;
+                }
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label(if projector_config.mapping.is_some() { "Projector mapping active" } else { "No projector mapping" });
+                if ui.add_enabled(projector_config.mapping.is_some(), egui::Button::new("Reset")).clicked() {
+                    projector_config.mapping = None;
+                }
+            });
+        });
+
+    if start {
+        calibration.fit = None;
+        calibration.run = Some(ProjectorCalibrator::new(
+            projector_config.output_resolution,
+            calibration.pattern,
+            scene_data.camera_mapping(),
+            camera_config.radiometry,
+        ));
+    }
+    if cancel {
+        calibration.run = None;
+    }
+    if let Some(homography) = applied {
+        info!("Applied projector mapping {:?}", homography);
+        projector_config.mapping = Some(homography);
+        calibration.corners = None;
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::config::CameraConfiguration;
+use common::recording::{FrameRecorder, RecordingHeader, RECORDING_EXTENSION};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::plugins::detection::{DetectionSystemSet, FrameCaptured, PlaybackCommand, PlaybackStatus};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "record";
+
+/// Directory recordings are written to, relative to the working directory
+const RECORDINGS_DIR: &str = "recordings";
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct RecordingSystemSet;
+
+/// Start or stop recording the thermal frames
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingCommand {
+    Start,
+    Stop,
+}
+
+/// Recording in progress, if any
+#[derive(Resource, Default)]
+struct ActiveRecording(Option<FrameRecorder>);
+
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveRecording>()
+            .add_message::<RecordingCommand>()
+            .add_systems(Startup, register_record_button)
+            .add_systems(Update, (
+                handle_record_button,
+                apply_recording_commands,
+                record_frames,
+            ).chain().in_set(RecordingSystemSet).after(DetectionSystemSet))
+            .add_systems(EguiPrimaryContextPass, playback_ui);
+    }
+}
+
+fn register_record_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Record thermal frames".to_string(),
+        icon: Some("\u{f03d}".to_string()), // Video camera icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_record_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    recording: Res<ActiveRecording>,
+    mut commands: MessageWriter<RecordingCommand>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            commands.write(if recording.0.is_some() { RecordingCommand::Stop } else { RecordingCommand::Start });
+        }
+    }
+}
+
+fn recording_path() -> PathBuf {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    PathBuf::from(RECORDINGS_DIR).join(format!("thermal-{}.{}", seconds, RECORDING_EXTENSION))
+}
+
+fn apply_recording_commands(
+    mut commands: MessageReader<RecordingCommand>,
+    mut recording: ResMut<ActiveRecording>,
+    config: Res<CameraConfiguration>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for command in commands.read() {
+        match command {
+            RecordingCommand::Start if recording.0.is_none() => {
+                let header = RecordingHeader {
+                    resolution: config.input_resolution,
+                    radiometry: config.radiometry,
+                };
+                let path = recording_path();
+                let recorder = std::fs::create_dir_all(RECORDINGS_DIR)
+                    .map_err(Into::into)
+                    .and_then(|_| FrameRecorder::create(&path, header));
+                match recorder {
+                    Ok(recorder) => {
+                        info!("Recording thermal frames to {}", path.display());
+                        recording.0 = Some(recorder);
+                    }
+                    Err(e) => error!("Failed to start recording to {}: {}", path.display(), e),
+                }
+            }
+            RecordingCommand::Stop => {
+                if let Some(recorder) = recording.0.take() {
+                    let frames = recorder.frames();
+                    match recorder.finish() {
+                        Ok(path) => info!("Recorded {} thermal frames to {}", frames, path.display()),
+                        Err(e) => error!("Failed to finish recording: {}", e),
+                    }
+                }
+            }
+            RecordingCommand::Start => {}
+        }
+        toolbar_registry.update_button_state(BTN_NAME, recording.0.is_some());
+    }
+}
+
+fn record_frames(mut frames: MessageReader<FrameCaptured>, mut recording: ResMut<ActiveRecording>) {
+    let Some(recorder) = recording.0.as_mut() else {
+        frames.clear();
+        return;
+    };
+    // Recordings hold the frames of the main camera
+    for FrameCaptured { frame, .. } in frames.read().filter(|captured| captured.camera == 0) {
+        if let Err(e) = recorder.write_frame(frame) {
+            error!("Failed to record thermal frame, stopping the recording: {}", e);
+            recording.0 = None;
+            return;
+        }
+    }
+}
+
+/// Pause, seek and speed controls shown while a recording plays back
+fn playback_ui(
+    mut egui_context: EguiContexts,
+    status: Res<PlaybackStatus>,
+    mut commands: MessageWriter<PlaybackCommand>,
+) {
+    if !status.available {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Playback")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 320.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let label = if status.paused { "Play" } else { "Pause" };
+                if ui.button(label).clicked() {
+                    commands.write(PlaybackCommand::Pause(!status.paused));
+                }
+                if ui.button("Restart").clicked() {
+                    commands.write(PlaybackCommand::Seek(Duration::ZERO));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Position:"));
+                let mut position = status.position.as_secs_f32();
+                let slider = egui::Slider::new(&mut position, 0.0..=status.duration.as_secs_f32()).suffix(" s");
+                if ui.add(slider).changed() {
+                    commands.write(PlaybackCommand::Seek(Duration::from_secs_f32(position)));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Speed:"));
+                let mut speed = status.speed;
+                let slider = egui::Slider::new(&mut speed, 0.1..=8.0).logarithmic(true).suffix("x");
+                if ui.add(slider).changed() {
+                    commands.write(PlaybackCommand::Speed(speed));
+                }
+            });
+        });
+}
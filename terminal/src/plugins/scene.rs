@@ -1,11 +1,17 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
-use common::config::CameraConfiguration;
+use common::config::{AdditionalCameras, CameraConfiguration};
 use common::config::ProjectorConfiguration;
 use common::config::SceneConfiguration;
+use common::detection::CameraMapping;
+use common::layout::nearest_plane_hit;
+use common::stitching::footprint_union;
+use common::venue::VenueConfiguration;
 use crate::plugins::camera::{CameraTag, ViewportMode};
 use crate::plugins::instructions::DebugInfoState;
 use crate::plugins::instructions::InstructionState;
+use crate::plugins::toolbar::ToolabarButton;
 use bevy::prelude::UVec2;
 use bevy::prelude::Vec2;
 
@@ -19,13 +25,40 @@ pub struct SceneTag;
 pub struct SceneSystemSet;
 pub struct ScenePlugin;
 
+/// Name of the plane of the scene the cameras and the projector are calibrated against
+pub const SCENE_PLANE: &str = "Scene";
+
+/// A named surface targets can be placed on, the z = 0 plane of its transform centered on its origin
+#[derive(Component, Debug, Clone)]
+pub struct ScenePlane {
+    pub name: String,
+    /// Width and height in world units
+    pub size: Vec2,
+}
+
+/// Where the mouse cursor points on the nearest plane
+#[derive(Debug, Clone)]
+pub struct PlaneHit {
+    pub plane: Entity,
+    pub name: String,
+    pub world: Vec3,
+    /// Position on the plane in its local coordinates
+    pub local: Vec2,
+}
+
+/// The plane under the mouse cursor, if any
+#[derive(Resource, Debug, Default)]
+pub struct PlaneCursor(pub Option<PlaneHit>);
+
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
        
         app.insert_resource(SceneConfiguration::default());
+        app.init_resource::<VenueConfiguration>();
+        app.init_resource::<PlaneCursor>();
         app.add_systems(Startup, setup_scene.in_set(SceneSystemSet));
-        app.add_systems(Update, update_scene.in_set(SceneSystemSet));  
+        app.add_systems(Update, (sync_target_planes, update_scene, draw_target_planes).chain().in_set(SceneSystemSet));  
     }
 }
 
@@ -43,32 +76,38 @@ pub struct SceneData{
     pub viewport_size: UVec2,
      /// The size of the camera resolution in pixels.   
     pub camera_resolution: UVec2,
+    /// Mapping of the main camera input pixels to scene-local meters.
+    pub main_camera: CameraMapping,
     /// The size of the projection resolution in pixels.   
     pub projection_resolution: UVec2,
     /// Most recent calculated world position of the mouse cursor intersection with the scene, if any.
+    /// None while another plane is in front of the scene under the cursor.
     pub mouse_world_pos: Option<Vec3>
 }
 
 impl SceneData {
 
+   /// Scene data for the cameras given by their mappings, the main camera first
    pub fn new(
         window_size: UVec2, 
-        camera_resolution: UVec2, 
+        cameras: &[CameraMapping], 
         projection_resolution: UVec2,
         distance: f32,
-        scene_width: f32,
         mouse_pos: Option<Vec3>, 
         scale_factor:f32,
         viewport_mode: ViewportMode,
         ) -> Self {
       
+        let dimensions = Self::get_scene_dimensions(cameras);
+        let main_camera = cameras.first().copied().unwrap_or_default();
         SceneData{
-            dimensions: Self::get_scene_dimensions(scene_width,camera_resolution),
+            dimensions,
             distance,
             window_size,
             scale_factor,
-            viewport_size: Self::calculate_viewport_size(window_size, camera_resolution, viewport_mode),
-            camera_resolution: camera_resolution,
+            viewport_size: Self::calculate_viewport_size(window_size, dimensions, viewport_mode),
+            camera_resolution: main_camera.resolution,
+            main_camera,
             mouse_world_pos: mouse_pos,
             projection_resolution: projection_resolution,
        }
@@ -125,15 +164,16 @@ impl SceneData {
    }
     
 
-   fn get_scene_dimensions(scene_width: f32, camera_input_size: UVec2) -> Vec2 {
-      let aspect_ratio = camera_input_size.y as f32 / camera_input_size.x as f32;
-      Vec2::new(scene_width, scene_width * aspect_ratio)
+   /// Size of the scene centered on its origin that holds the footprints of all cameras
+   fn get_scene_dimensions(cameras: &[CameraMapping]) -> Vec2 {
+      let union = footprint_union(cameras);
+      union.min.abs().max(union.max.abs()) * 2.0
    }
 
-   fn calculate_viewport_size(window_size: UVec2, camera_input_size: UVec2, viewport_mode: ViewportMode) -> UVec2 { 
+   fn calculate_viewport_size(window_size: UVec2, dimensions: Vec2, viewport_mode: ViewportMode) -> UVec2 { 
       
-      let hscale = window_size.x as f32 / camera_input_size.x as f32;
-      let vscale = window_size.y as f32 / camera_input_size.y as f32;
+      let hscale = window_size.x as f32 / dimensions.x;
+      let vscale = window_size.y as f32 / dimensions.y;
       
       let scale = match viewport_mode {
           ViewportMode::AspectFit => hscale.min(vscale),
@@ -141,8 +181,8 @@ impl SceneData {
       };
       
       return  UVec2::new(
-      (camera_input_size.x as f32 * scale).round() as u32, 
-      (camera_input_size.y as f32 * scale).round() as u32
+      (dimensions.x * scale).round() as u32, 
+      (dimensions.y * scale).round() as u32
       );
 
    }
@@ -155,30 +195,77 @@ impl SceneData {
    }
 
    pub fn get_world_units_per_camera_input_pixel(&self) -> f32 {
-      self.dimensions.x / self.camera_resolution.x as f32
+      self.main_camera.units_per_pixel
+   }
+
+   /// Mapping of main camera input pixels to scene-local meters for the current scene size
+   pub fn camera_mapping(&self) -> CameraMapping {
+      self.main_camera
    }
 
    pub fn get_world_units_per_viewport_pixel(&self) -> f32 {
       self.dimensions.x / self.viewport_size.x as f32
    }
+}
 
-   /// Calculates the projector angle (in degrees) needed to cover the scene width at the target distance.
-   /// Uses the formula: angle = 2 * atan(width / (2 * distance))
-   pub fn calculate_projector_angle_for_scene_width(&self) -> f32 {
-      let half_width = self.dimensions.x / 2.0;
-      let half_angle_rad = (half_width / self.distance).atan();
-      let full_angle_rad = 2.0 * half_angle_rad;
-      full_angle_rad.to_degrees()
-   }
+
+
+/// Mappings of the main camera followed by the additional cameras, for a scene of the given width
+pub fn camera_mappings(config: &CameraConfiguration, additional: &AdditionalCameras, scene_width: f32) -> Vec<CameraMapping> {
+    std::iter::once(config)
+        .chain(&additional.0)
+        .map(|camera| camera.mapping(scene_width))
+        .collect()
 }
 
+/// The thermal cameras watching the scene
+#[derive(SystemParam)]
+pub struct SceneCameras<'w> {
+    pub config: Res<'w, CameraConfiguration>,
+    pub additional: Res<'w, AdditionalCameras>,
+    pub scene_configuration: Res<'w, SceneConfiguration>,
+}
 
+impl SceneCameras<'_> {
+    /// Configuration of the main camera followed by the additional cameras
+    pub fn configurations(&self) -> impl Iterator<Item = &CameraConfiguration> {
+        std::iter::once(&*self.config).chain(&self.additional.0)
+    }
+
+    pub fn mappings(&self) -> Vec<CameraMapping> {
+        camera_mappings(&self.config, &self.additional, self.scene_configuration.scene_width)
+    }
+}
+
+/// Mouse clicks on the scene that do not hit the toolbar
+#[derive(SystemParam)]
+pub struct SceneClicks<'w, 's> {
+    mouse_button: Res<'w, ButtonInput<MouseButton>>,
+    scene_query: Query<'w, 's, (&'static SceneData, &'static GlobalTransform), With<SceneTag>>,
+    toolbar_query: Query<'w, 's, &'static Interaction, With<ToolabarButton>>,
+}
+
+impl SceneClicks<'_, '_> {
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_button.just_pressed(button)
+            && self.toolbar_query.iter().all(|interaction| *interaction == Interaction::None)
+    }
+
+    /// Scene-local position of a click of the given button on the scene
+    pub fn scene_position(&self, button: MouseButton) -> Option<Vec2> {
+        if !self.just_pressed(button) {
+            return None;
+        }
+        let (scene_data, scene_transform) = self.scene_query.single().ok()?;
+        let world_position = scene_data.mouse_world_pos?;
+        Some(scene_transform.affine().inverse().transform_point3(world_position).truncate())
+    }
+}
 
 fn setup_scene(
     mut commands: Commands, 
-    config: Res<CameraConfiguration>,
+    cameras: SceneCameras,
     projection_config: Res<ProjectorConfiguration>,
-    scene_configuration: Res<SceneConfiguration>,
     window: Single<&Window>,
     mut instruction_state: ResMut<InstructionState>,
     viewport_mode: Res<ViewportMode>,
@@ -188,12 +275,12 @@ fn setup_scene(
         instruction_state.instructions.push(INSTRUCTION_TEXT_B.to_string());
     
 
+        let scene_configuration = &cameras.scene_configuration;
         let scene_data = SceneData::new(
             window.physical_size(),
-            config.input_resolution,
+            &cameras.mappings(),
             projection_config.output_resolution,
             scene_configuration.target_projection_distance,
-            scene_configuration.scene_width,
             None,
             window.scale_factor(),
             *viewport_mode,
@@ -201,8 +288,9 @@ fn setup_scene(
 
         commands.spawn((
             SceneTag,
+            ScenePlane { name: SCENE_PLANE.to_string(), size: scene_data.dimensions },
             scene_data,
-            Transform::from_xyz(0.0, scene_data.dimensions.y / 2.0, -scene_configuration.target_projection_distance),
+            Transform::from_xyz(0.0, scene_data.dimensions.y / 2.0, cameras.config.transform.translation.z - scene_configuration.target_projection_distance),
             GlobalTransform::default(),
             Name::new("SceneTag"),
         ));
@@ -211,74 +299,144 @@ fn setup_scene(
 fn update_scene(
     camera_query: Query<(&Camera, &GlobalTransform), With<CameraTag>>,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    mut scene_query: Query<(&GlobalTransform, &mut Transform,&mut SceneData), With<SceneTag>>,
+    mut scene_query: Query<(Entity, &GlobalTransform, &mut Transform, &mut SceneData, &mut ScenePlane), With<SceneTag>>,
+    plane_query: Query<(Entity, &GlobalTransform, &ScenePlane), Without<SceneTag>>,
     mut config: ResMut<CameraConfiguration>,
+    additional: Res<AdditionalCameras>,
     mut scene_configuration: ResMut<SceneConfiguration>,
     projection_config: Res<ProjectorConfiguration>,
     keyboard: Res<ButtonInput<KeyCode>>,
     mut debug_info: ResMut<DebugInfoState>,
     viewport_mode: Res<ViewportMode>,
+    mut previous_scene: Local<Option<SceneConfiguration>>,
+    mut plane_cursor: ResMut<PlaneCursor>,
 ) {
 
     configure_scene(&mut config, &mut scene_configuration,&keyboard);
+    sync_scene_with_optics(&mut config, &mut scene_configuration, &mut previous_scene);
 
     if let Ok(window) = window_query.single()  {
 
 
         if let Ok((camera,camera_transform)) = camera_query.single()  {
 
-            let mut mouse_pos: Option<Vec3> = None;
-
             let cursor_ray = window
                 .cursor_position()
                 .and_then(|cursor_pos| camera.viewport_to_world(camera_transform, cursor_pos).ok());
 
 
-            for (scene_transform,mut transform,  mut scene_data) in scene_query.iter_mut() {
+            for (scene_entity, scene_transform, mut transform, mut scene_data, mut scene_plane) in scene_query.iter_mut() {
                 
-                transform.translation.z = -scene_configuration.target_projection_distance;
+                // The target distance is measured from the camera
+                transform.translation.z = config.transform.translation.z - scene_configuration.target_projection_distance;
                 transform.translation.y = scene_data.dimensions.y / 2.0;
 
-                if let Some(ray) = cursor_ray {
-                    let scene_position = scene_transform.translation();
-                    // The scene's plane is defined by its position and its forward vector (normal).
-                    let scene_plane = InfinitePlane3d::new(scene_transform.forward());
-
-                    // Find the intersection of the ray with the scene plane.
-                    if let Some(distance) = ray.intersect_plane(scene_position, scene_plane) {
-                        let intersection_point = ray.get_point(distance);
-                        let local_pos_3d = scene_transform.affine().inverse().transform_point(intersection_point);
-
-                        // Check if the intersection point is within the scene bounds
-                        if local_pos_3d.x.abs() <= scene_data.dimensions.x / 2.0
-                            && local_pos_3d.y.abs() <= scene_data.dimensions.y / 2.0
-                        {
-                            mouse_pos = Some(intersection_point);
-                        }
-                    }
-
-
-
-                }
+                // The nearest of the scene and the other planes is the one the cursor points at
+                let planes: Vec<(Entity, &str, &GlobalTransform, Vec2)> = std::iter::once((scene_entity, SCENE_PLANE, scene_transform, scene_data.dimensions))
+                    .chain(plane_query.iter().map(|(entity, plane_transform, plane)| (entity, plane.name.as_str(), plane_transform, plane.size)))
+                    .collect();
+                plane_cursor.0 = cursor_ray.and_then(|ray| {
+                    let (index, distance, local) = nearest_plane_hit(
+                        planes.iter().map(|(_, _, plane_transform, size)| (plane_transform.compute_transform(), *size)),
+                        ray.origin,
+                        *ray.direction,
+                    )?;
+                    let (plane, name, _, _) = planes[index];
+                    Some(PlaneHit { plane, name: name.to_string(), world: ray.get_point(distance), local })
+                });
+                let mouse_pos = plane_cursor.0.as_ref()
+                    .filter(|hit| hit.plane == scene_entity)
+                    .map(|hit| hit.world);
 
                 *scene_data = SceneData::new(
                     window.physical_size(),
-                    config.input_resolution,
+                    &camera_mappings(&config, &additional, scene_configuration.scene_width),
                     projection_config.output_resolution,    
                     scene_configuration.target_projection_distance,
-                    scene_configuration.scene_width,
                     mouse_pos,
                     window.scale_factor(),
                     *viewport_mode,
                 );
+                if scene_plane.size != scene_data.dimensions {
+                    scene_plane.size = scene_data.dimensions;
+                }
 
-                update_debug_info(&mut debug_info, &window, &config, &projection_config, &scene_data);
+                update_debug_info(&mut debug_info, &window, &config, &projection_config, &scene_data, &transform, &plane_cursor);
 
             }
         }
     }
 }
 
+/// Keep an entity for each of the target planes of the venue, matched by name so that the targets on them are kept
+fn sync_target_planes(
+    mut commands: Commands,
+    venue: Res<VenueConfiguration>,
+    mut plane_query: Query<(Entity, &mut Transform, &mut ScenePlane), Without<SceneTag>>,
+) {
+    if !venue.is_changed() {
+        return;
+    }
+
+    for (entity, mut transform, mut plane) in plane_query.iter_mut() {
+        match venue.planes.iter().find(|target_plane| target_plane.name == plane.name) {
+            Some(target_plane) => {
+                *transform = target_plane.transform();
+                plane.size = target_plane.size();
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    for target_plane in &venue.planes {
+        if !plane_query.iter().any(|(_, _, plane)| plane.name == target_plane.name) {
+            commands.spawn((
+                ScenePlane { name: target_plane.name.clone(), size: target_plane.size() },
+                target_plane.transform(),
+                Name::new(format!("ScenePlane {}", target_plane.name)),
+            ));
+        }
+    }
+}
+
+fn draw_target_planes(
+    mut gizmos: Gizmos,
+    plane_query: Query<(&GlobalTransform, &ScenePlane), Without<SceneTag>>,
+    plane_cursor: Res<PlaneCursor>,
+) {
+    for (plane_transform, plane) in plane_query.iter() {
+        let (_, rotation, translation) = plane_transform.to_scale_rotation_translation();
+        gizmos.rect(Isometry3d::new(translation, rotation), plane.size, Color::srgb(0.6, 0.6, 0.6));
+    }
+
+    if let Some(hit) = &plane_cursor.0 {
+        if let Ok((plane_transform, _)) = plane_query.get(hit.plane) {
+            let (_, rotation, _) = plane_transform.to_scale_rotation_translation();
+            gizmos.circle(Isometry3d::new(hit.world, rotation), 0.1, Color::srgb(1.0, 1.0, 0.0));
+        }
+    }
+}
+
+
+/// Keep the scene width and the camera field of view in agreement, whichever of them was changed
+fn sync_scene_with_optics(
+    config: &mut ResMut<CameraConfiguration>,
+    scene_configuration: &mut ResMut<SceneConfiguration>,
+    previous_scene: &mut Option<SceneConfiguration>,
+) {
+    if let Some(mut optics) = config.optics {
+        let mut scene = (**scene_configuration).clone();
+        let previous = previous_scene.clone().unwrap_or_else(|| scene.clone());
+        optics.sync_scene(&mut scene, &previous);
+        if config.optics != Some(optics) {
+            config.optics = Some(optics);
+        }
+        if **scene_configuration != scene {
+            **scene_configuration = scene;
+        }
+    }
+    *previous_scene = Some((**scene_configuration).clone());
+}
 
 fn configure_scene(config: &mut CameraConfiguration, scene_configuration: &mut SceneConfiguration, keyboard: &Res<ButtonInput<KeyCode>>){
             
@@ -300,7 +458,7 @@ fn configure_scene(config: &mut CameraConfiguration, scene_configuration: &mut S
 
 }
 
-fn update_debug_info(debug_info: &mut DebugInfoState, window: &Window, config: &CameraConfiguration, projector_config: &ProjectorConfiguration, scene_data: &SceneData){
+fn update_debug_info(debug_info: &mut DebugInfoState, window: &Window, config: &CameraConfiguration, projector_config: &ProjectorConfiguration, scene_data: &SceneData, scene: &Transform, plane_cursor: &PlaneCursor){
         
         let window_txt =  format!("Window size: {}x{} Camera input size: {}x{} Viewport size: {}x{} scale factor {}", 
             window.physical_size().x ,
@@ -331,11 +489,13 @@ fn update_debug_info(debug_info: &mut DebugInfoState, window: &Window, config: &
         
         debug_info.messages.push(ratio_txt);
 
-        // Calculate projector pixel to world ratio
-        let angle_rad = projector_config.angle.to_radians();
-        let half_angle = angle_rad / 2.0;
-        let projected_width = 2.0 * scene_data.distance * half_angle.tan();
-        let projector_pixel_to_world = projected_width / projector_config.output_resolution.x as f32;
+        // Projector pixel to world ratio across the middle of the output, as thrown from its pose
+        let frustum = projector_config.frustum();
+        let middle = projector_config.output_resolution.as_vec2() / 2.0;
+        let projector_pixel_to_world = match (frustum.to_scene(middle, scene), frustum.to_scene(middle + Vec2::X, scene)) {
+            (Some(a), Some(b)) => a.distance(b),
+            _ => f32::NAN,
+        };
         
         let projector_txt = format!("Projector angle: {:.2}° Resolution: {}x{} Pixel to world: {:.4}", 
             projector_config.angle,
@@ -349,6 +509,15 @@ fn update_debug_info(debug_info: &mut DebugInfoState, window: &Window, config: &
             let raypos = scene_data.mouse_world_pos.unwrap();
             let world_txt =  format!("Scene cursor pos: x:{:.2} y{:.2} z{:.2}", raypos.x ,raypos.y,raypos.z);
             debug_info.messages.push(world_txt);
+
+            let local_pos = scene.compute_affine().inverse().transform_point3(raypos).truncate();
+            if let Some(pixel) = frustum.to_pixel(local_pos, scene) {
+                debug_info.messages.push(format!("Projector pixel under cursor: x:{:.0} y:{:.0}", pixel.x, pixel.y));
+            }
+        }
+
+        if let Some(hit) = &plane_cursor.0 {
+            debug_info.messages.push(format!("Plane under cursor: {} x:{:.2} y:{:.2}", hit.name, hit.local.x, hit.local.y));
         }
 
        // let fps = 1.0 / time.delta_secs();
@@ -1,7 +1,9 @@
 
 use bevy_egui::EguiContexts;
 use bevy_egui::egui;
-use common::config::{SceneConfiguration, ProjectorConfiguration};
+use common::config::{CameraConfiguration, SceneConfiguration, ProjectorConfiguration};
+use common::lens::CameraOptics;
+use common::venue::{TargetPlane, VenueConfiguration};
 
 use crate::plugins::camera::DisplayMode;
 use crate::plugins::projector::ProjectorLockToScene;
@@ -64,9 +66,11 @@ pub fn overlay_ui_system(
     scene_query: Query<(&SceneData), With<SceneTag>>,
     overlay_visible: Res<OverlayVisible>,
     mut scene_configuration: ResMut<SceneConfiguration>,
+    mut camera_config: ResMut<CameraConfiguration>,
     mut display_mode: ResMut<DisplayMode>,
     mut projector_config: ResMut<ProjectorConfiguration>,
     mut lock_to_scene: ResMut<ProjectorLockToScene>,
+    mut venue: ResMut<VenueConfiguration>,
 ) {
     for scene_data in scene_query.iter() {
         if let Ok(ctx) = egui_context.ctx_mut() {
@@ -105,6 +109,20 @@ pub fn overlay_ui_system(
                                      
                             }
                         });
+                        ui.horizontal(|ui| {
+                            ui.add_sized([100.0, 0.0], egui::Label::new("Target width:"));
+                            let mut value = scene_configuration.scene_width;
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut value)
+                                        .range(0.5..=50.0)
+                                        .speed(0.1)
+                                        .suffix(" m"),
+                                )
+                                .changed() {
+                                    scene_configuration.scene_width = value;
+                            }
+                        });
                         ui.label("Camera Settings");
                         ui.horizontal(|ui| {
                             ui.add_sized([100.0, 0.0], egui::Label::new("Display mode:"));
@@ -124,6 +142,41 @@ pub fn overlay_ui_system(
                                 *display_mode = if mode == 0 { DisplayMode::Mode2D } else { DisplayMode::Mode3D };
                             }
                         });
+                        ui.horizontal(|ui| {
+                            ui.add_sized([150.0, 0.0], egui::Label::new("Physical optics:"));
+                            let mut enabled = camera_config.optics.is_some();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                // Start from a lens that sees the current scene, so nothing moves
+                                camera_config.optics = enabled.then(|| CameraOptics::default().for_scene_width(
+                                    scene_configuration.scene_width,
+                                    scene_configuration.target_projection_distance,
+                                ));
+                            }
+                        });
+                        if let Some(mut optics) = camera_config.optics {
+                            ui.horizontal(|ui| {
+                                ui.add_sized([100.0, 0.0], egui::Label::new("Focal length:"));
+                                ui.add(egui::DragValue::new(&mut optics.focal_length).range(0.5..=200.0).speed(0.05).suffix(" mm"));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add_sized([100.0, 0.0], egui::Label::new("Sensor size:"));
+                                ui.add(egui::DragValue::new(&mut optics.sensor_size.x).range(0.5..=50.0).speed(0.01).suffix(" mm"));
+                                ui.label("x");
+                                ui.add(egui::DragValue::new(&mut optics.sensor_size.y).range(0.5..=50.0).speed(0.01).suffix(" mm"));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add_sized([100.0, 0.0], egui::Label::new("Field of view:"));
+                                let fov = optics.fov();
+                                let mut horizontal = fov.x;
+                                if ui.add(egui::DragValue::new(&mut horizontal).range(1.0..=170.0).speed(0.1).suffix("°")).changed() {
+                                    optics = CameraOptics::from_fov(horizontal, optics.sensor_size);
+                                }
+                                ui.label(format!("x {:.1}°", fov.y));
+                            });
+                            if camera_config.optics != Some(optics) {
+                                camera_config.optics = Some(optics);
+                            }
+                        }
                         ui.separator();
                         
                         ui.label("Projector Settings");
@@ -151,6 +204,58 @@ pub fn overlay_ui_system(
                             });
                         });
                         ui.separator();
+
+                        egui::CollapsingHeader::new("Target planes").show(ui, |ui| {
+                            let mut planes = venue.planes.clone();
+                            let mut removed = None;
+                            for (index, plane) in planes.iter_mut().enumerate() {
+                                ui.push_id(index, |ui| {
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([100.0, 0.0], egui::Label::new("Name:"));
+                                        ui.text_edit_singleline(&mut plane.name);
+                                        if ui.button("Remove").clicked() {
+                                            removed = Some(index);
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([100.0, 0.0], egui::Label::new("Position:"));
+                                        for value in &mut plane.position {
+                                            ui.add(egui::DragValue::new(value).speed(0.05).suffix(" m"));
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([100.0, 0.0], egui::Label::new("Yaw/pitch/roll:"));
+                                        for value in &mut plane.rotation {
+                                            ui.add(egui::DragValue::new(value).range(-180.0..=180.0).speed(0.5).suffix("°"));
+                                        }
+                                    });
+                                    ui.horizontal(|ui| {
+                                        ui.add_sized([100.0, 0.0], egui::Label::new("Size:"));
+                                        ui.add(egui::DragValue::new(&mut plane.size[0]).range(0.1..=50.0).speed(0.05).suffix(" m"));
+                                        ui.label("x");
+                                        ui.add(egui::DragValue::new(&mut plane.size[1]).range(0.1..=50.0).speed(0.05).suffix(" m"));
+                                    });
+                                    ui.separator();
+                                });
+                            }
+                            if let Some(index) = removed {
+                                planes.remove(index);
+                            }
+                            if ui.button("Add plane").clicked() {
+                                // A floor halfway between the camera and the scene, as wide as the scene
+                                let distance = scene_configuration.target_projection_distance;
+                                planes.push(TargetPlane {
+                                    name: format!("Plane {}", planes.len() + 1),
+                                    position: [0.0, 0.0, camera_config.transform.translation.z - distance / 2.0],
+                                    rotation: [0.0, -90.0, 0.0],
+                                    size: [scene_configuration.scene_width, distance / 2.0],
+                                });
+                            }
+                            if venue.planes != planes {
+                                venue.planes = planes;
+                            }
+                        });
+                        ui.separator();
                     });
             }
         }
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use log::info;
 use crate::plugins::toolbar::{ToolabarButton, ToolbarRegistry, ToolbarItem, Docking};
-use crate::plugins::scene::{SceneData, SceneTag};
+use crate::plugins::scene::PlaneCursor;
 use crate::plugins::basictarget::BasicTarget;
 use common::path::PathRenderable;
 
@@ -47,7 +47,7 @@ fn handle_target_drag(
     mut commands: Commands,
     mut drag_state: ResMut<DragState>,
     button_query: Query<( &Interaction, &ToolabarButton)>,
-    scene_query: Query<(Entity, &SceneData, &GlobalTransform), With<SceneTag>>,
+    plane_cursor: Res<PlaneCursor>,
     mouse_button: Res<ButtonInput<MouseButton>>,
 ) {
 
@@ -68,20 +68,14 @@ fn handle_target_drag(
 
     // Check for drag end
     if drag_state.is_dragging && mouse_button.just_released(MouseButton::Left) {
-        info!("Drag ended, checking scene data...");
-        if let Ok((scene_entity, scene_data, scene_transform)) = scene_query.single() {
-            info!("Scene data found, mouse_world_pos: {:?}", scene_data.mouse_world_pos);
-            // Drag ended, spawn target at world position if mouse is over scene
-            if let Some(world_pos) = scene_data.mouse_world_pos {
-                // Convert world position to local position relative to scene
-                let local_pos = scene_transform.affine().inverse().transform_point3(world_pos);
-                spawn_target_circle(&mut commands, scene_entity, local_pos);
-                info!("Spawned target at local {:?}", local_pos);
-            } else {
-                info!("No mouse world position available");
-            }
+        info!("Drag ended, checking plane under cursor...");
+        // Drag ended, spawn target on the plane under the mouse, if any
+        if let Some(hit) = &plane_cursor.0 {
+            let local_pos = hit.local.extend(0.0);
+            spawn_target_circle(&mut commands, hit.plane, local_pos);
+            info!("Spawned target on {} at local {:?}", hit.name, local_pos);
         } else {
-            info!("Scene query failed");
+            info!("No plane under the mouse");
         }
         drag_state.is_dragging = false;
     }
@@ -89,11 +83,11 @@ fn handle_target_drag(
 
 fn spawn_target_circle(
     commands: &mut Commands,
-    scene_entity: Entity,
+    plane_entity: Entity,
     local_position: Vec3,
 ) {
-    // Spawn as child of scene entity with local position
-    commands.entity(scene_entity).with_children(|parent| {
+    // Spawn as child of the plane entity with local position
+    commands.entity(plane_entity).with_children(|parent| {
         parent.spawn((
             BasicTarget {
                 radius: 0.5,
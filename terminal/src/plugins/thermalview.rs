@@ -0,0 +1,275 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::color::palettes::css::{LIME, RED};
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::palette::{colorize, Palette, TemperatureRange};
+
+use crate::plugins::detection::{DetectionSystemSet, LatestFrame};
+use crate::plugins::scene::{SceneData, SceneSystemSet, SceneTag};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "thermal";
+
+/// Offset of the feed behind the scene plane, so target gizmos stay in front of it
+const FEED_DEPTH_OFFSET: f32 = 0.01;
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ThermalViewSystemSet;
+
+/// How the live thermal feed is shown in the scene viewport
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct ThermalViewSettings {
+    pub visible: bool,
+    pub palette: Palette,
+    pub range: TemperatureRange,
+    /// Opacity of the feed over the scene, between 0 and 1
+    pub opacity: f32,
+    /// Draw the spots found by the detector with their centroids
+    pub show_spots: bool,
+}
+
+impl Default for ThermalViewSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            palette: Palette::default(),
+            range: TemperatureRange::default(),
+            opacity: 0.8,
+            show_spots: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct ThermalFeedTag;
+
+/// Texture and material the latest frame is drawn with
+#[derive(Resource)]
+struct ThermalFeedTexture {
+    image: Handle<Image>,
+    material: Handle<StandardMaterial>,
+    resolution: UVec2,
+}
+
+pub struct ThermalViewPlugin;
+
+impl Plugin for ThermalViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ThermalViewSettings>()
+            .add_systems(Startup, register_thermal_button)
+            .add_systems(Update, (
+                handle_thermal_button,
+                spawn_thermal_feed,
+                update_thermal_feed,
+                draw_spots,
+            ).chain().in_set(ThermalViewSystemSet).after(SceneSystemSet).after(DetectionSystemSet))
+            .add_systems(EguiPrimaryContextPass, thermal_view_ui);
+    }
+}
+
+fn register_thermal_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Thermal feed".to_string(),
+        icon: Some("\u{f2c9}".to_string()), // Thermometer icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_thermal_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut settings: ResMut<ThermalViewSettings>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            settings.visible = !settings.visible;
+            toolbar_registry.update_button_state(BTN_NAME, settings.visible);
+        }
+    }
+}
+
+fn feed_image(resolution: UVec2) -> Image {
+    Image::new_fill(
+        Extent3d { width: resolution.x, height: resolution.y, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Attach a quad showing the feed to the scene once the scene exists
+fn spawn_thermal_feed(
+    mut commands: Commands,
+    feed_query: Query<(), With<ThermalFeedTag>>,
+    scene_query: Query<(Entity, &SceneData), With<SceneTag>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !feed_query.is_empty() {
+        return;
+    }
+    let Ok((scene_entity, scene_data)) = scene_query.single() else {
+        return;
+    };
+
+    let resolution = scene_data.camera_resolution;
+    let image = images.add(feed_image(resolution));
+    let material = materials.add(StandardMaterial {
+        base_color_texture: Some(image.clone()),
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+
+    commands.entity(scene_entity).with_children(|parent| {
+        parent.spawn((
+            ThermalFeedTag,
+            Mesh3d(meshes.add(Rectangle::new(1.0, 1.0))),
+            MeshMaterial3d(material.clone()),
+            Transform::from_xyz(0.0, 0.0, -FEED_DEPTH_OFFSET),
+            Visibility::Hidden,
+            Name::new("ThermalFeed"),
+        ));
+    });
+    commands.insert_resource(ThermalFeedTexture { image, material, resolution });
+}
+
+/// Upload the latest frame and keep the quad covering the part of the scene the main camera sees
+fn update_thermal_feed(
+    settings: Res<ThermalViewSettings>,
+    latest: Res<LatestFrame>,
+    texture: Option<ResMut<ThermalFeedTexture>>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut feed_query: Query<(&mut Transform, &mut Visibility), With<ThermalFeedTag>>,
+    scene_query: Query<&SceneData, With<SceneTag>>,
+) {
+    let (Some(mut texture), Ok((mut transform, mut visibility)), Ok(scene_data)) =
+        (texture, feed_query.single_mut(), scene_query.single())
+    else {
+        return;
+    };
+
+    let shown = settings.visible && latest.frame.is_some();
+    visibility.set_if_neq(if shown { Visibility::Inherited } else { Visibility::Hidden });
+    let footprint = scene_data.camera_mapping().footprint();
+    transform.scale = footprint.size().extend(1.0);
+    transform.translation = footprint.center().extend(-FEED_DEPTH_OFFSET);
+    if !shown {
+        return;
+    }
+
+    if settings.is_changed()
+        && let Some(material) = materials.get_mut(&texture.material)
+    {
+        material.base_color = Color::srgba(1.0, 1.0, 1.0, settings.opacity.clamp(0.0, 1.0));
+    }
+
+    if !latest.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let Some(frame) = latest.frame.as_ref() else {
+        return;
+    };
+    if frame.resolution != texture.resolution {
+        texture.resolution = frame.resolution;
+        if let Some(image) = images.get_mut(&texture.image) {
+            *image = feed_image(frame.resolution);
+        }
+    }
+    if let Some(image) = images.get_mut(&texture.image) {
+        image.data = Some(colorize(frame, settings.palette, settings.range, &latest.radiometry));
+    }
+}
+
+/// Outline the spots of the latest frame and mark their centroids
+fn draw_spots(
+    mut gizmos: Gizmos,
+    settings: Res<ThermalViewSettings>,
+    latest: Res<LatestFrame>,
+    scene_query: Query<(&GlobalTransform, &SceneData), With<SceneTag>>,
+) {
+    if !settings.visible || !settings.show_spots {
+        return;
+    }
+    let Ok((scene_transform, scene_data)) = scene_query.single() else {
+        return;
+    };
+
+    let mapping = scene_data.camera_mapping();
+    let rotation = scene_transform.rotation();
+    for spot in &latest.spots {
+        let center = scene_transform.transform_point(mapping.to_scene(spot.centroid).extend(0.0));
+        let radius = spot.size.max_element() as f32 * mapping.units_per_pixel / 2.0;
+        gizmos.circle(Isometry3d::new(center, rotation), radius.max(mapping.units_per_pixel), LIME);
+        gizmos.cross(Isometry3d::new(center, rotation), mapping.units_per_pixel, RED);
+    }
+}
+
+fn thermal_view_ui(
+    mut egui_context: EguiContexts,
+    mut settings: ResMut<ThermalViewSettings>,
+) {
+    if !settings.visible {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    let mut edited = *settings;
+    egui::Window::new("Thermal Feed")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 60.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Palette:"));
+                egui::ComboBox::from_id_salt("thermal_palette_combo")
+                    .selected_text(edited.palette.name())
+                    .show_ui(ui, |ui| {
+                        for palette in Palette::ALL {
+                            ui.selectable_value(&mut edited.palette, palette, palette.name());
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Opacity:"));
+                ui.add(egui::Slider::new(&mut edited.opacity, 0.0..=1.0));
+            });
+            ui.checkbox(&mut edited.show_spots, "Show detected spots");
+            ui.separator();
+
+            let mut auto = edited.range == TemperatureRange::Auto;
+            if ui.checkbox(&mut auto, "Automatic range").changed() {
+                edited.range = if auto {
+                    TemperatureRange::Auto
+                } else {
+                    TemperatureRange::Manual { min: 15.0, max: 40.0 }
+                };
+            }
+            if let TemperatureRange::Manual { min, max } = &mut edited.range {
+                ui.horizontal(|ui| {
+                    ui.add_sized([100.0, 0.0], egui::Label::new("Range:"));
+                    let max_celsius = *max;
+                    ui.add(egui::DragValue::new(min).speed(0.1).range(-40.0..=max_celsius).suffix(" °C"));
+                    let min_celsius = *min;
+                    ui.add(egui::DragValue::new(max).speed(0.1).range(min_celsius..=500.0).suffix(" °C"));
+                });
+            }
+        });
+
+    if edited.palette != settings.palette
+        || edited.opacity != settings.opacity
+        || edited.show_spots != settings.show_spots
+        || edited.range != settings.range
+    {
+        *settings = edited;
+    }
+}
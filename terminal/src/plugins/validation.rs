@@ -0,0 +1,306 @@
+use bevy::color::palettes::css::{DARK_GREY, LIME, RED, YELLOW};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::config::SceneConfiguration;
+use common::validation::{reference_grid, ValidationReport};
+use common::venue::VenueConfiguration;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::plugins::detection::{DetectionSystemSet, ShotDetected};
+use crate::plugins::scene::{SceneData, SceneTag};
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "validation";
+
+/// Directory exported reports are written to, relative to the working directory
+const REPORTS_DIR: &str = "reports";
+
+/// Size of the reference crosses on the scene in meters
+const CROSS_SIZE: f32 = 0.3;
+
+/// Width of the error plot in the window, in points
+const PLOT_WIDTH: f32 = 280.0;
+
+/// Shots fired at projected crosses to prove the accuracy of the calibration
+#[derive(Resource)]
+pub struct Validation {
+    active: bool,
+    columns: u32,
+    rows: u32,
+    /// Routine in progress, moved into the venue once every cross was fired at
+    running: Option<ValidationReport>,
+    /// Error vectors are drawn this many times longer than they are
+    exaggeration: f32,
+    /// Outcome of the last export
+    exported: Option<Result<PathBuf, String>>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self {
+            active: false,
+            columns: 3,
+            rows: 3,
+            running: None,
+            exaggeration: 10.0,
+            exported: None,
+        }
+    }
+}
+
+impl Validation {
+    /// Whether shots are taken as validation shots rather than game hits
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// The routine in progress, or else the latest one saved with the venue
+    fn report<'a>(&'a self, venue: &'a VenueConfiguration) -> Option<&'a ValidationReport> {
+        self.running.as_ref().or(venue.validation.as_ref())
+    }
+}
+
+pub struct ValidationPlugin;
+
+impl Plugin for ValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Validation>()
+            .add_systems(Startup, register_validation_button)
+            .add_systems(Update, (
+                handle_validation_button,
+                record_validation_shots.after(DetectionSystemSet),
+                draw_validation,
+            ))
+            .add_systems(EguiPrimaryContextPass, validation_ui);
+    }
+}
+
+fn register_validation_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Validate calibration".to_string(),
+        icon: Some("\u{f560}".to_string()), // Double check icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn handle_validation_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut validation: ResMut<Validation>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            validation.active = !validation.active;
+            // A routine hidden from view would keep taking the shots of the lane
+            if !validation.active && validation.running.take().is_some() {
+                info!("Cancelled the calibration validation");
+            }
+            toolbar_registry.update_button_state(BTN_NAME, validation.active);
+        }
+    }
+}
+
+/// Take each detected shot as the one fired at the next cross, and save the report once all were fired at
+fn record_validation_shots(
+    mut shots: MessageReader<ShotDetected>,
+    mut validation: ResMut<Validation>,
+    mut venue: ResMut<VenueConfiguration>,
+) {
+    let Some(report) = validation.running.as_mut() else {
+        shots.clear();
+        return;
+    };
+    // The calibration is validated by where the laser hit, whoever fires
+    for shot in shots.read() {
+        if let Some(index) = report.record(shot.raw) {
+            info!("Validation shot {} at x:{:.3} y:{:.3}", index + 1, shot.raw.x, shot.raw.y);
+        }
+    }
+    if report.is_complete() {
+        if let Some(rms) = report.rms_error() {
+            info!("Validation finished with {:.0} mm rms, {:.2} MOA", rms * 1000.0, report.moa(rms));
+        }
+        venue.validation = validation.running.take();
+    }
+}
+
+/// Show the crosses on the scene, the next one to fire at highlighted, with the error of each shot
+fn draw_validation(
+    mut gizmos: Gizmos,
+    validation: Res<Validation>,
+    venue: Res<VenueConfiguration>,
+    scene_query: Query<&GlobalTransform, With<SceneTag>>,
+) {
+    if !validation.active {
+        return;
+    }
+    let (Some(report), Ok(scene_transform)) = (validation.report(&venue), scene_query.single()) else {
+        return;
+    };
+
+    let rotation = scene_transform.rotation();
+    let to_world = |position: Vec2| scene_transform.transform_point(position.extend(0.0));
+    let next = validation.running.as_ref().and_then(ValidationReport::next_point);
+    for (index, point) in report.points.iter().enumerate() {
+        let reference = to_world(Vec2::from(point.reference));
+        let (size, color) = if Some(index) == next { (CROSS_SIZE * 1.5, YELLOW) } else { (CROSS_SIZE, DARK_GREY) };
+        gizmos.cross(Isometry3d::new(reference, rotation), size, color);
+        if let Some(detected) = point.detected {
+            let detected = to_world(Vec2::from(detected));
+            gizmos.circle(Isometry3d::new(detected, rotation), CROSS_SIZE * 0.1, LIME);
+            gizmos.line(reference, detected, RED);
+        }
+    }
+}
+
+fn export_path(report: &ValidationReport) -> PathBuf {
+    PathBuf::from(REPORTS_DIR).join(format!("validation-{}.csv", report.timestamp))
+}
+
+fn export(report: &ValidationReport) -> Result<PathBuf, String> {
+    let path = export_path(report);
+    std::fs::create_dir_all(REPORTS_DIR)
+        .and_then(|_| std::fs::write(&path, report.to_csv()))
+        .map(|_| path)
+        .map_err(|e| e.to_string())
+}
+
+/// Draw the error of each shot as an arrow from its cross, the scene scaled to fit the width of the plot
+fn error_plot(ui: &mut egui::Ui, report: &ValidationReport, dimensions: Vec2, exaggeration: f32) {
+    let scale = PLOT_WIDTH / dimensions.x.max(f32::EPSILON);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(PLOT_WIDTH, dimensions.y * scale), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::GRAY), egui::StrokeKind::Inside);
+    // Scene-local meters have y up, the plot y down
+    let to_plot = |position: Vec2| rect.center() + egui::vec2(position.x, -position.y) * scale;
+    for point in &report.points {
+        let reference = to_plot(Vec2::from(point.reference));
+        painter.line_segment([reference - egui::vec2(4.0, 0.0), reference + egui::vec2(4.0, 0.0)], egui::Stroke::new(1.0, egui::Color32::GRAY));
+        painter.line_segment([reference - egui::vec2(0.0, 4.0), reference + egui::vec2(0.0, 4.0)], egui::Stroke::new(1.0, egui::Color32::GRAY));
+        if let Some(error) = point.error() {
+            painter.arrow(reference, egui::vec2(error.x, -error.y) * scale * exaggeration, egui::Stroke::new(2.0, egui::Color32::LIGHT_RED));
+        }
+    }
+}
+
+fn validation_ui(
+    mut egui_context: EguiContexts,
+    mut validation: ResMut<Validation>,
+    venue: Res<VenueConfiguration>,
+    scene_configuration: Res<SceneConfiguration>,
+    scene_query: Query<&SceneData, With<SceneTag>>,
+) {
+    if !validation.active {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+    let Ok(scene_data) = scene_query.single() else {
+        return;
+    };
+
+    let mut start = false;
+    let mut cancel = false;
+    let mut export_clicked = false;
+    egui::Window::new("Calibration Validation")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 440.0])
+        .show(ctx, |ui| {
+            ui.label("Fire once at each projected cross, the highlighted one first.");
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Crosses:"));
+                ui.add_enabled_ui(validation.running.is_none(), |ui| {
+                    ui.add(egui::DragValue::new(&mut validation.columns).range(1..=10));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(&mut validation.rows).range(1..=10));
+                });
+            });
+            ui.horizontal(|ui| {
+                if validation.running.is_none() {
+                    start = ui.button("Start").clicked();
+                } else {
+                    cancel = ui.button("Cancel").clicked();
+                }
+            });
+            ui.separator();
+
+            let Some(report) = validation.report(&venue) else {
+                ui.label("No validation yet");
+                return;
+            };
+            match validation.running.as_ref().and_then(ValidationReport::next_point) {
+                Some(next) => ui.colored_label(egui::Color32::YELLOW, format!("Waiting for the shot at cross {} of {}", next + 1, report.points.len())),
+                None => ui.label(format!("Validated at {:.1} m", report.distance)),
+            };
+            for (index, point) in report.points.iter().enumerate() {
+                if let Some(error) = point.error() {
+                    ui.label(format!(
+                        "{}: {:.0} mm, {:.2} MOA ({:+.0}, {:+.0} mm)",
+                        index + 1,
+                        error.length() * 1000.0,
+                        report.moa(error.length()),
+                        error.x * 1000.0,
+                        error.y * 1000.0,
+                    ));
+                }
+            }
+            if let (Some(rms), Some(max)) = (report.rms_error(), report.max_error()) {
+                ui.label(format!(
+                    "RMS {:.0} mm, {:.2} MOA, max {:.0} mm, {:.2} MOA",
+                    rms * 1000.0,
+                    report.moa(rms),
+                    max * 1000.0,
+                    report.moa(max),
+                ));
+            }
+            if let Some(mean) = report.mean_error() {
+                ui.label(format!("Mean shift {:+.0}, {:+.0} mm", mean.x * 1000.0, mean.y * 1000.0));
+            }
+
+            let exaggeration = validation.exaggeration;
+            error_plot(ui, report, scene_data.dimensions, exaggeration);
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Errors drawn:"));
+                ui.add(egui::DragValue::new(&mut validation.exaggeration).range(1.0..=100.0).speed(0.5).prefix("x "));
+            });
+
+            if validation.running.is_none() {
+                export_clicked = ui.button("Export CSV").clicked();
+            }
+            match &validation.exported {
+                Some(Ok(path)) => {
+                    ui.colored_label(egui::Color32::LIGHT_GREEN, format!("Exported to {}", path.display()));
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, e);
+                }
+                None => {}
+            }
+        });
+
+    if start {
+        // The crosses cover the whole scene, seen from the target distance
+        let area = Rect::from_center_size(Vec2::ZERO, scene_data.dimensions);
+        let references = reference_grid(area, validation.columns, validation.rows);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        validation.running = Some(ValidationReport::new(&references, scene_configuration.target_projection_distance, timestamp));
+        validation.exported = None;
+    }
+    if cancel {
+        validation.running = None;
+    }
+    if export_clicked && let Some(report) = venue.validation.as_ref() {
+        let exported = export(report);
+        match &exported {
+            Ok(path) => info!("Exported the validation report to {}", path.display()),
+            Err(e) => error!("Failed to export the validation report: {}", e),
+        }
+        validation.exported = Some(exported);
+    }
+}
@@ -0,0 +1,302 @@
+use bevy::color::palettes::css::{LIME, ORANGE, YELLOW};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use common::config::SceneConfiguration;
+use common::shooter::{mean_point_of_impact, ShooterProfile, ShooterProfiles};
+use std::path::Path;
+
+use crate::plugins::detection::{DetectionSystemSet, ShotDetected};
+use crate::plugins::networking::TerminalLane;
+use crate::plugins::scene::SceneTag;
+use crate::plugins::toolbar::{Docking, ToolabarButton, ToolbarItem, ToolbarRegistry};
+
+const BTN_NAME: &str = "zeroing";
+
+/// Shooter profiles file, relative to the working directory
+const SHOOTERS_FILE: &str = "shooters.json";
+
+/// Radius of the zero target on the scene in meters
+const ZERO_TARGET_RADIUS: f32 = 0.15;
+
+/// Zeroing of the laser of the shooter on the lane by a group fired at the middle of the scene
+#[derive(Resource)]
+pub struct Zeroing {
+    active: bool,
+    /// Shots in a group
+    group_size: usize,
+    /// Raw hits of the group being fired, none when no group is being fired
+    group: Option<Vec<Vec2>>,
+    /// Name of the profile being added
+    new_profile: String,
+}
+
+impl Default for Zeroing {
+    fn default() -> Self {
+        Self {
+            active: false,
+            group_size: 5,
+            group: None,
+            new_profile: String::new(),
+        }
+    }
+}
+
+impl Zeroing {
+    /// Whether shots are taken into a zeroing group rather than scored
+    pub fn is_collecting(&self) -> bool {
+        self.group.is_some()
+    }
+}
+
+pub struct ZeroingPlugin;
+
+impl Plugin for ZeroingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Zeroing>()
+            .init_resource::<ShooterProfiles>()
+            .add_systems(Startup, (register_zeroing_button, load_shooters))
+            .add_systems(Update, (
+                handle_zeroing_button,
+                record_zeroing_shots,
+                save_shooters,
+                draw_zero_target,
+            ).chain().after(DetectionSystemSet))
+            .add_systems(EguiPrimaryContextPass, (shooter_hud, zeroing_ui));
+    }
+}
+
+fn register_zeroing_button(mut toolbar: ResMut<ToolbarRegistry>) {
+    toolbar.register_button(ToolbarItem {
+        name: BTN_NAME.to_string(),
+        label: "Laser zeroing".to_string(),
+        icon: Some("\u{f192}".to_string()), // Dot circle icon
+        is_active: false,
+        docking: Docking::Left,
+        button_size: 36.0,
+    });
+}
+
+fn load_shooters(mut shooters: ResMut<ShooterProfiles>) {
+    if !Path::new(SHOOTERS_FILE).exists() {
+        return;
+    }
+    match ShooterProfiles::load(SHOOTERS_FILE) {
+        Ok(loaded) => {
+            info!("Loaded {} shooter profiles from {}", loaded.profiles.len(), SHOOTERS_FILE);
+            *shooters = loaded;
+        }
+        Err(e) => error!("Failed to load shooter profiles from {}: {}", SHOOTERS_FILE, e),
+    }
+}
+
+fn save_shooters(shooters: Res<ShooterProfiles>) {
+    if !shooters.is_changed() || shooters.is_added() {
+        return;
+    }
+    if let Err(e) = shooters.save(SHOOTERS_FILE) {
+        error!("Failed to save shooter profiles to {}: {}", SHOOTERS_FILE, e);
+    }
+}
+
+fn handle_zeroing_button(
+    button_query: Query<(&Interaction, &ToolabarButton), Changed<Interaction>>,
+    mut zeroing: ResMut<Zeroing>,
+    mut toolbar_registry: ResMut<ToolbarRegistry>,
+) {
+    for (interaction, button) in &button_query {
+        if button.name == BTN_NAME && *interaction == Interaction::Pressed {
+            zeroing.active = !zeroing.active;
+            // A group hidden from view would keep taking the shots of the lane
+            if !zeroing.active && zeroing.group.take().is_some() {
+                info!("Cancelled the zeroing group");
+            }
+            toolbar_registry.update_button_state(BTN_NAME, zeroing.active);
+        }
+    }
+}
+
+/// Collect the raw hits of the group, and zero the active profile on their mean once it is complete
+fn record_zeroing_shots(
+    mut shots: MessageReader<ShotDetected>,
+    mut zeroing: ResMut<Zeroing>,
+    mut shooters: ResMut<ShooterProfiles>,
+    lane: Res<TerminalLane>,
+    scene_configuration: Res<SceneConfiguration>,
+) {
+    let group_size = zeroing.group_size;
+    let Some(group) = zeroing.group.as_mut() else {
+        shots.clear();
+        return;
+    };
+    // The previous zero must not take part in the new one
+    let missing = group_size.saturating_sub(group.len());
+    group.extend(shots.read().map(|shot| shot.raw).take(missing));
+    if group.len() < group_size {
+        return;
+    }
+
+    let distance = scene_configuration.target_projection_distance;
+    match shooters.active_mut(lane.0) {
+        Some(profile) => {
+            if let Some(zero) = profile.zero_on(group, Vec2::ZERO, distance) {
+                info!("Zeroed the laser of {} at {:.2}, {:.2} MOA from {:.1} m", profile.name, zero.x, zero.y, distance);
+            }
+        }
+        None => warn!("Dropped the zeroing group, no shooter is on lane {}", lane.0),
+    }
+    zeroing.group = None;
+}
+
+/// The zero target in the middle of the scene, with the hits of the group and their mean
+fn draw_zero_target(
+    mut gizmos: Gizmos,
+    zeroing: Res<Zeroing>,
+    scene_query: Query<&GlobalTransform, With<SceneTag>>,
+) {
+    if !zeroing.active {
+        return;
+    }
+    let (Some(group), Ok(scene_transform)) = (zeroing.group.as_ref(), scene_query.single()) else {
+        return;
+    };
+
+    let rotation = scene_transform.rotation();
+    let to_world = |position: Vec2| Isometry3d::new(scene_transform.transform_point(position.extend(0.0)), rotation);
+    gizmos.circle(to_world(Vec2::ZERO), ZERO_TARGET_RADIUS, YELLOW);
+    gizmos.cross(to_world(Vec2::ZERO), ZERO_TARGET_RADIUS * 2.0, YELLOW);
+    for hit in group {
+        gizmos.circle(to_world(*hit), ZERO_TARGET_RADIUS * 0.1, LIME);
+    }
+    if let Some(impact) = mean_point_of_impact(group) {
+        gizmos.cross(to_world(impact), ZERO_TARGET_RADIUS, ORANGE);
+    }
+}
+
+/// Keep the shooter whose zero corrects the shots in sight, as the server does not know who is on the lane
+fn shooter_hud(
+    mut egui_context: EguiContexts,
+    shooters: Res<ShooterProfiles>,
+    lane: Res<TerminalLane>,
+) {
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+    egui::Area::new(egui::Id::new("shooter_hud"))
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 8.0])
+        .interactable(false)
+        .show(ctx, |ui| match shooters.active(lane.0) {
+            Some(profile) => ui.label(
+                egui::RichText::new(format!(
+                    "Lane {} shooter: {}, zero {:+.2}, {:+.2} MOA",
+                    lane.0, profile.name, profile.zero[0], profile.zero[1]
+                ))
+                .strong(),
+            ),
+            None => ui.colored_label(egui::Color32::YELLOW, format!("Lane {}: no shooter, shots are not corrected", lane.0)),
+        });
+}
+
+fn zeroing_ui(
+    mut egui_context: EguiContexts,
+    mut zeroing: ResMut<Zeroing>,
+    mut shooters: ResMut<ShooterProfiles>,
+    lane: Res<TerminalLane>,
+    scene_configuration: Res<SceneConfiguration>,
+) {
+    if !zeroing.active {
+        return;
+    }
+    let Ok(ctx) = egui_context.ctx_mut() else {
+        return;
+    };
+
+    let distance = scene_configuration.target_projection_distance;
+    let mut edited = shooters.clone();
+    let mut active = edited.lanes.get(&lane.0).cloned();
+    egui::Window::new("Laser Zeroing")
+        .collapsible(true)
+        .resizable(false)
+        .default_pos([60.0, 560.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Shooter:"));
+                egui::ComboBox::from_id_salt("shooter_profile_combo")
+                    .selected_text(active.as_deref().unwrap_or("None"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut active, None, "None");
+                        for profile in &shooters.profiles {
+                            ui.selectable_value(&mut active, Some(profile.name.clone()), profile.name.as_str());
+                        }
+                    });
+                if ui.add_enabled(active.is_some(), egui::Button::new("Remove")).clicked()
+                    && let Some(name) = active.take()
+                {
+                    edited.profiles.retain(|profile| profile.name != name);
+                    // Nobody is on another lane as the removed shooter either
+                    edited.lanes.retain(|_, shooter| *shooter != name);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("New shooter:"));
+                ui.text_edit_singleline(&mut zeroing.new_profile);
+                let name = zeroing.new_profile.trim().to_string();
+                let unique = !name.is_empty() && edited.profiles.iter().all(|profile| profile.name != name);
+                if ui.add_enabled(unique, egui::Button::new("Add")).clicked() {
+                    edited.profiles.push(ShooterProfile::new(name.clone()));
+                    active = Some(name);
+                    zeroing.new_profile.clear();
+                }
+            });
+            edited.set_active(lane.0, active.clone());
+            ui.separator();
+
+            let Some(profile) = edited.active_mut(lane.0) else {
+                ui.label("Shots are not corrected without a shooter");
+                return;
+            };
+            let offset = profile.offset(distance);
+            ui.label(format!(
+                "Zero {:+.2}, {:+.2} MOA, {:+.0}, {:+.0} mm at {:.1} m",
+                profile.zero[0],
+                profile.zero[1],
+                offset.x * 1000.0,
+                offset.y * 1000.0,
+                distance,
+            ));
+            if ui.add_enabled(profile.zero != [0.0; 2], egui::Button::new("Clear zero")).clicked() {
+                profile.zero = [0.0; 2];
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.add_sized([100.0, 0.0], egui::Label::new("Group size:"));
+                ui.add_enabled(zeroing.group.is_none(), egui::DragValue::new(&mut zeroing.group_size).range(1..=20));
+            });
+            match zeroing.group.as_ref().map(Vec::len) {
+                None => {
+                    if ui.button("Start").clicked() {
+                        zeroing.group = Some(Vec::new());
+                    }
+                }
+                Some(fired) => {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Aim at the middle of the projected target, {} of {} shots fired", fired, zeroing.group_size),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        zeroing.group = None;
+                    }
+                }
+            }
+        });
+
+    if *shooters != edited {
+        // Another shooter takes over, the group fired so far was not theirs
+        if shooters.lanes.get(&lane.0) != edited.lanes.get(&lane.0)
+            && let Some(group) = zeroing.group.take()
+        {
+            warn!("Dropped the zeroing group of {} shots, another shooter is on lane {}", group.len(), lane.0);
+        }
+        *shooters = edited;
+    }
+}